@@ -0,0 +1,199 @@
+//! On-disk cache of publisher lookups made through the crates.io API.
+//!
+//! Complements the daily database dump: crates that are missing from the dump,
+//! or all crates if the dump is missing or expired, are looked up one by one,
+//! and the results are remembered here so that the next run doesn't have to repeat them.
+
+use crate::crates_cache::{prepare_cache_dir, CratesCache};
+use crate::publishers::{CrateData, OwnershipChange, PublisherData};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+pub struct ApiCache {
+    path: Option<PathBuf>,
+    entries: HashMap<String, CachedOwners>,
+    modified: bool,
+}
+
+/// Owners are a property of the crate as a whole rather than of any particular version,
+/// so the entries are keyed by crate name only.
+#[derive(Clone, Deserialize, Serialize)]
+struct CachedOwners {
+    #[serde(with = "humantime_serde")]
+    fetched: SystemTime,
+    users: Vec<PublisherData>,
+    teams: Vec<PublisherData>,
+    #[serde(default)]
+    yanked: Vec<String>,
+    #[serde(default)]
+    last_published: Option<String>,
+    /// Every change of publishers noticed since the crate was first looked up
+    #[serde(default)]
+    ownership_changes: Vec<OwnershipChange>,
+}
+
+impl ApiCache {
+    const API_CACHE_FS: &'static str = "api_responses.json";
+
+    /// The files this cache may keep in `dir`, whether or not they exist
+    pub(crate) fn files_in(dir: &Path) -> Vec<PathBuf> {
+        let path = dir.join(Self::API_CACHE_FS);
+        vec![path.with_extension("part"), path]
+    }
+
+    /// Loads the cache from disk. A missing or unreadable file results in an empty cache.
+    pub fn load() -> Self {
+        let path = CratesCache::cache_dir().map(|dir| dir.join(Self::API_CACHE_FS));
+        let entries = path
+            .as_ref()
+            .and_then(|path| {
+                log::debug!("Loading {}", path.display());
+                fs::read(path)
+                    .map_err(|e| log::debug!("Could not read {}: {}", path.display(), e))
+                    .ok()
+            })
+            .and_then(|contents| {
+                serde_json::from_slice(&contents)
+                    .map_err(|e| log::debug!("Ignoring the API cache, it is not valid: {}", e))
+                    .ok()
+            })
+            .unwrap_or_default();
+        ApiCache {
+            path,
+            entries,
+            modified: false,
+        }
+    }
+
+    /// Returns the data about the crate, unless it was fetched longer than `max_age` ago.
+    /// If `max_age` is `None`, entries of any age are returned, and if it's zero, none are.
+    pub fn get(&self, crate_name: &str, max_age: Option<Duration>) -> Option<CrateData> {
+        let entry = match self.entries.get(crate_name) {
+            Some(entry) => entry,
+            None => {
+                log::debug!("{}: not in the API cache", crate_name);
+                return None;
+            }
+        };
+        if let Some(max_age) = max_age {
+            // With a zero `max_age`, `age >= max_age` holds for every entry.
+            // A timestamp in the future means the clock has changed; consider it expired for safety
+            if entry.fetched.elapsed().map_or(true, |age| age >= max_age) {
+                log::debug!("{}: the API cache entry has expired", crate_name);
+                return None;
+            }
+        }
+        log::debug!("{}: found in the API cache", crate_name);
+        Some(CrateData {
+            users: entry.users.clone(),
+            teams: entry.teams.clone(),
+            yanked: entry.yanked.clone(),
+            last_published: entry.last_published.clone(),
+            ownership_changes: entry.ownership_changes.clone(),
+            // Only crates that were found are cached
+            published: true,
+        })
+    }
+
+    /// Remembers freshly fetched data about the crate. Publishers that differ from
+    /// the previous entry, regardless of its age, are recorded as changes and added to `data`.
+    pub fn insert(&mut self, crate_name: &str, data: &mut CrateData) {
+        let fetched = SystemTime::now();
+        if let Some(previous) = self.entries.get(crate_name) {
+            let time = humantime::format_rfc3339_seconds(fetched).to_string();
+            let mut changes = previous.ownership_changes.clone();
+            let before = previous.users.iter().chain(&previous.teams);
+            let after = data.users.iter().chain(&data.teams);
+            // IDs of users and teams come from different tables, so they may coincide
+            let same = |a: &PublisherData, b: &PublisherData| a.kind == b.kind && a.id == b.id;
+            for publisher in after
+                .clone()
+                .filter(|p| !before.clone().any(|b| same(b, p)))
+            {
+                changes.push(OwnershipChange::noticed(publisher, true, &time));
+            }
+            for publisher in before.filter(|p| !after.clone().any(|a| same(a, p))) {
+                changes.push(OwnershipChange::noticed(publisher, false, &time));
+            }
+            if changes.len() > previous.ownership_changes.len() {
+                log::debug!("{}: publishers changed since the last lookup", crate_name);
+            }
+            data.ownership_changes = changes;
+        }
+        let entry = CachedOwners {
+            fetched,
+            users: data.users.clone(),
+            teams: data.teams.clone(),
+            yanked: data.yanked.clone(),
+            last_published: data.last_published.clone(),
+            ownership_changes: data.ownership_changes.clone(),
+        };
+        self.entries.insert(crate_name.to_owned(), entry);
+        self.modified = true;
+    }
+
+    /// Writes the cache to disk if anything was inserted since it was loaded
+    pub fn save(&mut self) -> Result<(), Error> {
+        let path = match (&self.path, self.modified) {
+            (Some(path), true) => path,
+            _ => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            prepare_cache_dir(dir)?;
+        }
+        // Write to a temporary file first so that an interrupted write doesn't corrupt the cache
+        let part = path.with_extension("part");
+        let mut out = io::BufWriter::new(fs::File::create(&part)?);
+        serde_json::to_writer(&mut out, &self.entries)?;
+        out.flush()?;
+        fs::rename(part, path)?;
+        self.modified = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_with(crate_name: &str) -> ApiCache {
+        let mut cache = ApiCache {
+            path: None,
+            entries: HashMap::new(),
+            modified: false,
+        };
+        let mut data = CrateData {
+            users: Vec::new(),
+            teams: Vec::new(),
+            yanked: Vec::new(),
+            last_published: None,
+            ownership_changes: Vec::new(),
+            published: true,
+        };
+        cache.insert(crate_name, &mut data);
+        cache
+    }
+
+    #[test]
+    fn a_zero_max_age_expires_an_entry_fetched_a_moment_ago() {
+        let cache = cache_with("foo");
+        assert!(cache.get("foo", Some(Duration::ZERO)).is_none());
+        assert!(cache.get("foo", Some(Duration::from_secs(3600))).is_some());
+    }
+
+    #[test]
+    fn entries_of_any_age_are_used_without_a_max_age() {
+        let mut cache = cache_with("foo");
+        cache.entries.get_mut("foo").unwrap().fetched =
+            SystemTime::now() - Duration::from_secs(365 * 24 * 3600);
+        assert!(cache.get("foo", Some(Duration::from_secs(3600))).is_none());
+        assert!(cache.get("foo", None).is_some());
+    }
+}
@@ -1,15 +1,71 @@
-use std::time::{Duration, Instant};
+use crate::Error;
+use std::{
+    io,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
+/// Can be shared between threads. The rate limit applies to all of them combined.
 pub struct RateLimitedClient {
-    last_request_time: Option<Instant>,
+    /// The time at which the most recent request was allowed to be sent
+    last_request_time: Mutex<Option<Instant>>,
     agent: ureq::Agent,
+    request_timeout: Duration,
+    min_interval: Duration,
+    retries: u8,
+    initial_backoff: Duration,
+    offline: bool,
+    no_cache: bool,
+    allow_stale: bool,
+    allow_ownerless: bool,
+    keep_going: bool,
+    quiet: bool,
+    concurrency: usize,
+    registry_url: String,
+    dump_url: String,
+    /// Sent with API requests. Never print or log it.
+    token: Option<String>,
 }
 
+/// Identifies the tool to crates.io, as requested in <https://crates.io/data-access>
+const USER_AGENT: &str = concat!(
+    "cargo-supply-chain/",
+    env!("CARGO_PKG_VERSION"),
+    " (https://github.com/rust-secure-code/cargo-supply-chain)"
+);
+
+/// Long enough for a slow connection, short enough that a stalled one doesn't hang the whole run
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variable holding a crates.io API token, for crawlers granted a higher rate limit
+pub const TOKEN_ENV: &str = "CARGO_SUPPLY_CHAIN_TOKEN";
+
+/// What crates.io asks of unauthenticated crawlers, in requests per second
+pub const UNAUTHENTICATED_RATE_LIMIT: f64 = 1.0;
+
+pub const DEFAULT_REGISTRY_URL: &str = "https://crates.io";
+const DEFAULT_DUMP_URL: &str = "https://static.crates.io/db-dump.tar.gz";
+
 impl Default for RateLimitedClient {
     fn default() -> Self {
         RateLimitedClient {
-            last_request_time: None,
-            agent: ureq::agent(),
+            last_request_time: Mutex::new(None),
+            agent: agent_with_timeout(DEFAULT_REQUEST_TIMEOUT),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            min_interval: Duration::from_secs(1),
+            // Three attempts in all
+            retries: 2,
+            initial_backoff: Duration::from_secs(1),
+            offline: false,
+            no_cache: false,
+            allow_stale: false,
+            allow_ownerless: false,
+            keep_going: false,
+            quiet: false,
+            concurrency: 4,
+            registry_url: DEFAULT_REGISTRY_URL.to_owned(),
+            dump_url: DEFAULT_DUMP_URL.to_owned(),
+            token: token_from_env(),
         }
     }
 }
@@ -19,23 +75,355 @@ impl RateLimitedClient {
         RateLimitedClient::default()
     }
 
-    pub fn get(&mut self, url: &str) -> ureq::Request {
+    /// Sets how many times a failed request is retried by `get_with_retry()`,
+    /// and how long to wait before the first retry. The wait doubles on every subsequent attempt.
+    pub fn with_retries(mut self, retries: u8, initial_backoff: Duration) -> Self {
+        self.retries = retries;
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Limits the rate of outgoing requests. The default of 1 request per second
+    /// is what crates.io asks of crawlers; only raise it if you have been granted a higher limit,
+    /// along with a token to set in `CARGO_SUPPLY_CHAIN_TOKEN`.
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.min_interval = Duration::from_secs_f64(1.0 / requests_per_second);
+        self
+    }
+
+    /// Sets how long `get_with_retry()` waits for a response, including its body.
+    /// Requests made with `get()`, such as downloads of the daily dump, may take longer,
+    /// but fail if the connection is idle for this long.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = agent_with_timeout(timeout);
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Converts a failed request into an error, telling timeouts apart from other failures
+    pub(crate) fn request_error(&self, url: &str, error: ureq::Error) -> Error {
+        if is_timeout(&error) {
+            Error::TimedOut {
+                url: url.to_owned(),
+                timeout: self.request_timeout,
+                error: Box::new(error),
+            }
+        } else {
+            Error::Network(Box::new(error))
+        }
+    }
+
+    /// Makes `get_with_retry()` refuse to access the network
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Makes lookups through this client ignore the local cache and always fetch live data.
+    /// Fetched data is still saved to the cache for later runs.
+    pub fn with_no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+
+    /// Silences the warning about using a cached dump generated longer ago than the cache's maximum age
+    pub fn with_allow_stale(mut self, allow_stale: bool) -> Self {
+        self.allow_stale = allow_stale;
+        self
+    }
+
+    /// Silences the warning about published crates that have no owners left
+    pub fn with_allow_ownerless(mut self, allow_ownerless: bool) -> Self {
+        self.allow_ownerless = allow_ownerless;
+        self
+    }
+
+    /// Looks up the other crates when one fails to be looked up, rather than stopping there.
+    /// The failures are then recorded in `CrateOwners::lookup_errors`.
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Keeps the notices about retried requests off stderr. They are still logged.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Sets how many requests may be in flight at the same time.
+    /// They are still spaced out according to the rate limit.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sends requests to a mirror or a mock of crates.io instead of the real thing.
+    /// The API is expected under `{url}/api/v1/`, and the daily dump at `{url}/db-dump.tar.gz`.
+    pub fn with_registry_url(mut self, url: &str) -> Self {
+        let url = url.trim_end_matches('/');
+        self.registry_url = url.to_owned();
+        self.dump_url = format!("{}/db-dump.tar.gz", url);
+        self
+    }
+
+    /// Sets the crates.io API token sent to the registry, instead of the one in
+    /// `CARGO_SUPPLY_CHAIN_TOKEN`. `None` sends no token at all.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Returns the URL of an API endpoint, such as `crates/serde/owner_user`
+    pub fn api_url(&self, endpoint: &str) -> String {
+        format!("{}/api/v1/{}", self.registry_url, endpoint)
+    }
+
+    /// Whether `url` points at the same scheme, host and port as the registry
+    pub(crate) fn is_registry_url(&self, url: &str) -> bool {
+        origin(url).eq_ignore_ascii_case(origin(&self.registry_url))
+    }
+
+    /// Returns the URL of the daily database dump
+    pub fn dump_url(&self) -> &str {
+        &self.dump_url
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn ignores_cache(&self) -> bool {
+        self.no_cache
+    }
+
+    pub fn allows_stale(&self) -> bool {
+        self.allow_stale
+    }
+
+    pub fn allows_ownerless(&self) -> bool {
+        self.allow_ownerless
+    }
+
+    pub fn keeps_going(&self) -> bool {
+        self.keep_going
+    }
+
+    /// Whether API requests carry the token from `CARGO_SUPPLY_CHAIN_TOKEN`
+    pub fn is_authenticated(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// The minimum time between two consecutive requests
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    pub fn get(&self, url: &str) -> ureq::Request {
+        self.wait_to_honor_rate_limit();
+        log::debug!("GET {}", url);
+        self.agent.get(url).set("User-Agent", USER_AGENT)
+    }
+
+    pub fn head(&self, url: &str) -> ureq::Request {
         self.wait_to_honor_rate_limit();
-        self.agent.get(url).set(
-            "User-Agent",
-            "cargo supply-chain (https://github.com/rust-secure-code/cargo-supply-chain)",
-        )
-    }
-
-    /// Waits until at least 1 second has elapsed since last request,
-    /// as per https://crates.io/data-access
-    fn wait_to_honor_rate_limit(&mut self) {
-        if let Some(prev_req_time) = self.last_request_time {
-            let next_req_time = prev_req_time + Duration::from_secs(1);
-            if let Some(time_to_wait) = next_req_time.checked_duration_since(Instant::now()) {
-                std::thread::sleep(time_to_wait);
+        log::debug!("HEAD {}", url);
+        self.agent.head(url).set("User-Agent", USER_AGENT)
+    }
+
+    /// Performs a GET request, retrying with exponential backoff
+    /// on connection failures and server-side errors.
+    /// Honors the `Retry-After` header if the server sends one, but waits no longer
+    /// than the request timeout times the number of retries, so that a misbehaving server
+    /// can't stall the run for hours.
+    /// Returns the last error once all retries are exhausted.
+    /// HTTP status 404 is not retried.
+    pub fn get_with_retry(&self, url: &str) -> Result<ureq::Response, Error> {
+        if self.offline {
+            return Err(Error::Cache(format!(
+                "Refusing to fetch {:?} because of --offline",
+                url
+            )));
+        }
+        let mut wait = self.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            let mut request = self.get(url).timeout(self.request_timeout);
+            // The token is only meant for the registry, and must not leak to any other host
+            if let (Some(token), true) = (&self.token, self.is_registry_url(url)) {
+                request = request.set("Authorization", token);
+            }
+            let error = match request.call() {
+                Ok(response) => {
+                    log::trace!("{} responded with status {}", url, response.status());
+                    return Ok(response);
+                }
+                Err(error) => error,
+            };
+            log::debug!("{} failed: {}", url, error);
+            if let ureq::Error::Status(404, _) = error {
+                return Err(Error::Network(Box::new(error)));
+            }
+            if attempt >= self.retries || !is_transient(&error) {
+                return Err(self.request_error(url, error));
+            }
+            attempt += 1;
+            let delay = match retry_after(&error) {
+                Some(delay) => delay.min(self.request_timeout * u32::from(self.retries)),
+                None => wait,
+            };
+            let notice = format!(
+                "Failed retrieving {:?} ({}), trying again in {}, retry {}/{}",
+                url,
+                error_summary(&error, self.request_timeout),
+                humantime::format_duration(delay),
+                attempt,
+                self.retries
+            );
+            if self.quiet {
+                log::debug!("{}", notice);
+            } else {
+                eprintln!("{}", notice);
+            }
+            std::thread::sleep(delay);
+            wait *= 2;
+        }
+    }
+
+    /// Waits until at least `min_interval` (1 second by default) has elapsed since last request,
+    /// as per <https://crates.io/data-access>
+    fn wait_to_honor_rate_limit(&self) {
+        // Reserve the next free slot while holding the lock, but sleep without it
+        // so that concurrent callers can queue up behind us
+        let req_time = {
+            let mut last_request_time = self.last_request_time.lock().unwrap();
+            let now = Instant::now();
+            let req_time = match *last_request_time {
+                Some(prev_req_time) => (prev_req_time + self.min_interval).max(now),
+                None => now,
+            };
+            *last_request_time = Some(req_time);
+            req_time
+        };
+        if let Some(time_to_wait) = req_time.checked_duration_since(Instant::now()) {
+            std::thread::sleep(time_to_wait);
+        }
+    }
+}
+
+/// Connection errors, server errors and rate limiting are worth retrying;
+/// other client errors such as 404 will not go away on their own.
+fn is_transient(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::Status(code, _) => *code >= 500 || *code == 429,
+        ureq::Error::Transport(_) => true,
+    }
+}
+
+/// Parses the `Retry-After` header. Only the delay-seconds form is supported.
+fn retry_after(error: &ureq::Error) -> Option<Duration> {
+    match error {
+        ureq::Error::Status(_, response) => response
+            .header("retry-after")
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs),
+        ureq::Error::Transport(_) => None,
+    }
+}
+
+fn error_summary(error: &ureq::Error, timeout: Duration) -> String {
+    match error {
+        ureq::Error::Status(code, _) => format!("HTTP status {}", code),
+        _ if is_timeout(error) => {
+            format!("no response within {}", humantime::format_duration(timeout))
+        }
+        ureq::Error::Transport(_) => error.kind().to_string(),
+    }
+}
+
+/// Timeouts surface as I/O errors from the underlying socket
+fn is_timeout(error: &ureq::Error) -> bool {
+    let mut source = std::error::Error::source(error);
+    while let Some(inner) = source {
+        if let Some(io_error) = inner.downcast_ref::<io::Error>() {
+            if matches!(
+                io_error.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ) {
+                return true;
             }
         }
-        self.last_request_time = Some(Instant::now());
+        source = inner.source();
+    }
+    false
+}
+
+/// The scheme, host and port of `url`, such as `https://crates.io`
+pub(crate) fn origin(url: &str) -> &str {
+    let host_start = url.find("://").map_or(0, |scheme_end| scheme_end + 3);
+    let host_end = url[host_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| host_start + i);
+    &url[..host_end]
+}
+
+/// Returns the API token from the environment, if one is set
+pub fn token_from_env() -> Option<String> {
+    std::env::var(TOKEN_ENV)
+        .ok()
+        .map(|token| token.trim().to_owned())
+        .filter(|token| !token.is_empty())
+}
+
+fn agent_with_timeout(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .timeout_write(timeout)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn a_failing_request_is_attempted_three_times_by_default() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/api/v1/crates/foo",
+            listener.local_addr().unwrap()
+        );
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                stream
+                    .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+            }
+        });
+        let mut client = RateLimitedClient::new().with_rate_limit(1000.0);
+        client.initial_backoff = Duration::from_millis(1);
+        client.quiet = true;
+        assert!(client.get_with_retry(&url).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
     }
 }
@@ -1,53 +1,554 @@
-use crate::err_exit;
-use cargo_metadata::{CargoOpt::AllFeatures, MetadataCommand, Package, PackageId};
-use std::collections::HashMap;
+use crate::lockfile::metadata_from_lockfile;
+use crate::published::metadata_of_published_crate;
+use crate::publishers::CrateOwners;
+use crate::timing::{self, Phase};
+use crate::{Error, MetadataArgs};
+use cargo_metadata::{
+    CargoOpt, DependencyKind, Metadata, MetadataCommand, NodeDep, Package, PackageId,
+};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::{fs, io, path::Path, time::Duration};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+/// Where a package comes from, according to the `source` field of `cargo metadata`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum PkgSource {
-    Local,
     CratesIo,
+    /// A workspace member or a path dependency
+    Local,
+    /// A git repository
+    Git,
+    /// A registry other than crates.io
+    Registry,
+    /// A source Cargo may add in the future
     Foreign,
 }
+
+impl PkgSource {
+    /// Human-readable name of the source kind, as shown in the output
+    pub fn description(self) -> &'static str {
+        match self {
+            PkgSource::Local => "path",
+            PkgSource::CratesIo => "crates.io",
+            PkgSource::Git => "git",
+            PkgSource::Registry => "other registry",
+            PkgSource::Foreign => "other source",
+        }
+    }
+}
+
+/// Classifies a package by the `source` field of `cargo metadata`.
+/// Local packages have no source.
+fn classify_source(package: &Package) -> PkgSource {
+    let source = match &package.source {
+        Some(source) => source,
+        None => return PkgSource::Local,
+    };
+    if source.is_crates_io() || source.repr == "sparse+https://index.crates.io/" {
+        PkgSource::CratesIo
+    } else if source.repr.starts_with("git+") {
+        PkgSource::Git
+    } else if source.repr.starts_with("registry+") || source.repr.starts_with("sparse+") {
+        PkgSource::Registry
+    } else {
+        PkgSource::Foreign
+    }
+}
 #[derive(Debug, Clone)]
 pub struct SourcedPackage {
     pub source: PkgSource,
     pub package: Package,
 }
 
-pub fn sourced_dependencies(metadata_args: Vec<String>) -> Vec<SourcedPackage> {
-    let meta = match MetadataCommand::new()
-        .features(AllFeatures)
-        .other_options(metadata_args)
-        .exec()
-    {
-        Ok(v) => v,
-        Err(cargo_metadata::Error::CargoMetadata { stderr: e }) => err_exit(&e),
-        Err(err) => err_exit(format!("Failed to fetch crate metadata!\n  {}", err).as_str()),
+/// The `cargo metadata --format-version` we know how to interpret
+const METADATA_FORMAT_VERSION: u64 = 1;
+
+pub fn sourced_dependencies(metadata_args: &MetadataArgs) -> Result<Vec<SourcedPackage>, Error> {
+    Ok(DependencyGraph::load(metadata_args)?.packages)
+}
+
+/// The dependency graph of a single invocation. It's loaded once, and everything that needs it
+/// borrows it from here, since running `cargo metadata` on a large workspace takes a while.
+pub(crate) struct DependencyGraph {
+    /// Everything reported by `cargo metadata`, before any filtering
+    pub metadata: Metadata,
+    /// The packages that passed the filters, classified by source
+    pub packages: Vec<SourcedPackage>,
+    /// Names of the crates left out because they matched an `--exclude` pattern
+    pub excluded: BTreeSet<String>,
+    /// Names of the crates left out because their license didn't satisfy the `--license` patterns
+    pub license_mismatches: BTreeSet<String>,
+    /// Names of the crates left out because they are in none of the `--category` categories
+    pub category_mismatches: BTreeSet<String>,
+    /// The kinds of dependency edges followed
+    kinds: DependencyKinds,
+    /// Computed before `--min-dependents` leaves crates out, so that the counts don't change
+    dependents_before_filtering: Option<BTreeMap<String, usize>>,
+    /// Packages only pulled in by optional dependencies that no selected feature activates,
+    /// present if `--include-inactive-optional` included them
+    inactive_optional: HashSet<PackageId>,
+}
+
+impl DependencyGraph {
+    /// Runs `cargo metadata` or reads its equivalent, and applies the filters from `metadata_args`.
+    /// Fails with `EmptyGraph` if no crates.io crates are left to analyze, which usually means
+    /// that the wrong manifest was given, rather than that there is nothing to worry about.
+    pub fn load(metadata_args: &MetadataArgs) -> Result<Self, Error> {
+        let graph = Self::load_possibly_empty(metadata_args)?;
+        if !graph.has_crates_io_crates() {
+            return Err(graph.nothing_to_analyze(metadata_args));
+        }
+        Ok(graph)
+    }
+
+    /// Like `load`, for callers that combine several graphs, some of which may be empty
+    pub fn load_possibly_empty(metadata_args: &MetadataArgs) -> Result<Self, Error> {
+        let mut metadata = load_metadata(metadata_args)?;
+        let mut inactive_optional = HashSet::new();
+        if metadata_args.include_inactive_optional {
+            let kinds = DependencyKinds::of(metadata_args);
+            let active = resolved_packages(&metadata, kinds);
+            // Features are additive, so this is a superset of the graph with the selected ones
+            let all_features = MetadataArgs {
+                all_features: true,
+                no_default_features: false,
+                features: None,
+                include_inactive_optional: false,
+                ..metadata_args.clone()
+            };
+            merge_metadata(&mut metadata, load_metadata(&all_features)?);
+            inactive_optional = resolved_packages(&metadata, kinds)
+                .into_iter()
+                .filter(|id| !active.contains(id))
+                .collect();
+        }
+        let mut packages = sourced_packages(&metadata, metadata_args)?;
+        let patterns = compile_patterns("--exclude", &metadata_args.exclude)?;
+        let mut excluded = BTreeSet::new();
+        packages.retain(|dep| {
+            let name = &dep.package.name;
+            let matches = patterns.iter().any(|pattern| pattern.is_match(name));
+            if matches {
+                excluded.insert(name.clone());
+            }
+            !matches
+        });
+        let license_filters = license_filters(&metadata_args.licenses)?;
+        let mut license_mismatches = BTreeSet::new();
+        packages.retain(|dep| {
+            if dep.source == PkgSource::Local {
+                return true;
+            }
+            // Crates that only point at a license file are matched as if their license were empty
+            let license = dep.package.license.as_deref().unwrap_or("");
+            let satisfied = license_filters
+                .iter()
+                .all(|(negated, pattern)| pattern.is_match(license) != *negated);
+            if !satisfied {
+                license_mismatches.insert(dep.package.name.clone());
+            }
+            satisfied
+        });
+        let mut category_mismatches = BTreeSet::new();
+        if !metadata_args.categories.is_empty() {
+            packages.retain(|dep| {
+                if dep.source == PkgSource::Local {
+                    return true;
+                }
+                let in_category = dep.package.categories.iter().any(|category| {
+                    metadata_args.categories.iter().any(|wanted| {
+                        // Subcategories are named like `cryptography::cryptocurrencies`
+                        category == wanted || category.starts_with(&format!("{}::", wanted))
+                    })
+                });
+                if !in_category {
+                    category_mismatches.insert(dep.package.name.clone());
+                }
+                in_category
+            });
+        }
+        let mut graph = DependencyGraph {
+            metadata,
+            packages,
+            excluded,
+            license_mismatches,
+            category_mismatches,
+            kinds: DependencyKinds::of(metadata_args),
+            dependents_before_filtering: None,
+            inactive_optional,
+        };
+        if let Some(min_dependents) = metadata_args.min_dependents {
+            let dependents = graph.dependent_counts();
+            graph.packages.retain(|dep| {
+                dep.source != PkgSource::CratesIo
+                    || dependents
+                        .get(&dep.package.name)
+                        .is_some_and(|&count| count >= min_dependents)
+            });
+            graph.dependents_before_filtering = Some(dependents);
+        }
+        Ok(graph)
+    }
+
+    pub fn has_crates_io_crates(&self) -> bool {
+        self.packages
+            .iter()
+            .any(|dep| dep.source == PkgSource::CratesIo)
+    }
+
+    /// The error for a graph without crates.io crates, naming where it was read from
+    pub fn nothing_to_analyze(&self, metadata_args: &MetadataArgs) -> Error {
+        let input = match (
+            &metadata_args.metadata_file,
+            &metadata_args.lockfile,
+            &metadata_args.published_crate,
+        ) {
+            (Some(path), _, _) => format!("the cargo metadata output in {}", path.display()),
+            (None, Some(path), _) => path.display().to_string(),
+            (None, None, Some(spec)) => format!("--crate {}", spec),
+            (None, None, None) => self
+                .metadata
+                .workspace_root
+                .join("Cargo.toml")
+                .display()
+                .to_string(),
+        };
+        let filtered =
+            self.excluded.len() + self.license_mismatches.len() + self.category_mismatches.len();
+        let hint = if filtered > 0 {
+            format!(
+                "{} crate{} left out by --exclude, --license or --category.",
+                filtered,
+                if filtered == 1 { " was" } else { "s were" }
+            )
+        } else {
+            "Check that --manifest-path points at the intended package, and that the filters don't leave everything out.".to_string()
+        };
+        Error::EmptyGraph(format!(
+            "The dependency graph of {} has no crates.io crates to analyze. {}",
+            input, hint
+        ))
+    }
+
+    /// Tells how many crates `--exclude`, `--license` and `--category` left out,
+    /// so that the filtering is not silent
+    pub fn complain_about_excluded_crates(&self, quiet: bool) {
+        if quiet {
+            return;
+        }
+        for (crates, reason) in [
+            (&self.excluded, "matching --exclude"),
+            (
+                &self.license_mismatches,
+                "whose license doesn't satisfy --license",
+            ),
+            (
+                &self.category_mismatches,
+                "in none of the --category categories",
+            ),
+        ] {
+            if crates.is_empty() {
+                continue;
+            }
+            eprintln!(
+                "\nLeft out {} crate{} {}: {}",
+                crates.len(),
+                if crates.len() == 1 { "" } else { "s" },
+                reason,
+                comma_separated_list(&crates.iter().cloned().collect::<Vec<_>>())
+            );
+        }
+    }
+
+    /// Tells which of the crates included by `--include-inactive-optional` no selected feature
+    /// activates, since they don't end up in the build as configured
+    pub fn complain_about_inactive_optional_crates(&self, quiet: bool) {
+        let names: BTreeSet<String> = self
+            .packages
+            .iter()
+            .filter(|dep| self.inactive_optional.contains(&dep.package.id))
+            .map(|dep| dep.package.name.clone())
+            .collect();
+        if quiet || names.is_empty() {
+            return;
+        }
+        eprintln!(
+            "\nIncluded {} crate{} only pulled in by optional dependencies that no selected feature activates: {}",
+            names.len(),
+            if names.len() == 1 { "" } else { "s" },
+            comma_separated_list(&names.into_iter().collect::<Vec<_>>())
+        );
+    }
+
+    /// Returns the names of the crates.io crates that a workspace member depends on directly,
+    /// as opposed to the ones only pulled in by other dependencies.
+    /// A crate is direct if any of its versions is.
+    pub fn direct_dependencies(&self) -> BTreeSet<String> {
+        let direct = direct_dependency_ids(&self.metadata, self.kinds);
+        self.crates_io_names_among(&direct)
+    }
+
+    /// Returns the names of the crates.io crates that run at build time,
+    /// as proc-macros, build-dependencies or their dependencies. A crate is included if any of its versions is.
+    pub fn build_time_dependencies(&self) -> BTreeSet<String> {
+        self.crates_io_names_among(&build_time_ids(&self.metadata))
+    }
+
+    fn crates_io_names_among(&self, ids: &HashSet<PackageId>) -> BTreeSet<String> {
+        self.packages
+            .iter()
+            .filter(|dep| dep.source == PkgSource::CratesIo && ids.contains(&dep.package.id))
+            .map(|dep| dep.package.name.clone())
+            .collect()
+    }
+
+    /// Counts, for every crates.io crate, how many other packages in the graph
+    /// depend on any of its versions, directly or transitively.
+    /// Dev-dependency and build-dependency edges are not followed if they are excluded.
+    pub fn dependent_counts(&self) -> BTreeMap<String, usize> {
+        if let Some(dependents) = &self.dependents_before_filtering {
+            let mut dependents = dependents.clone();
+            let names = crate_names_from_source(&self.packages, PkgSource::CratesIo);
+            dependents.retain(|crate_name, _| names.binary_search(crate_name).is_ok());
+            return dependents;
+        }
+        let included: HashSet<&PackageId> = self.packages.iter().map(|d| &d.package.id).collect();
+        // Maps each package to the packages that depend on it directly
+        let mut reverse_edges: HashMap<&PackageId, Vec<&PackageId>> = HashMap::new();
+        for node in self.metadata.resolve.iter().flat_map(|r| &r.nodes) {
+            for dep in &node.deps {
+                if !self.kinds.follows(dep) {
+                    continue;
+                }
+                if included.contains(&node.id) && included.contains(&dep.pkg) {
+                    reverse_edges.entry(&dep.pkg).or_default().push(&node.id);
+                }
+            }
+        }
+
+        let mut counts = BTreeMap::new();
+        for (crate_name, ids) in crates_io_package_ids(&self.packages) {
+            let mut dependents: HashSet<&PackageId> = HashSet::new();
+            let mut queue = ids.clone();
+            while let Some(id) = queue.pop() {
+                for &dependent in reverse_edges.get(id).into_iter().flatten() {
+                    if dependents.insert(dependent) {
+                        queue.push(dependent);
+                    }
+                }
+            }
+            // Versions of the same crate depending on one another are not separate dependents
+            for id in &ids {
+                dependents.remove(id);
+            }
+            counts.insert(crate_name, dependents.len());
+        }
+        counts
+    }
+
+    /// Maps each package that can end up in the build of a workspace member to the packages
+    /// that depend on it directly, which is the graph `cargo tree -i` walks.
+    /// Dev-dependency and build-dependency edges are not followed if they are excluded.
+    pub fn reverse_dependencies(&self) -> HashMap<&PackageId, Vec<&PackageId>> {
+        let reachable = reachable_from(
+            &self.metadata,
+            self.metadata.workspace_members.iter().collect(),
+            self.kinds,
+        );
+        let mut reverse_edges: HashMap<&PackageId, Vec<&PackageId>> = HashMap::new();
+        for node in self.metadata.resolve.iter().flat_map(|r| &r.nodes) {
+            if !reachable.contains(&node.id) {
+                continue;
+            }
+            for dep in &node.deps {
+                if self.kinds.follows(dep) {
+                    reverse_edges.entry(&dep.pkg).or_default().push(&node.id);
+                }
+            }
+        }
+        reverse_edges
+    }
+}
+
+/// Compiles the `--license` patterns, each along with whether it starts with `!`,
+/// which means the license must not match it
+fn license_filters(patterns: &[String]) -> Result<Vec<(bool, Regex)>, Error> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let (negated, pattern) = match pattern.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, pattern.as_str()),
+            };
+            let regex = compile_patterns("--license", &[pattern.to_string()])?;
+            Ok((
+                negated,
+                regex.into_iter().next().expect("one pattern was given"),
+            ))
+        })
+        .collect()
+}
+
+/// Compiles the patterns of `option`. A pattern between slashes is a regular expression,
+/// anything else is a glob where `*` matches any number of characters and `?` a single one.
+/// Both must match the whole crate name or license, unless the regular expression says otherwise.
+fn compile_patterns(option: &str, patterns: &[String]) -> Result<Vec<Regex>, Error> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let regex = match pattern
+                .strip_prefix('/')
+                .and_then(|rest| rest.strip_suffix('/'))
+            {
+                Some(regex) => regex.to_string(),
+                None => {
+                    let glob: Vec<String> = pattern
+                        .split('*')
+                        .map(|part| {
+                            let escaped: Vec<String> = part.split('?').map(regex::escape).collect();
+                            escaped.join(".")
+                        })
+                        .collect();
+                    format!("^{}$", glob.join(".*"))
+                }
+            };
+            Regex::new(&regex)
+                .map_err(|e| Error::InvalidInput(format!("{} {}: {}", option, pattern, e)))
+        })
+        .collect()
+}
+
+/// Runs `cargo metadata`, or reads its output from a file if one was specified,
+/// or builds the equivalent from a lockfile, or resolves a published crate
+fn load_metadata(metadata_args: &MetadataArgs) -> Result<Metadata, Error> {
+    timing::measure(Phase::Metadata, || {
+        match (
+            &metadata_args.metadata_file,
+            &metadata_args.lockfile,
+            &metadata_args.published_crate,
+        ) {
+            (Some(path), _, _) => read_metadata_file(path),
+            (None, Some(path), _) => metadata_from_lockfile(path),
+            (None, None, Some(spec)) => metadata_of_published_crate(spec, metadata_args),
+            (None, None, None) => match metadata_args.manifest_paths.as_slice() {
+                [] => run_cargo_metadata(metadata_args, None),
+                [first, rest @ ..] => {
+                    let mut merged = run_cargo_metadata(metadata_args, Some(first))?;
+                    for manifest_path in rest {
+                        merge_metadata(
+                            &mut merged,
+                            run_cargo_metadata(metadata_args, Some(manifest_path))?,
+                        );
+                    }
+                    Ok(merged)
+                }
+            },
+        }
+    })
+}
+
+/// Adds the packages and dependency edges of another workspace to `merged`, skipping duplicates,
+/// so that the workspaces are analyzed as if they were one.
+/// The workspace root of `merged` is kept, so SARIF output points into the first workspace only.
+fn merge_metadata(merged: &mut Metadata, other: Metadata) {
+    let known: HashSet<PackageId> = merged.packages.iter().map(|p| p.id.clone()).collect();
+    merged.packages.extend(
+        other
+            .packages
+            .into_iter()
+            .filter(|p| !known.contains(&p.id)),
+    );
+    for member in other.workspace_members {
+        if !merged.workspace_members.contains(&member) {
+            merged.workspace_members.push(member);
+        }
+    }
+    let (resolve, other_resolve) = match (&mut merged.resolve, other.resolve) {
+        (Some(resolve), Some(other_resolve)) => (resolve, other_resolve),
+        _ => return,
     };
+    // There is no single root package anymore
+    resolve.root = None;
+    for node in other_resolve.nodes {
+        match resolve.nodes.iter_mut().find(|n| n.id == node.id) {
+            // The same package may be built with different dependencies in different workspaces
+            Some(existing) => {
+                for dep in node.deps {
+                    if !existing.deps.iter().any(|d| d.pkg == dep.pkg) {
+                        existing.deps.push(dep);
+                    }
+                }
+                for dependency in node.dependencies {
+                    if !existing.dependencies.contains(&dependency) {
+                        existing.dependencies.push(dependency);
+                    }
+                }
+                for feature in node.features {
+                    if !existing.features.contains(&feature) {
+                        existing.features.push(feature);
+                    }
+                }
+            }
+            None => resolve.nodes.push(node),
+        }
+    }
+}
+
+/// Classifies the packages in `meta` by source, applying the filters from `metadata_args`
+fn sourced_packages(
+    meta: &Metadata,
+    metadata_args: &MetadataArgs,
+) -> Result<Vec<SourcedPackage>, Error> {
+    let kinds = DependencyKinds::of(metadata_args);
+    let mut included: HashSet<PackageId> = if metadata_args.workspace_only {
+        meta.workspace_members.iter().cloned().collect()
+    } else {
+        resolved_packages(meta, kinds)
+    };
+    if metadata_args.direct_only {
+        let mut direct = direct_dependency_ids(meta, kinds);
+        direct.extend(meta.workspace_members.iter().cloned());
+        included = included.intersection(&direct).cloned().collect();
+    }
+    if let Some(depth) = metadata_args.depth {
+        let within = ids_within_depth(meta, depth, kinds);
+        included = included.intersection(&within).cloned().collect();
+    }
+    if metadata_args.build_time_only {
+        let mut build_time = build_time_ids(meta);
+        build_time.extend(meta.workspace_members.iter().cloned());
+        included = included.intersection(&build_time).cloned().collect();
+    }
+    if !metadata_args.filter_crates.is_empty() {
+        let subtrees = subtrees_of(meta, &metadata_args.filter_crates, kinds)?;
+        included = included.intersection(&subtrees).cloned().collect();
+    }
 
     let mut how: HashMap<PackageId, PkgSource> = HashMap::new();
     let what: HashMap<PackageId, Package> = meta
         .packages
         .iter()
+        .filter(|package| included.contains(&package.id))
         .map(|package| (package.id.clone(), package.clone()))
         .collect();
 
-    for pkg in &meta.packages {
-        // Suppose every package is foreign, until proven otherwise..
-        how.insert(pkg.id.clone(), PkgSource::Foreign);
+    for pkg in what.values() {
+        how.insert(pkg.id.clone(), classify_source(pkg));
     }
 
-    // Find the crates.io dependencies..
-    for pkg in &meta.packages {
-        if let Some(source) = pkg.source.as_ref() {
-            if source.is_crates_io() {
-                how.insert(pkg.id.clone(), PkgSource::CratesIo);
-            }
-        }
-    }
-
-    for pkg in meta.workspace_members {
-        *how.get_mut(&pkg).unwrap() = PkgSource::Local;
+    for pkg in &meta.workspace_members {
+        // Workspace members are left out when only some subtrees are included
+        let package = match what.get(pkg) {
+            Some(package) => package,
+            None => continue,
+        };
+        // When restricted to the workspace, look up the crates we publish ourselves
+        let source = if metadata_args.workspace_only && publishes_to_crates_io(package) {
+            PkgSource::CratesIo
+        } else {
+            PkgSource::Local
+        };
+        how.insert(pkg.clone(), source);
     }
 
     let dependencies: Vec<_> = how
@@ -55,13 +556,344 @@ pub fn sourced_dependencies(metadata_args: Vec<String>) -> Vec<SourcedPackage> {
         .map(|(id, kind)| {
             let dep = what.get(id).cloned().unwrap();
             SourcedPackage {
-                source: kind.clone(),
+                source: *kind,
                 package: dep,
             }
         })
         .collect();
 
-    dependencies
+    Ok(dependencies)
+}
+
+/// Whether the `publish` field of the manifest allows publishing the package to crates.io
+fn publishes_to_crates_io(package: &Package) -> bool {
+    match &package.publish {
+        None => true,
+        Some(registries) => registries.iter().any(|r| r == "crates-io"),
+    }
+}
+
+pub(crate) fn run_cargo_metadata(
+    metadata_args: &MetadataArgs,
+    manifest_path: Option<&Path>,
+) -> Result<Metadata, Error> {
+    let mut other_options = metadata_args.other_args.clone();
+    // Cargo includes the union of dependencies for all the given platforms
+    for target in &metadata_args.target {
+        other_options.push(format!("--filter-platform={}", target));
+    }
+    if metadata_args.offline {
+        other_options.push("--offline".to_string());
+    }
+    if metadata_args.locked {
+        other_options.push("--locked".to_string());
+    }
+    if metadata_args.frozen {
+        other_options.push("--frozen".to_string());
+    }
+    let mut command = MetadataCommand::new();
+    if let Some(manifest_path) = manifest_path {
+        log::debug!("Running cargo metadata for {}", manifest_path.display());
+        command.manifest_path(manifest_path);
+    }
+    if metadata_args.all_features {
+        command.features(CargoOpt::AllFeatures);
+    }
+    if metadata_args.no_default_features {
+        command.features(CargoOpt::NoDefaultFeatures);
+    }
+    if let Some(features) = &metadata_args.features {
+        // Cargo accepts features separated by either commas or spaces
+        let features = features
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .map(String::from)
+            .collect();
+        command.features(CargoOpt::SomeFeatures(features));
+    }
+    log::debug!(
+        "Running cargo metadata with extra arguments {:?}",
+        other_options
+    );
+    match command.other_options(other_options).exec() {
+        Ok(v) => Ok(v),
+        Err(cargo_metadata::Error::CargoMetadata { stderr: e }) => Err(Error::MetadataFailed(e)),
+        // Common when the binary was installed on its own, such as from a release archive
+        Err(cargo_metadata::Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+            Err(Error::MetadataFailed(format!(
+                "Failed to run `{}`: {}\n  cargo must be installed and on PATH to read the dependency graph.\n  Alternatively, pass --lockfile Cargo.lock, or --metadata-file with the output of\n  `cargo metadata --format-version 1` saved on a machine that has cargo.",
+                std::env::var("CARGO")
+                    .map(|cargo| format!("{} metadata", cargo))
+                    .unwrap_or_else(|_| "cargo metadata".to_string()),
+                e
+            )))
+        }
+        Err(err) => Err(Error::MetadataFailed(format!(
+            "Failed to fetch crate metadata!\n  {}",
+            err
+        ))),
+    }
+}
+
+/// Reads the output of `cargo metadata --format-version 1` previously saved to a file
+fn read_metadata_file(path: &Path) -> Result<Metadata, Error> {
+    log::debug!("Reading the dependency graph from {}", path.display());
+    let contents = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to read metadata file {}: {}", path.display(), e),
+        )
+    })?;
+    let invalid =
+        |msg: String| Error::Parse(format!("Invalid metadata file {}: {}", path.display(), msg));
+    let value: serde_json::Value =
+        serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+    match value.get("version").and_then(|v| v.as_u64()) {
+        Some(METADATA_FORMAT_VERSION) => (),
+        Some(version) => {
+            return Err(invalid(format!(
+                "format version {} is not supported, expected {}.\n  Generate it with `cargo metadata --format-version {}`",
+                version, METADATA_FORMAT_VERSION, METADATA_FORMAT_VERSION
+            )))
+        }
+        None => return Err(invalid("no format version specified".to_string())),
+    }
+    serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
+}
+
+/// Walks the resolved dependency graph from the workspace members, following the edges of `kinds`.
+/// Returns the set of packages that can end up in the build of a workspace member with the
+/// selected features. Packages of optional dependencies that no feature activates are not in
+/// the resolve graph, even if some versions of Cargo list them among the packages.
+fn resolved_packages(meta: &Metadata, kinds: DependencyKinds) -> HashSet<PackageId> {
+    if meta.resolve.is_none() {
+        // Without a resolve graph (e.g. `--no-deps`) there is nothing to filter out
+        return meta.packages.iter().map(|p| p.id.clone()).collect();
+    }
+    reachable_from(meta, meta.workspace_members.iter().collect(), kinds)
+}
+
+/// Returns the packages named in `roots` along with everything they depend on, directly or transitively.
+/// Every version of a named crate present in the graph is a root.
+fn subtrees_of(
+    meta: &Metadata,
+    roots: &[String],
+    kinds: DependencyKinds,
+) -> Result<HashSet<PackageId>, Error> {
+    let mut root_ids = Vec::new();
+    for name in roots {
+        let before = root_ids.len();
+        root_ids.extend(
+            meta.packages
+                .iter()
+                .filter(|package| &package.name == name)
+                .map(|package| &package.id),
+        );
+        if root_ids.len() == before {
+            return Err(Error::InvalidInput(format!(
+                "--filter-crate {}: no package with this name in the dependency graph",
+                name
+            )));
+        }
+    }
+    Ok(reachable_from(meta, root_ids, kinds))
+}
+
+/// Walks the resolved dependency graph from `roots`, following the edges of `kinds`.
+/// The roots themselves are included.
+fn reachable_from(
+    meta: &Metadata,
+    roots: Vec<&PackageId>,
+    kinds: DependencyKinds,
+) -> HashSet<PackageId> {
+    let mut reachable: HashSet<PackageId> = roots.iter().map(|&id| id.clone()).collect();
+    let resolve = match &meta.resolve {
+        Some(resolve) => resolve,
+        None => return reachable,
+    };
+    let nodes: HashMap<&PackageId, _> = resolve.nodes.iter().map(|n| (&n.id, n)).collect();
+    let mut queue = roots;
+    while let Some(id) = queue.pop() {
+        let node = match nodes.get(id) {
+            Some(node) => node,
+            None => continue,
+        };
+        for dep in &node.deps {
+            if kinds.follows(dep) && reachable.insert(dep.pkg.clone()) {
+                queue.push(&dep.pkg);
+            }
+        }
+    }
+    reachable
+}
+
+/// The kinds of dependencies followed when walking the dependency graph.
+/// Normal dependencies are always followed, and so are proc-macros, which are normal dependencies
+/// even though they run at build time.
+#[derive(Debug, Copy, Clone)]
+struct DependencyKinds {
+    dev: bool,
+    build: bool,
+}
+
+impl DependencyKinds {
+    fn of(metadata_args: &MetadataArgs) -> Self {
+        DependencyKinds {
+            dev: metadata_args.include_dev_dependencies,
+            build: !metadata_args.exclude_build_dependencies,
+        }
+    }
+
+    /// Whether the dependency edge exists because of any of the followed kinds of dependencies
+    fn follows(self, dep: &NodeDep) -> bool {
+        // `dep_kinds` is empty on Cargo older than 1.41; treat such edges as normal ones
+        dep.dep_kinds.is_empty() || dep.dep_kinds.iter().any(|info| self.includes(info.kind))
+    }
+
+    fn includes(self, kind: DependencyKind) -> bool {
+        match kind {
+            DependencyKind::Development => self.dev,
+            DependencyKind::Build => self.build,
+            _ => true,
+        }
+    }
+}
+
+/// Returns the packages that a workspace member depends on directly.
+/// Only dependencies of `kinds` are included.
+fn direct_dependency_ids(meta: &Metadata, kinds: DependencyKinds) -> HashSet<PackageId> {
+    let resolve = match &meta.resolve {
+        Some(resolve) => resolve,
+        // Without a resolve graph (e.g. `--no-deps`), go by the dependencies declared in the manifests
+        None => {
+            let declared: HashSet<&str> = meta
+                .packages
+                .iter()
+                .filter(|package| meta.workspace_members.contains(&package.id))
+                .flat_map(|package| &package.dependencies)
+                .filter(|dep| kinds.includes(dep.kind))
+                .map(|dep| dep.name.as_str())
+                .collect();
+            return meta
+                .packages
+                .iter()
+                .filter(|package| declared.contains(package.name.as_str()))
+                .map(|package| package.id.clone())
+                .collect();
+        }
+    };
+    resolve
+        .nodes
+        .iter()
+        .filter(|node| meta.workspace_members.contains(&node.id))
+        .flat_map(|node| &node.deps)
+        .filter(|dep| kinds.follows(dep))
+        .map(|dep| dep.pkg.clone())
+        .collect()
+}
+
+/// Walks the resolved dependency graph breadth-first from the workspace members,
+/// returning the packages at most `depth` edges away from one, the members included.
+/// Only the edges of `kinds` are followed.
+fn ids_within_depth(meta: &Metadata, depth: usize, kinds: DependencyKinds) -> HashSet<PackageId> {
+    let mut within: HashSet<PackageId> = meta.workspace_members.iter().cloned().collect();
+    let resolve = match &meta.resolve {
+        Some(resolve) => resolve,
+        // Without a resolve graph only the declared dependencies are known
+        None => {
+            within.extend(direct_dependency_ids(meta, kinds));
+            return within;
+        }
+    };
+    let nodes: HashMap<&PackageId, _> = resolve.nodes.iter().map(|n| (&n.id, n)).collect();
+    let mut frontier: Vec<&PackageId> = meta.workspace_members.iter().collect();
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for id in frontier {
+            let node = match nodes.get(id) {
+                Some(node) => node,
+                None => continue,
+            };
+            for dep in &node.deps {
+                if kinds.follows(dep) && within.insert(dep.pkg.clone()) {
+                    next.push(&dep.pkg);
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    within
+}
+
+/// Returns the packages whose code runs on the machine doing the build:
+/// proc-macros, build-dependencies, and everything they depend on.
+/// Crates with a build script of their own are not included unless they fall in one of these groups.
+fn build_time_ids(meta: &Metadata) -> HashSet<PackageId> {
+    let mut roots: Vec<&PackageId> = meta
+        .packages
+        .iter()
+        .filter(|package| {
+            package
+                .targets
+                .iter()
+                .any(|target| target.kind.iter().any(|kind| kind == "proc-macro"))
+        })
+        .map(|package| &package.id)
+        .collect();
+    match &meta.resolve {
+        Some(resolve) => roots.extend(
+            resolve
+                .nodes
+                .iter()
+                .flat_map(|node| &node.deps)
+                .filter(|dep| {
+                    dep.dep_kinds
+                        .iter()
+                        .any(|info| info.kind == DependencyKind::Build)
+                })
+                .map(|dep| &dep.pkg),
+        ),
+        // Without a resolve graph (e.g. `--no-deps`), go by the dependencies declared in the manifests
+        None => {
+            let declared: HashSet<&str> = meta
+                .packages
+                .iter()
+                .flat_map(|package| &package.dependencies)
+                .filter(|dep| dep.kind == DependencyKind::Build)
+                .map(|dep| dep.name.as_str())
+                .collect();
+            roots.extend(
+                meta.packages
+                    .iter()
+                    .filter(|package| declared.contains(package.name.as_str()))
+                    .map(|package| &package.id),
+            );
+        }
+    }
+    reachable_from(
+        meta,
+        roots,
+        DependencyKinds {
+            dev: false,
+            build: true,
+        },
+    )
+}
+
+fn crates_io_package_ids(dependencies: &[SourcedPackage]) -> BTreeMap<String, Vec<&PackageId>> {
+    let mut result: BTreeMap<String, Vec<&PackageId>> = BTreeMap::new();
+    for dep in dependencies {
+        if dep.source == PkgSource::CratesIo {
+            result
+                .entry(dep.package.name.clone())
+                .or_default()
+                .push(&dep.package.id);
+        }
+    }
+    result
 }
 
 pub fn crate_names_from_source(crates: &[SourcedPackage], source: PkgSource) -> Vec<String> {
@@ -76,8 +908,83 @@ pub fn crate_names_from_source(crates: &[SourcedPackage], source: PkgSource) ->
     filtered_crate_names
 }
 
-pub fn complain_about_non_crates_io_crates(dependencies: &[SourcedPackage]) {
-    {
+/// Maps the names and versions of crates.io crates to the license expressions in their manifests,
+/// for the versions that have one rather than only a license file
+pub(crate) fn version_licenses(
+    dependencies: &[SourcedPackage],
+) -> BTreeMap<(String, String), String> {
+    dependencies
+        .iter()
+        .filter(|dep| dep.source == PkgSource::CratesIo)
+        .filter_map(|dep| {
+            let license = dep.package.license.clone()?;
+            Some((
+                (dep.package.name.clone(), dep.package.version.to_string()),
+                license,
+            ))
+        })
+        .collect()
+}
+
+/// Maps names of crates.io crates to the distinct license expressions of their versions
+/// present in the dependency graph, which usually agree
+pub(crate) fn crate_licenses(dependencies: &[SourcedPackage]) -> BTreeMap<String, Vec<String>> {
+    let mut licenses: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for ((crate_name, _), license) in version_licenses(dependencies) {
+        let entry = licenses.entry(crate_name).or_default();
+        if !entry.contains(&license) {
+            entry.push(license);
+        }
+    }
+    licenses
+}
+
+/// Maps names of crates.io crates to the categories or keywords that `tags` takes from their
+/// manifests, merged across their versions present in the dependency graph and sorted.
+/// Crates without any are left out.
+pub(crate) fn crate_tags(
+    dependencies: &[SourcedPackage],
+    tags: impl Fn(&Package) -> &Vec<String>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut merged: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for dep in dependencies {
+        if dep.source == PkgSource::CratesIo && !tags(&dep.package).is_empty() {
+            merged
+                .entry(dep.package.name.clone())
+                .or_default()
+                .extend(tags(&dep.package).iter().cloned());
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(name, tags)| (name, tags.into_iter().collect()))
+        .collect()
+}
+
+/// Maps names of crates.io crates to all of their versions present in the dependency graph
+pub fn crate_versions(dependencies: &[SourcedPackage]) -> BTreeMap<String, Vec<String>> {
+    let mut versions: BTreeMap<String, Vec<_>> = BTreeMap::new();
+    for dep in dependencies {
+        if dep.source == PkgSource::CratesIo {
+            versions
+                .entry(dep.package.name.clone())
+                .or_default()
+                .push(dep.package.version.clone());
+        }
+    }
+    versions
+        .into_iter()
+        .map(|(name, mut versions)| {
+            versions.sort_unstable();
+            (name, versions.iter().map(|v| v.to_string()).collect())
+        })
+        .collect()
+}
+
+/// Warns about crates that cannot be audited.
+/// Unless `quiet`, also lists the crates from local directories, which are skipped as expected.
+pub fn complain_about_non_crates_io_crates(dependencies: &[SourcedPackage], quiet: bool) {
+    if !quiet {
         // scope bound to avoid accidentally referencing local crates when working with foreign ones
         let local_crate_names = crate_names_from_source(dependencies, PkgSource::Local);
         if !local_crate_names.is_empty() {
@@ -91,16 +998,125 @@ pub fn complain_about_non_crates_io_crates(dependencies: &[SourcedPackage]) {
     }
 
     {
-        let foreign_crate_names = crate_names_from_source(dependencies, PkgSource::Foreign);
-        if !foreign_crate_names.is_empty() {
+        let foreign_crates: Vec<_> = non_crates_io_crates(dependencies)
+            .into_iter()
+            .filter(|(_, source)| *source != PkgSource::Local)
+            .collect();
+        if !foreign_crates.is_empty() {
+            let registries = alternative_registries(dependencies);
             eprintln!("\nCannot audit the following crates because they are not from crates.io:");
-            for crate_name in &foreign_crate_names {
-                eprintln!(" - {}", crate_name);
+            for (crate_name, source) in &foreign_crates {
+                eprintln!(
+                    " - {} ({})",
+                    crate_name,
+                    describe_source(crate_name, *source, &registries)
+                );
             }
         }
     }
 }
 
+/// Names of crates that do not come from crates.io along with their source, sorted by name
+pub fn non_crates_io_crates(dependencies: &[SourcedPackage]) -> Vec<(String, PkgSource)> {
+    let mut result: Vec<_> = dependencies
+        .iter()
+        .filter(|p| p.source != PkgSource::CratesIo)
+        .map(|p| (p.package.name.clone(), p.source))
+        .collect();
+    result.sort_unstable();
+    result.dedup();
+    result
+}
+
+/// Maps the names of crates from registries other than crates.io to the index URL of their registry.
+/// Their publishers are not looked up: alternative registries rarely implement the owners API,
+/// and their owners wouldn't be comparable to those on crates.io anyway.
+pub fn alternative_registries(dependencies: &[SourcedPackage]) -> BTreeMap<String, String> {
+    dependencies
+        .iter()
+        .filter(|p| p.source == PkgSource::Registry)
+        .filter_map(|p| {
+            let source = p.package.source.as_ref()?;
+            let index = source
+                .repr
+                .trim_start_matches("registry+")
+                .trim_start_matches("sparse+");
+            Some((p.package.name.clone(), index.to_owned()))
+        })
+        .collect()
+}
+
+/// Describes the source of a crate, naming the registry if it's from one other than crates.io
+pub fn describe_source(
+    crate_name: &str,
+    source: PkgSource,
+    registries: &BTreeMap<String, String>,
+) -> String {
+    match registries.get(crate_name) {
+        Some(index) if source == PkgSource::Registry => {
+            format!("{} {}", source.description(), index)
+        }
+        _ => source.description().to_owned(),
+    }
+}
+
+/// Counts distinct crate names from each kind of source
+pub fn source_counts(dependencies: &[SourcedPackage]) -> BTreeMap<PkgSource, usize> {
+    let names: HashSet<(PkgSource, &String)> = dependencies
+        .iter()
+        .map(|p| (p.source, &p.package.name))
+        .collect();
+    let mut counts = BTreeMap::new();
+    for (source, _) in names {
+        *counts.entry(source).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Lists the versions in the dependency graph that have been yanked, such as `serde 1.0.0`
+pub fn yanked_in_dependency_graph(
+    dependencies: &[SourcedPackage],
+    owners: &CrateOwners,
+) -> Vec<String> {
+    let mut yanked = Vec::new();
+    for (crate_name, versions) in crate_versions(dependencies) {
+        for version in owners.yanked_in(&crate_name, &versions) {
+            yanked.push(format!("{} {}", crate_name, version));
+        }
+    }
+    yanked
+}
+
+pub fn complain_about_yanked_versions(dependencies: &[SourcedPackage], owners: &CrateOwners) {
+    let yanked = yanked_in_dependency_graph(dependencies, owners);
+    if !yanked.is_empty() {
+        eprintln!("\nWarning: the following versions in the dependency graph have been yanked:");
+        for crate_version in &yanked {
+            eprintln!(" - {}", crate_version);
+        }
+        eprintln!(
+            "Pass --include-yanked to silence this warning if you depend on them intentionally."
+        );
+    }
+}
+
+/// Converts a crates.io timestamp into RFC 3339 with a precision of one second, such as `2020-01-31T12:00:00Z`.
+/// The API returns timestamps such as `2020-01-31T12:00:00.123456+00:00`,
+/// while the database dump uses `2020-01-31 12:00:00.123456`. Both are in UTC.
+pub(crate) fn normalize_timestamp(raw: &str) -> Option<String> {
+    let normalized = format!("{}Z", raw.get(..19)?.replacen(' ', "T", 1));
+    humantime::parse_rfc3339(&normalized).ok()?;
+    Some(normalized)
+}
+
+/// Returns true if the timestamp produced by `normalize_timestamp` is older than `max_age`
+pub(crate) fn is_older_than(timestamp: &str, max_age: Duration) -> bool {
+    humantime::parse_rfc3339(timestamp)
+        .ok()
+        .and_then(|time| time.elapsed().ok())
+        .is_some_and(|age| age > max_age)
+}
+
 pub fn comma_separated_list(list: &[String]) -> String {
     let mut result = String::new();
     let mut first_loop = true;
@@ -113,3 +1129,49 @@ pub fn comma_separated_list(list: &[String]) -> String {
     }
     result
 }
+
+/// Colors used to highlight text output on a terminal
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Color {
+    /// Crates that a single individual can publish
+    Red,
+    /// Trusted publishers
+    Green,
+}
+
+/// Wraps `text` in the ANSI escape codes for `color` if `enabled`, and returns it unchanged otherwise
+pub(crate) fn paint(text: String, color: Color, enabled: bool) -> String {
+    if !enabled {
+        return text;
+    }
+    let code = match color {
+        Color::Red => 31,
+        Color::Green => 32,
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_whole_names_and_slashes_delimit_regular_expressions() {
+        let patterns = compile_patterns(
+            "--exclude",
+            &[
+                "windows-*".to_owned(),
+                "/^(libc|cc)$/".to_owned(),
+                "it?a".to_owned(),
+            ],
+        )
+        .unwrap();
+        let excluded = |name: &str| patterns.iter().any(|pattern| pattern.is_match(name));
+        assert!(excluded("windows-sys") && excluded("libc") && excluded("itoa"));
+        assert!(!excluded("my-windows-sys") && !excluded("libcc") && !excluded("itoaa"));
+        // Characters special to regular expressions are literal in globs
+        let dotted = compile_patterns("--exclude", &["a.b".to_owned()]).unwrap();
+        assert!(dotted[0].is_match("a.b") && !dotted[0].is_match("axb"));
+        assert!(compile_patterns("--exclude", &["/(/".to_owned()]).is_err());
+    }
+}
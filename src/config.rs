@@ -0,0 +1,157 @@
+//! Defaults for command-line options, loaded from `supply-chain.toml`.
+//!
+//! The file can be committed to the project so that everyone reviews with the same settings:
+//!
+//! ```toml
+//! cache-max-age = "1w"
+//! target = ["x86_64-unknown-linux-gnu"]
+//! exclude = ["windows-*"]
+//! include-dev-dependencies = true
+//! ```
+//!
+//! Options given on the command line take precedence over those in the file.
+
+use serde::Deserialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Looked up in the current directory, and in the user's configuration directory
+pub const DEFAULT_CONFIG_FILE: &str = "supply-chain.toml";
+
+/// Options that apply to every subcommand querying the dependency graph.
+/// Each field is named after the command-line option it provides a default for.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(default, with = "humantime_serde")]
+    pub cache_max_age: Option<Duration>,
+    #[serde(default)]
+    pub target: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Switches are optional rather than `false` by default,
+    /// so that a project file can turn off one that the user's file turns on
+    #[serde(default)]
+    pub include_dev_dependencies: Option<bool>,
+    #[serde(default)]
+    pub exclude_build_dependencies: Option<bool>,
+    #[serde(default)]
+    pub features: Option<String>,
+    #[serde(default)]
+    pub all_features: Option<bool>,
+    #[serde(default)]
+    pub no_default_features: Option<bool>,
+    #[serde(default)]
+    pub requests_per_second: Option<f64>,
+    #[serde(default)]
+    pub jobs: Option<usize>,
+    /// One of `gentle`, `normal` or `aggressive`, as accepted by `--throttle`
+    #[serde(default)]
+    pub throttle: Option<String>,
+    #[serde(default)]
+    pub registry_url: Option<String>,
+    #[serde(default, with = "humantime_serde")]
+    pub request_timeout: Option<Duration>,
+    /// Warning categories, as accepted by `--allow`
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+impl Config {
+    /// Loads `DEFAULT_CONFIG_FILE` from the user's configuration directory and from the current
+    /// directory, with the settings in the latter taking precedence. Missing files are skipped.
+    pub fn load() -> Result<Self, io::Error> {
+        let project = Self::read(Path::new(DEFAULT_CONFIG_FILE))?;
+        let user = match Self::user_config_path() {
+            Some(path) => Self::read(&path)?,
+            None => None,
+        };
+        Ok(match (project, user) {
+            (Some(project), Some(user)) => project.or(user),
+            (project, user) => project.or(user).unwrap_or_default(),
+        })
+    }
+
+    /// Such as `~/.config/cargo-supply-chain/supply-chain.toml` on Linux
+    fn user_config_path() -> Option<PathBuf> {
+        let projects =
+            directories_next::ProjectDirs::from("", "rust-secure-code", "cargo-supply-chain")?;
+        Some(projects.config_dir().join(DEFAULT_CONFIG_FILE))
+    }
+
+    fn read(path: &Path) -> Result<Option<Self>, io::Error> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("Failed to read config file {}: {}", path.display(), e),
+                ))
+            }
+        };
+        log::debug!("Loaded defaults from {}", path.display());
+        toml::from_str(&contents).map(Some).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid config file {}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Fills in the settings missing from `self` with those of `fallback`.
+    /// Lists are taken as a whole, rather than merged.
+    fn or(self, fallback: Config) -> Config {
+        Config {
+            cache_max_age: self.cache_max_age.or(fallback.cache_max_age),
+            target: if self.target.is_empty() {
+                fallback.target
+            } else {
+                self.target
+            },
+            exclude: if self.exclude.is_empty() {
+                fallback.exclude
+            } else {
+                self.exclude
+            },
+            include_dev_dependencies: self
+                .include_dev_dependencies
+                .or(fallback.include_dev_dependencies),
+            exclude_build_dependencies: self
+                .exclude_build_dependencies
+                .or(fallback.exclude_build_dependencies),
+            features: self.features.or(fallback.features),
+            all_features: self.all_features.or(fallback.all_features),
+            no_default_features: self.no_default_features.or(fallback.no_default_features),
+            requests_per_second: self.requests_per_second.or(fallback.requests_per_second),
+            jobs: self.jobs.or(fallback.jobs),
+            throttle: self.throttle.or(fallback.throttle),
+            registry_url: self.registry_url.or(fallback.registry_url),
+            request_timeout: self.request_timeout.or(fallback.request_timeout),
+            allow: if self.allow.is_empty() {
+                fallback.allow
+            } else {
+                self.allow
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_project_file_can_turn_off_the_switches_of_the_user_file() {
+        let user: Config =
+            toml::from_str("all-features = true\ninclude-dev-dependencies = true").unwrap();
+        let project: Config = toml::from_str("all-features = false").unwrap();
+        let merged = project.or(user);
+        assert_eq!(merged.all_features, Some(false));
+        assert_eq!(merged.include_dev_dependencies, Some(true));
+        assert_eq!(merged.no_default_features, None);
+    }
+}
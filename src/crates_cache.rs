@@ -1,14 +1,16 @@
 use crate::api_client::RateLimitedClient;
-use crate::publishers::{PublisherData, PublisherKind};
-use flate2::read::GzDecoder;
+use crate::common::normalize_timestamp;
+use crate::publishers::{OwnershipChange, PublisherData, PublisherKind};
+use crate::Error;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use serde::{Deserialize, Serialize};
 use std::iter::FromIterator;
 use std::{
     collections::{BTreeSet, HashMap},
     fs,
-    io::{self, ErrorKind},
+    io::{self, ErrorKind, Seek, Write},
     mem,
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::Duration,
     time::SystemTimeError,
 };
@@ -20,7 +22,8 @@ pub struct CratesCache {
     crate_owners: Option<HashMap<u64, Vec<CrateOwner>>>,
     users: Option<HashMap<u64, User>>,
     teams: Option<HashMap<u64, Team>>,
-    versions: Option<HashMap<(u64, String), Publisher>>,
+    yanked_versions: Option<HashMap<u64, Vec<Version>>>,
+    last_published: Option<HashMap<u64, String>>,
 }
 
 pub enum CacheState {
@@ -30,14 +33,28 @@ pub enum CacheState {
 }
 
 pub enum DownloadState {
-    /// The tag still matched and resource was not stale.
+    /// The cache was young enough that nothing was requested.
     Fresh,
+    /// The server confirmed that the cached dump is still the latest one.
+    NotModified,
     /// There was a newer resource.
     Expired,
     /// We forced the download of an update.
     Stale,
 }
 
+/// What the server tells about the data dump without sending it, as returned by `CratesCache::probe()`
+pub struct RemoteDump {
+    /// The `Last-Modified` header, or that of the cached dump if it's still the latest one
+    pub last_modified: Option<String>,
+    /// Size of the whole dump. Not known if it's still the latest one.
+    pub length: Option<u64>,
+    /// The server confirmed that the cached dump is still the latest one
+    pub not_modified: bool,
+    /// Bytes left over from an interrupted download of this very dump, which would be resumed
+    pub resumable: Option<u64>,
+}
+
 struct CacheDir(PathBuf);
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -52,6 +69,11 @@ struct MetadataStored {
     timestamp: std::time::SystemTime,
     #[serde(default)]
     etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    /// When the server last confirmed that the dump has not changed since it was downloaded
+    #[serde(default, with = "humantime_serde")]
+    checked: Option<std::time::SystemTime>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -59,6 +81,8 @@ struct Crate {
     name: String,
     id: u64,
     repository: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -66,12 +90,21 @@ struct CrateOwner {
     crate_id: u64,
     owner_id: u64,
     owner_kind: i32,
+    /// When the owner was added, in the format of the dump. Missing from caches created by older versions.
+    #[serde(default)]
+    created_at: Option<String>,
 }
 
+/// Only the yanked versions are kept, since the full list of versions is huge
 #[derive(Clone, Deserialize, Serialize)]
-struct Publisher {
+struct Version {
     crate_id: u64,
-    published_by: u64,
+    num: String,
+    /// PostgreSQL boolean, either `t` or `f`
+    yanked: String,
+    /// Only used to find the newest version of each crate while reading the dump
+    #[serde(default, skip_serializing)]
+    created_at: String,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -97,9 +130,10 @@ impl CratesCache {
     const CRATE_OWNERS_FS: &'static str = "crate_owners.json";
     const USERS_FS: &'static str = "users.json";
     const TEAMS_FS: &'static str = "teams.json";
-    const VERSIONS_FS: &'static str = "versions.json";
+    const YANKED_VERSIONS_FS: &'static str = "yanked_versions.json";
+    const LAST_PUBLISHED_FS: &'static str = "last_published.json";
 
-    const DUMP_URL: &'static str = "https://static.crates.io/db-dump.tar.gz";
+    const DUMP_FS: &'static str = "db-dump.tar.gz";
 
     /// Open a crates cache.
     pub fn new() -> Self {
@@ -110,22 +144,61 @@ impl CratesCache {
             crate_owners: None,
             users: None,
             teams: None,
-            versions: None,
+            yanked_versions: None,
+            last_published: None,
         }
     }
 
-    fn cache_dir() -> Option<PathBuf> {
+    /// Environment variable that overrides the location of the cache
+    pub(crate) const CACHE_DIR_ENV: &'static str = "CARGO_SUPPLY_CHAIN_CACHE";
+
+    /// The directory named by `CARGO_SUPPLY_CHAIN_CACHE`, or the platform's cache directory
+    pub(crate) fn cache_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os(Self::CACHE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            return Some(PathBuf::from(dir));
+        }
         let projects =
             directories_next::ProjectDirs::from("", "rust-secure-code", "cargo-supply-chain")?;
         Some(projects.cache_dir().to_owned())
     }
 
+    /// The files this cache may keep in `dir`, whether or not they exist: the tables, including
+    /// uncompressed ones left behind by older versions, the archive of the dump,
+    /// and the leftovers of interrupted downloads and writes.
+    pub(crate) fn files_in(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for table in &[
+            Self::METADATA_FS,
+            Self::CRATES_FS,
+            Self::CRATE_OWNERS_FS,
+            Self::USERS_FS,
+            Self::TEAMS_FS,
+            Self::YANKED_VERSIONS_FS,
+            Self::LAST_PUBLISHED_FS,
+        ] {
+            let stored = dir.join(stored_name(table));
+            files.push(stored.with_extension("part"));
+            if stored != dir.join(table) {
+                files.push(dir.join(table));
+            }
+            files.push(stored);
+        }
+        let partial = PartialDownload::new(dir);
+        files.push(dir.join(Self::DUMP_FS));
+        files.push(partial.path);
+        files.push(partial.etag_path);
+        files
+    }
+
     /// Re-download the list from the data dumps.
+    ///
+    /// The archive is first downloaded to a temporary file, so that an interrupted download
+    /// can be resumed and a corrupt one never replaces the existing cache.
     pub fn download(
         &mut self,
-        client: &mut RateLimitedClient,
+        client: &RateLimitedClient,
         max_age: Duration,
-    ) -> Result<DownloadState, io::Error> {
+    ) -> Result<DownloadState, Error> {
         let bar = indicatif::ProgressBar::new(!0)
             .with_prefix("Downloading")
             .with_style(
@@ -134,34 +207,65 @@ impl CratesCache {
             )
             .with_message("preparing");
 
-        let remembered_etag;
-        let response = {
-            let mut request = client.get(Self::DUMP_URL);
-            if let Some(meta) = self.load_metadata() {
-                remembered_etag = meta.etag.clone();
-                // See if we can consider the resource not-yet-stale.
-                if let Some(true) = meta.validate(max_age) {
-                    if let Some(etag) = meta.etag.as_ref() {
-                        request = request.set("if-none-match", &etag);
-                    }
-                }
-            } else {
-                remembered_etag = None;
+        let cache_dir = CratesCache::cache_dir().ok_or_else(|| {
+            Error::Cache(format!(
+                "No cache directory was found for this platform.\n  Set {} to choose one.",
+                Self::CACHE_DIR_ENV
+            ))
+        })?;
+        let mut cache_updater = CacheUpdater::new(cache_dir.clone())?;
+        let archive_path = cache_dir.join(Self::DUMP_FS);
+        let partial = PartialDownload::new(&cache_dir);
+
+        // The cache was downloaded or confirmed to be up to date recently enough
+        if let Some(true) = self.validate(max_age) {
+            bar.finish_and_clear();
+            return Ok(DownloadState::Fresh);
+        }
+
+        let remembered_etag = self.load_metadata().and_then(|meta| meta.etag.clone());
+        // `--cache-max-age 0` forces a refresh, so the dump is downloaded even if it hasn't changed
+        let forced = max_age.is_zero();
+        let resume_from = partial.resumable();
+        let response = match self.request_dump(client, resume_from.as_ref(), !forced) {
+            // The partial download is already complete or no longer matches, so start over
+            Err(e) if matches!(*e, ureq::Error::Status(416, _)) => {
+                partial.discard();
+                self.request_dump(client, None, !forced)
             }
-            request.call()
+            response => response,
         }
-        .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+        .map_err(|e| client.request_error(client.dump_url(), *e))?;
 
-        // Not modified.
+        // Not modified, so the cache is as fresh as it can be. Remember that instead of downloading it again.
         if response.status() == 304 {
             bar.finish_and_clear();
-            return Ok(DownloadState::Fresh);
+            if let Some(meta) = self.load_metadata() {
+                let mut meta = meta.clone();
+                meta.checked = Some(std::time::SystemTime::now());
+                cache_updater.store(&mut self.metadata, Self::METADATA_FS, meta)?;
+                cache_updater.commit()?;
+            }
+            return Ok(DownloadState::NotModified);
         }
 
-        if let Some(length) = response
+        let etag = response.header("etag").map(String::from);
+        let last_modified = response.header("last-modified").map(String::from);
+        let offset = match (response.status(), &resume_from) {
+            (206, Some((offset, _))) => *offset,
+            _ => 0,
+        };
+        if offset > 0 {
+            bar.println(format!(
+                "Resuming the download from {}",
+                indicatif::HumanBytes(offset)
+            ));
+        }
+        let expected_length = response
             .header("content-length")
-            .and_then(|l| l.parse().ok())
-        {
+            .and_then(|l| l.parse::<u64>().ok())
+            .map(|length| offset + length);
+        if let Some(length) = expected_length {
             bar.set_style(
                 indicatif::ProgressStyle::default_bar()
                     .template("{prefix:>12.bright.cyan} [{bar:27}] {bytes:>9}/{total_bytes:9}  {bytes_per_sec}  ETA {eta:4} - {msg:.cyan}")
@@ -173,114 +277,301 @@ impl CratesCache {
                 "{prefix:>12.bright.cyan} {spinner} {bytes:>9} {bytes_per_sec} - {msg:.cyan}",
             ));
         }
+        bar.set_position(offset);
+        bar.set_message(Self::DUMP_FS);
 
-        let etag = response.header("etag").map(String::from);
-        let reader = bar.wrap_read(response.into_reader());
+        let mut file = partial.open(offset, etag.as_deref())?;
+        let interrupted = |e: io::Error| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "Download interrupted, run the 'update' subcommand again to resume it: {}",
+                    e
+                ),
+            )
+        };
+        let downloaded =
+            io::copy(&mut bar.wrap_read(response.into_reader()), &mut file).map_err(interrupted)?;
+        file.sync_all()?;
+        // The connection may be closed early without an error
+        if let Some(length) = expected_length {
+            if offset + downloaded < length {
+                return Err(interrupted(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("received {} of {} bytes", offset + downloaded, length),
+                ))
+                .into());
+            }
+        }
+        drop(file);
+        partial.finish(&archive_path)?;
+
+        bar.set_style(
+            indicatif::ProgressStyle::default_spinner()
+                .template("{prefix:>12.bright.cyan} {spinner} {msg:.cyan}"),
+        );
+        bar.set_prefix("Verifying");
+        bar.set_message(Self::DUMP_FS);
+        let result = verify_archive(&archive_path)
+            .map_err(|e| {
+                Error::Cache(format!(
+                    "The downloaded data dump is corrupt, the existing cache was left intact: {}",
+                    e
+                ))
+            })
+            .and_then(|()| {
+                bar.set_prefix("Extracting");
+                self.extract_archive(
+                    &archive_path,
+                    &mut cache_updater,
+                    &etag,
+                    &last_modified,
+                    &bar,
+                )
+                .map_err(Error::from)
+            });
+        // The archive is no longer needed once extracted, and must not be reused if corrupt
+        let _ = fs::remove_file(&archive_path);
+        result?;
+        // Now that we've successfully downloaded and stored everything,
+        // replace the old cache contents with the new one.
+        cache_updater.commit()?;
+        bar.finish_and_clear();
+
+        // If we get here, we had no validators or the dump has changed, or the server ignored them.
+        // Catch the last if the etag still matches, as it means the crates.io daily dumps were not updated.
+        if is_same_dump(&remembered_etag, &etag) && !forced {
+            Ok(DownloadState::Stale)
+        } else {
+            Ok(DownloadState::Expired)
+        }
+    }
+
+    /// Asks the server about the data dump with a HEAD request, changing nothing on disk
+    pub fn probe(&mut self, client: &RateLimitedClient) -> Result<RemoteDump, Error> {
+        let response = match self.revalidating(client.head(client.dump_url())).call() {
+            // Simple mirrors may not implement HEAD. The body of a GET is then never read.
+            Err(ureq::Error::Status(405 | 501, _)) => {
+                self.revalidating(client.get(client.dump_url())).call()
+            }
+            response => response,
+        }
+        .map_err(|e| client.request_error(client.dump_url(), e))?;
+        if response.status() == 304 {
+            return Ok(RemoteDump {
+                last_modified: self
+                    .load_metadata()
+                    .and_then(|meta| meta.last_modified.clone()),
+                length: None,
+                not_modified: true,
+                resumable: None,
+            });
+        }
+        let etag = response.header("etag");
+        let resumable = CratesCache::cache_dir()
+            .and_then(|dir| PartialDownload::new(&dir).resumable())
+            .filter(|(_, partial_etag)| Some(partial_etag.as_str()) == etag)
+            .map(|(offset, _)| offset);
+        Ok(RemoteDump {
+            last_modified: response.header("last-modified").map(String::from),
+            length: response
+                .header("content-length")
+                .and_then(|l| l.parse().ok()),
+            not_modified: false,
+            resumable,
+        })
+    }
+
+    /// Requests the data dump, only the part after `resume_from` if specified.
+    /// The server is asked to respond with 304 Not Modified if the cached dump is still current.
+    fn request_dump(
+        &mut self,
+        client: &RateLimitedClient,
+        resume_from: Option<&(u64, String)>,
+        revalidate: bool,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let mut request = client.get(client.dump_url());
+        if revalidate {
+            request = self.revalidating(request);
+        }
+        if let Some((offset, etag)) = resume_from {
+            log::debug!("Resuming the download of dump {} at byte {}", etag, offset);
+            // If the dump has changed since, If-Range makes the server send all of it instead
+            request = request
+                .set("range", &format!("bytes={}-", offset))
+                .set("if-range", etag);
+        }
+        request.call().map_err(Box::new)
+    }
+
+    /// Sets the validators of the cached dump on `request`,
+    /// so that the server responds with 304 Not Modified if it's still current
+    fn revalidating(&mut self, mut request: ureq::Request) -> ureq::Request {
+        if let Some(meta) = self.load_metadata() {
+            if let Some(etag) = meta.etag.as_ref() {
+                log::debug!("Revalidating the cached dump with ETag {}", etag);
+                request = request.set("if-none-match", etag);
+            }
+            if let Some(last_modified) = meta.last_modified.as_ref() {
+                log::debug!("Revalidating the cached dump modified at {}", last_modified);
+                request = request.set("if-modified-since", last_modified);
+            }
+        }
+        request
+    }
+
+    /// Stages the tables we use from a downloaded data dump in the cache
+    fn extract_archive(
+        &mut self,
+        path: &Path,
+        cache_updater: &mut CacheUpdater,
+        etag: &Option<String>,
+        last_modified: &Option<String>,
+        bar: &indicatif::ProgressBar,
+    ) -> Result<(), io::Error> {
+        let reader = io::BufReader::new(fs::File::open(path)?);
         let ungzip = GzDecoder::new(reader);
         let mut archive = tar::Archive::new(ungzip);
 
-        let cache_dir = CratesCache::cache_dir().ok_or(ErrorKind::NotFound)?;
-        let mut cache_updater = CacheUpdater::new(cache_dir)?;
         let required_files = BTreeSet::from_iter(
             [
                 Self::CRATE_OWNERS_FS,
                 Self::CRATES_FS,
                 Self::USERS_FS,
                 Self::TEAMS_FS,
+                Self::YANKED_VERSIONS_FS,
+                Self::LAST_PUBLISHED_FS,
                 Self::METADATA_FS,
             ]
             .iter()
             .map(|x| x.to_string()),
         );
-        for file in archive.entries()? {
-            if let Ok(entry) = file {
-                if let Ok(path) = entry.path() {
-                    if let Some(name) = path.file_name().and_then(|f| f.to_str()) {
-                        bar.set_message(name.to_string());
-                    }
+        for entry in archive.entries()? {
+            let entry = entry?;
+            if let Ok(path) = entry.path() {
+                log::trace!("Found {} in the dump", path.display());
+                if let Some(name) = path.file_name().and_then(|f| f.to_str()) {
+                    bar.set_message(name.to_string());
                 }
-                if entry.path_bytes().ends_with(b"crate_owners.csv") {
-                    let owners: Vec<CrateOwner> = read_csv_data(entry)?;
-                    cache_updater.store_multi_map(
-                        &mut self.crate_owners,
-                        Self::CRATE_OWNERS_FS,
-                        owners.as_slice(),
-                        &|owner| owner.crate_id,
-                    )?;
-                } else if entry.path_bytes().ends_with(b"crates.csv") {
-                    let crates: Vec<Crate> = read_csv_data(entry)?;
-                    cache_updater.store_map(
-                        &mut self.crates,
-                        Self::CRATES_FS,
-                        crates.as_slice(),
-                        &|crate_| crate_.name.clone(),
-                    )?;
-                } else if entry.path_bytes().ends_with(b"users.csv") {
-                    let users: Vec<User> = read_csv_data(entry)?;
-                    cache_updater.store_map(
-                        &mut self.users,
-                        Self::USERS_FS,
-                        users.as_slice(),
-                        &|user| user.id,
-                    )?;
-                } else if entry.path_bytes().ends_with(b"teams.csv") {
-                    let teams: Vec<Team> = read_csv_data(entry)?;
-                    cache_updater.store_map(
-                        &mut self.teams,
-                        Self::TEAMS_FS,
-                        teams.as_slice(),
-                        &|team| team.id,
-                    )?;
-                } else if entry.path_bytes().ends_with(b"metadata.json") {
-                    let meta: Metadata = serde_json::from_reader(entry)?;
-                    cache_updater.store(
-                        &mut self.metadata,
-                        Self::METADATA_FS,
-                        MetadataStored {
-                            timestamp: meta.timestamp,
-                            etag: etag.clone(),
-                        },
-                    )?;
-                } else {
-                    // This was not a file with a filename we actually use.
-                    // Check if we've obtained all the files we need.
-                    // If yes, we can end the download early.
-                    // This saves hundreds of megabytes of traffic.
-                    if required_files.is_subset(&cache_updater.staged_files) {
-                        break;
+            }
+            if entry.path_bytes().ends_with(b"crate_owners.csv") {
+                let owners: Vec<CrateOwner> = read_csv_data(entry)?;
+                cache_updater.store_multi_map(
+                    &mut self.crate_owners,
+                    Self::CRATE_OWNERS_FS,
+                    owners.as_slice(),
+                    &|owner| owner.crate_id,
+                )?;
+            } else if entry.path_bytes().ends_with(b"crates.csv") {
+                let crates: Vec<Crate> = read_csv_data(entry)?;
+                cache_updater.store_map(
+                    &mut self.crates,
+                    Self::CRATES_FS,
+                    crates.as_slice(),
+                    &|crate_| crate_.name.clone(),
+                )?;
+            } else if entry.path_bytes().ends_with(b"users.csv") {
+                let users: Vec<User> = read_csv_data(entry)?;
+                cache_updater.store_map(
+                    &mut self.users,
+                    Self::USERS_FS,
+                    users.as_slice(),
+                    &|user| user.id,
+                )?;
+            } else if entry.path_bytes().ends_with(b"teams.csv") {
+                let teams: Vec<Team> = read_csv_data(entry)?;
+                cache_updater.store_map(
+                    &mut self.teams,
+                    Self::TEAMS_FS,
+                    teams.as_slice(),
+                    &|team| team.id,
+                )?;
+            } else if entry.path_bytes().ends_with(b"/versions.csv") {
+                // The slash prevents matching other tables such as `default_versions.csv`
+                let mut newest: HashMap<u64, String> = HashMap::new();
+                let yanked: Vec<Version> = read_csv_data_filtered(entry, |v: &Version| {
+                    let newest = newest.entry(v.crate_id).or_default();
+                    // Timestamps in the dump all have the same format, so they sort chronologically
+                    if v.created_at > *newest {
+                        *newest = v.created_at.clone();
                     }
+                    v.yanked == "t"
+                })?;
+                cache_updater.store_multi_map(
+                    &mut self.yanked_versions,
+                    Self::YANKED_VERSIONS_FS,
+                    yanked.as_slice(),
+                    &|version| version.crate_id,
+                )?;
+                let last_published = newest
+                    .into_iter()
+                    .filter_map(|(id, created_at)| Some((id, normalize_timestamp(&created_at)?)))
+                    .collect();
+                cache_updater.store(
+                    &mut self.last_published,
+                    Self::LAST_PUBLISHED_FS,
+                    last_published,
+                )?;
+            } else if entry.path_bytes().ends_with(b"metadata.json") {
+                let meta: Metadata = serde_json::from_reader(entry)?;
+                cache_updater.store(
+                    &mut self.metadata,
+                    Self::METADATA_FS,
+                    MetadataStored {
+                        timestamp: meta.timestamp,
+                        etag: etag.clone(),
+                        last_modified: last_modified.clone(),
+                        checked: None,
+                    },
+                )?;
+            } else {
+                // This was not a file with a filename we actually use.
+                // Check if we've obtained all the files we need.
+                // If yes, we can skip decompressing the rest of the archive.
+                if required_files.is_subset(&cache_updater.staged_files) {
+                    break;
                 }
             }
         }
-        // Now that we've successfully downloaded and stored everything,
-        // replace the old cache contents with the new one.
-        cache_updater.commit()?;
+        Ok(())
+    }
 
-        // If we get here, we had no etag or the etag mismatched or we forced a download due to
-        // stale data. Catch the last as it means the crates.io daily dumps were not updated.
-        if remembered_etag == etag {
-            Ok(DownloadState::Stale)
-        } else {
-            Ok(DownloadState::Expired)
+    /// Checks the state of the cache, and disables it unless it's fresh.
+    pub fn expire(&mut self, max_age: Duration) -> CacheState {
+        let state = self.state(max_age);
+        match state {
+            CacheState::Fresh => (),
+            CacheState::Expired | CacheState::Unknown => self.disable(),
         }
+        state
     }
 
-    pub fn expire(&mut self, max_age: Duration) -> CacheState {
+    /// Makes all lookups miss, as if the cache didn't exist
+    pub fn disable(&mut self) {
+        self.cache_dir = None;
+    }
+
+    /// Checks the state of the cache without disabling it, so that expired data can still be used.
+    pub fn state(&mut self, max_age: Duration) -> CacheState {
         match self.validate(max_age) {
             // Still fresh.
             Some(true) => CacheState::Fresh,
             // There was no valid meta data. Consider expired for safety.
-            None => {
-                self.cache_dir = None;
-                CacheState::Unknown
-            }
-            Some(false) => {
-                self.cache_dir = None;
-                CacheState::Expired
-            }
+            None => CacheState::Unknown,
+            Some(false) => CacheState::Expired,
         }
     }
 
+    /// When the cached dump was generated by crates.io
+    pub fn generated_at(&mut self) -> Option<std::time::SystemTime> {
+        Some(self.load_metadata()?.timestamp)
+    }
+
+    /// Time since the dump was generated by crates.io, however recently the server confirmed it's current
+    pub fn dump_age(&mut self) -> Option<Duration> {
+        self.load_metadata()?.timestamp.elapsed().ok()
+    }
+
     pub fn age(&mut self) -> Option<Duration> {
         match self.load_metadata() {
             Some(meta) => meta.age().ok(),
@@ -303,6 +594,8 @@ impl CratesCache {
                     login: user.gh_login.clone(),
                     name: user.name.clone(),
                     kind: PublisherKind::user,
+                    contact: None,
+                    total_crates: None,
                 })
             })
             .collect();
@@ -324,15 +617,104 @@ impl CratesCache {
                     login: team.login.clone(),
                     name: team.name.clone(),
                     kind: PublisherKind::team,
+                    contact: None,
+                    total_crates: None,
                 })
             })
             .collect();
         Some(publisher)
     }
 
+    /// Counts the crates on all of crates.io that each user and team can publish,
+    /// keyed by kind and ID. Returns `None` if the cache is missing or disabled.
+    pub fn owned_crate_counts(&mut self) -> Option<HashMap<(PublisherKind, u64), usize>> {
+        let mut counts = HashMap::new();
+        for owner in self.load_crate_owners()?.values().flatten() {
+            let kind = match owner.owner_kind {
+                0 => PublisherKind::user,
+                1 => PublisherKind::team,
+                _ => continue,
+            };
+            *counts.entry((kind, owner.owner_id)).or_insert(0) += 1;
+        }
+        Some(counts)
+    }
+
+    /// Returns the current owners of the crate along with the time they were added,
+    /// leaving out the ones that published the first version.
+    /// Returns `None` if the crate is unknown, or if the cache predates tracking of these times.
+    pub fn owners_added(&mut self, crate_name: &str) -> Option<Vec<OwnershipChange>> {
+        let krate = self.load_crates()?.get(crate_name)?;
+        let id = krate.id;
+        let crate_created = parse_dump_timestamp(krate.created_at.as_deref()?)?;
+        let mut added = Vec::new();
+        for owner in self.load_crate_owners()?.get(&id)?.clone() {
+            let time = normalize_timestamp(owner.created_at.as_deref()?)?;
+            // Owners added along with the crate show up a moment after it was created
+            let owner_added = humantime::parse_rfc3339(&time).ok()?;
+            if owner_added
+                .duration_since(crate_created)
+                .map_or(true, |delay| delay < Duration::from_secs(60))
+            {
+                continue;
+            }
+            let publisher = match owner.owner_kind {
+                0 => self
+                    .load_users()?
+                    .get(&owner.owner_id)
+                    .map(|user| (PublisherKind::user, user.gh_login.clone())),
+                1 => self
+                    .load_teams()?
+                    .get(&owner.owner_id)
+                    .map(|team| (PublisherKind::team, team.login.clone())),
+                _ => None,
+            };
+            let (kind, login) = match publisher {
+                Some(publisher) => publisher,
+                None => continue,
+            };
+            added.push(OwnershipChange {
+                login,
+                kind,
+                added: true,
+                time,
+                noticed: false,
+            });
+        }
+        Some(added)
+    }
+
+    /// Returns `None` if the crate is unknown, or if the cache predates tracking of yanked versions
+    pub fn yanked_versions(&mut self, crate_name: &str) -> Option<Vec<String>> {
+        let id = self.load_crates()?.get(crate_name)?.id;
+        let versions = self.load_yanked_versions()?.get(&id);
+        Some(versions.map_or_else(Vec::new, |versions| {
+            versions.iter().map(|v| v.num.clone()).collect()
+        }))
+    }
+
+    /// Returns the time the newest version of the crate was published, in RFC 3339
+    pub fn last_published(&mut self, crate_name: &str) -> Option<String> {
+        let id = self.load_crates()?.get(crate_name)?.id;
+        self.load_last_published()?.get(&id).cloned()
+    }
+
+    /// Returns false if the cache predates tracking of publication times
+    pub fn tracks_last_published(&mut self) -> bool {
+        self.load_last_published().is_some()
+    }
+
     fn validate(&mut self, max_age: Duration) -> Option<bool> {
         let meta = self.load_metadata()?;
-        meta.validate(max_age)
+        let valid = meta.validate(max_age);
+        if let Ok(age) = meta.age() {
+            log::debug!(
+                "The cached dump is {} old, and is considered fresh for {}",
+                humantime::format_duration(Duration::from_secs(age.as_secs())),
+                humantime::format_duration(max_age)
+            );
+        }
+        valid
     }
 
     fn load_metadata(&mut self) -> Option<&MetadataStored> {
@@ -370,14 +752,46 @@ impl CratesCache {
             .ok()
     }
 
-    fn load_versions(&mut self) -> Option<&HashMap<(u64, String), Publisher>> {
+    fn load_yanked_versions(&mut self) -> Option<&HashMap<u64, Vec<Version>>> {
+        self.cache_dir
+            .as_ref()?
+            .load_cached(&mut self.yanked_versions, Self::YANKED_VERSIONS_FS)
+            .ok()
+    }
+
+    fn load_last_published(&mut self) -> Option<&HashMap<u64, String>> {
         self.cache_dir
             .as_ref()?
-            .load_cached(&mut self.versions, Self::VERSIONS_FS)
+            .load_cached(&mut self.last_published, Self::LAST_PUBLISHED_FS)
             .ok()
     }
 }
 
+fn parse_dump_timestamp(raw: &str) -> Option<std::time::SystemTime> {
+    humantime::parse_rfc3339(&normalize_timestamp(raw)?).ok()
+}
+
+/// Like `read_csv_data`, but only keeps the records matching `filter`,
+/// so that huge tables don't have to fit in memory all at once
+fn read_csv_data_filtered<T: serde::de::DeserializeOwned>(
+    from: impl io::Read,
+    mut filter: impl FnMut(&T) -> bool,
+) -> Result<Vec<T>, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b',')
+        .double_quote(true)
+        .quoting(true)
+        .from_reader(from);
+    let mut result = Vec::new();
+    for record in reader.deserialize() {
+        let record: T = record?;
+        if filter(&record) {
+            result.push(record);
+        }
+    }
+    Ok(result)
+}
+
 fn read_csv_data<T: serde::de::DeserializeOwned>(
     from: impl io::Read,
 ) -> Result<Vec<T>, csv::Error> {
@@ -389,20 +803,131 @@ fn read_csv_data<T: serde::de::DeserializeOwned>(
     reader.deserialize().collect()
 }
 
+/// Checks that the archive decompresses fully, which also verifies its checksum
+fn verify_archive(path: &Path) -> Result<(), io::Error> {
+    let reader = io::BufReader::new(fs::File::open(path)?);
+    io::copy(&mut GzDecoder::new(reader), &mut io::sink())?;
+    Ok(())
+}
+
+/// A data dump download in progress, kept next to the cache so that it can be resumed.
+/// The ETag of the dump being downloaded is stored alongside it,
+/// so that the rest of a different dump is never appended to it.
+struct PartialDownload {
+    path: PathBuf,
+    etag_path: PathBuf,
+}
+
+impl PartialDownload {
+    fn new(dir: &Path) -> Self {
+        let name = CratesCache::DUMP_FS;
+        PartialDownload {
+            path: dir.join(format!("{}.part", name)),
+            etag_path: dir.join(format!("{}.etag", name)),
+        }
+    }
+
+    /// Returns the length downloaded so far and the ETag of the dump, if it can be resumed
+    fn resumable(&self) -> Option<(u64, String)> {
+        let len = fs::metadata(&self.path).ok()?.len();
+        let etag = fs::read_to_string(&self.etag_path).ok()?;
+        if len == 0 || etag.is_empty() {
+            return None;
+        }
+        Some((len, etag))
+    }
+
+    /// Opens the file for writing, keeping the first `offset` bytes
+    fn open(&self, offset: u64, etag: Option<&str>) -> Result<fs::File, io::Error> {
+        // Without an ETag there is no way to tell whether the dump has changed, so don't resume it
+        match etag {
+            Some(etag) => fs::write(&self.etag_path, etag)?,
+            None => self.remove_etag()?,
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&self.path)?;
+        file.set_len(offset)?;
+        file.seek(io::SeekFrom::End(0))?;
+        Ok(file)
+    }
+
+    /// Moves the completed download to `destination`
+    fn finish(&self, destination: &Path) -> Result<(), io::Error> {
+        fs::rename(&self.path, destination)?;
+        self.remove_etag()
+    }
+
+    fn discard(&self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = self.remove_etag();
+    }
+
+    fn remove_etag(&self) -> Result<(), io::Error> {
+        match fs::remove_file(&self.etag_path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl MetadataStored {
     fn validate(&self, max_age: Duration) -> Option<bool> {
+        // Nothing is fresh for no time at all, even if the clock has changed since it was fetched
+        if max_age.is_zero() {
+            return Some(false);
+        }
         match self.age() {
             Ok(duration) => Some(duration < max_age),
             Err(_) => None,
         }
     }
 
+    /// Time since the dump was created, or since the server last confirmed it's still current
     pub fn age(&self) -> Result<Duration, SystemTimeError> {
-        self.timestamp.elapsed()
+        match self.checked {
+            Some(checked) if checked > self.timestamp => checked.elapsed(),
+            _ => self.timestamp.elapsed(),
+        }
+    }
+}
+
+/// Tables taken from the dump are stored compressed, since they take up hundreds of megabytes otherwise.
+/// The metadata is tiny, and stays uncompressed so that it's easy to inspect.
+fn stored_name(file: &str) -> String {
+    if file == CratesCache::METADATA_FS {
+        file.to_owned()
+    } else {
+        format!("{}.gz", file)
     }
 }
 
+/// JSON compresses well even at the fastest level, which keeps `update` quick
+const COMPRESSION: Compression = Compression::fast();
+
+/// Whether both ETags identify the same dump. Without one, a server tells nothing about it.
+fn is_same_dump(remembered_etag: &Option<String>, etag: &Option<String>) -> bool {
+    etag.is_some() && remembered_etag == etag
+}
+
 impl CacheDir {
+    /// Compresses a table written uncompressed by an older version, replacing it.
+    /// On failure, such as in a read-only cache directory, the uncompressed table is left as is.
+    fn migrate(&self, file: &str) -> Result<(), io::Error> {
+        let source = self.0.join(file);
+        let destination = self.0.join(stored_name(file));
+        let part = destination.with_extension("part");
+        log::debug!("Compressing {}", source.display());
+        let mut reader = io::BufReader::new(fs::File::open(&source)?);
+        let mut encoder = GzEncoder::new(io::BufWriter::new(fs::File::create(&part)?), COMPRESSION);
+        io::copy(&mut reader, &mut encoder)?;
+        encoder.finish()?.flush()?;
+        fs::rename(&part, &destination)?;
+        fs::remove_file(&source)
+    }
+
     fn load_cached<'cache, T>(
         &self,
         cache: &'cache mut Option<T>,
@@ -414,9 +939,30 @@ impl CacheDir {
         match cache {
             Some(datum) => Ok(datum),
             None => {
-                let file = fs::File::open(self.0.join(file))?;
-                let reader = io::BufReader::new(file);
-                let crates: T = serde_json::from_reader(reader).unwrap();
+                let mut path = self.0.join(stored_name(file));
+                let compressed = path != self.0.join(file);
+                if compressed && !path.exists() && self.0.join(file).exists() {
+                    if let Err(e) = self.migrate(file) {
+                        log::debug!("Could not compress {}: {}", file, e);
+                        path = self.0.join(file);
+                    }
+                }
+                log::debug!("Loading {}", path.display());
+                let opened = fs::File::open(&path).map_err(|e| {
+                    log::debug!("Could not open {}: {}", path.display(), e);
+                    e
+                })?;
+                let reader: Box<dyn io::Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+                    Box::new(io::BufReader::new(GzDecoder::new(io::BufReader::new(
+                        opened,
+                    ))))
+                } else {
+                    Box::new(io::BufReader::new(opened))
+                };
+                let crates: T = serde_json::from_reader(reader).map_err(|e| {
+                    log::debug!("Ignoring {}, it is not valid: {}", path.display(), e);
+                    io::Error::new(ErrorKind::InvalidData, e)
+                })?;
                 Ok(cache.get_or_insert(crates))
             }
         }
@@ -430,19 +976,39 @@ struct CacheUpdater {
     staged_files: BTreeSet<String>,
 }
 
-/// Creates the cache directory if it doesn't exist.
-/// Returns an error if creation fails.
-impl CacheUpdater {
-    fn new(dir: PathBuf) -> Result<Self, io::Error> {
-        if !dir.exists() {
-            fs::create_dir_all(&dir)?;
-        }
-
-        if !dir.is_dir() {
-            // Well. We certainly don't want to delete anything.
-            return Err(io::ErrorKind::AlreadyExists.into());
-        }
+/// Creates the cache directory if needed and checks that files can be written to it,
+/// so that a misconfigured location is reported before any work is done
+pub(crate) fn prepare_cache_dir(dir: &Path) -> Result<(), Error> {
+    let describe = |e: io::Error, problem: &str| {
+        Error::Cache(format!(
+            "The cache directory {} {}: {}\n  Set {} to use a different location.",
+            dir.display(),
+            problem,
+            e,
+            CratesCache::CACHE_DIR_ENV
+        ))
+    };
+    if !dir.exists() {
+        fs::create_dir_all(dir).map_err(|e| describe(e, "could not be created"))?;
+    }
+    if !dir.is_dir() {
+        // Well. We certainly don't want to delete anything.
+        return Err(describe(
+            io::ErrorKind::AlreadyExists.into(),
+            "is not a directory",
+        ));
+    }
+    let probe = dir.join(".write-test");
+    fs::File::create(&probe).map_err(|e| describe(e, "is not writable"))?;
+    fs::remove_file(&probe)?;
+    Ok(())
+}
 
+impl CacheUpdater {
+    /// Creates the cache directory if it doesn't exist.
+    /// Returns an error if creation fails.
+    fn new(dir: PathBuf) -> Result<Self, Error> {
+        prepare_cache_dir(&dir)?;
         Ok(Self {
             dir,
             staged_files: BTreeSet::new(),
@@ -451,12 +1017,17 @@ impl CacheUpdater {
 
     /// Commits to disk any changes that you have staged via the `store()` function.
     fn commit(&mut self) -> io::Result<()> {
-        let mut uncommitted_files = mem::replace(&mut self.staged_files, BTreeSet::new());
+        let mut uncommitted_files = mem::take(&mut self.staged_files);
         let metadata_file = uncommitted_files.take(CratesCache::METADATA_FS);
         for file in uncommitted_files {
-            let source = self.dir.join(&file).with_extension("part");
-            let destination = self.dir.join(&file);
+            let destination = self.dir.join(stored_name(&file));
+            let source = destination.with_extension("part");
             fs::rename(source, destination)?;
+            // Left behind by a version that stored tables uncompressed
+            match fs::remove_file(self.dir.join(&file)) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
         }
         // metadata_file is special since it contains the timestamp for the cache.
         // We will only commit it and update the timestamp if updating everything else succeeds.
@@ -479,10 +1050,15 @@ impl CacheUpdater {
         let value = cache.get_or_insert(value);
 
         self.staged_files.insert(file.to_owned());
-        let out_path = self.dir.join(file).with_extension("part");
-        let out_file = fs::File::create(out_path)?;
-        let out = io::BufWriter::new(out_file);
-        serde_json::to_writer(out, value)?;
+        let out_path = self.dir.join(stored_name(file)).with_extension("part");
+        let out_file = io::BufWriter::new(fs::File::create(out_path)?);
+        if file == CratesCache::METADATA_FS {
+            serde_json::to_writer(out_file, value)?;
+        } else {
+            let mut out = GzEncoder::new(out_file, COMPRESSION);
+            serde_json::to_writer(&mut out, value)?;
+            out.finish()?.flush()?;
+        }
         Ok(())
     }
 
@@ -526,3 +1102,43 @@ impl CacheUpdater {
         self.store(cache, file, hashed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn dumps_without_an_etag_are_not_the_same() {
+        let etag = Some("\"abc\"".to_owned());
+        assert!(is_same_dump(&etag, &etag));
+        assert!(!is_same_dump(&etag, &Some("\"def\"".to_owned())));
+        assert!(!is_same_dump(&None, &etag));
+        assert!(!is_same_dump(&None, &None));
+    }
+
+    fn dump_created(ago: Duration) -> MetadataStored {
+        MetadataStored {
+            timestamp: SystemTime::now() - ago,
+            etag: None,
+            last_modified: None,
+            checked: None,
+        }
+    }
+
+    #[test]
+    fn a_zero_max_age_expires_a_dump_created_a_moment_ago() {
+        let dump = dump_created(Duration::ZERO);
+        assert_eq!(dump.validate(Duration::ZERO), Some(false));
+        assert_eq!(dump.validate(Duration::from_secs(3600)), Some(true));
+    }
+
+    #[test]
+    fn a_dump_confirmed_recently_is_fresh() {
+        let mut dump = dump_created(Duration::from_secs(7 * 24 * 3600));
+        assert_eq!(dump.validate(Duration::from_secs(3600)), Some(false));
+        dump.checked = Some(SystemTime::now());
+        assert_eq!(dump.validate(Duration::from_secs(3600)), Some(true));
+        assert_eq!(dump.validate(Duration::ZERO), Some(false));
+    }
+}
@@ -0,0 +1,106 @@
+//! Output of the `json` subcommand as a CycloneDX 1.6 SBOM, for tools that consume those
+//! rather than the bespoke JSON.
+//!
+//! Each version of a crates.io crate in the dependency graph is a component, identified by its
+//! package URL. Its `authors` are the users and teams that can publish it, and its `supplier`
+//! is crates.io. The kind of each publisher is kept in the component's `properties`,
+//! since CycloneDX has no notion of teams. Crates from elsewhere are left out.
+
+use crate::publishers::PublisherKind;
+use crate::subcommands::json::StructuredOutput;
+use serde_json::{json, Value};
+
+const SPEC_VERSION: &str = "1.6";
+
+/// Prefix of the names of the properties this tool sets, as CycloneDX recommends
+const PROPERTY_NAMESPACE: &str = "cargo-supply-chain";
+
+/// Converts `output` into a complete CycloneDX document.
+/// It has no serial number or timestamp, so that the same dependency graph gives the same document.
+pub(crate) fn render(output: &StructuredOutput) -> Value {
+    let mut components = Vec::new();
+    for (crate_name, versions) in &output.crate_versions {
+        let publishers = output.crates_io_crates.get(crate_name);
+        let authors: Vec<Value> = publishers
+            .into_iter()
+            .flatten()
+            .map(|publisher| json!({ "name": publisher.login }))
+            .collect();
+        let mut properties: Vec<Value> = publishers
+            .into_iter()
+            .flatten()
+            .map(|publisher| {
+                let kind = match publisher.kind {
+                    PublisherKind::user => "user",
+                    PublisherKind::team => "team",
+                };
+                property(&format!("publisher:{}", kind), &publisher.login)
+            })
+            .collect();
+        if let Some(error) = output.lookup_errors.get(crate_name) {
+            properties.push(property("lookup-error", error));
+        }
+        if output.direct_dependencies.contains(crate_name) {
+            properties.push(property("direct-dependency", "true"));
+        }
+        if output.build_time.get(crate_name) == Some(&true) {
+            properties.push(property("build-time", "true"));
+        }
+        if output.ownerless.get(crate_name) == Some(&true) {
+            properties.push(property("ownerless", "true"));
+        }
+        for version in versions {
+            let purl = format!("pkg:cargo/{}@{}", crate_name, version.version);
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "name": crate_name,
+                "version": version.version,
+                "purl": purl,
+                "supplier": {
+                    "name": "crates.io",
+                    "url": ["https://crates.io"],
+                },
+                "authors": authors,
+                "externalReferences": [{
+                    "type": "distribution",
+                    "url": format!("https://crates.io/crates/{}/{}", crate_name, version.version),
+                }],
+                "properties": properties,
+            });
+            if let Some(license) = &version.license {
+                component["licenses"] = json!([{ "expression": license }]);
+            }
+            if version.yanked {
+                component["properties"]
+                    .as_array_mut()
+                    .expect("properties is an array")
+                    .push(property("yanked", "true"));
+            }
+            components.push(component);
+        }
+    }
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": SPEC_VERSION,
+        "version": 1,
+        "metadata": {
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+        },
+        "components": components,
+    })
+}
+
+fn property(name: &str, value: &str) -> Value {
+    json!({
+        "name": format!("{}:{}", PROPERTY_NAMESPACE, name),
+        "value": value,
+    })
+}
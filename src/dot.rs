@@ -0,0 +1,98 @@
+//! Graphviz DOT rendering of the output of the `json` subcommand, as a bipartite graph
+//! linking each crates.io crate to the users and teams that can publish it.
+//!
+//! Render it with `dot -Tsvg`. Publishers of many crates stand out as hubs,
+//! and their labels tell how many crates they can publish.
+
+use crate::publishers::PublisherKind;
+use crate::subcommands::json::StructuredOutput;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Renders `output` as a complete DOT graph
+pub(crate) fn render(output: &StructuredOutput) -> String {
+    let mut crate_counts: BTreeMap<(PublisherKind, &str), usize> = BTreeMap::new();
+    for crate_publishers in output.crates_io_crates.values() {
+        for publisher in crate_publishers {
+            *crate_counts
+                .entry((publisher.kind, &publisher.login))
+                .or_default() += 1;
+        }
+    }
+
+    let mut dot = String::new();
+    dot.push_str("digraph supply_chain {\n");
+    dot.push_str("  graph [rankdir=LR, overlap=false];\n");
+    dot.push_str("  node [fontname=\"sans-serif\", style=filled];\n");
+    dot.push_str("  edge [color=\"#999999\"];\n\n");
+
+    for crate_name in output.crates_io_crates.keys() {
+        let _ = writeln!(
+            dot,
+            "  {} [label={}, shape=box, fillcolor=\"#f4f4f4\"];",
+            quote(&crate_node(crate_name)),
+            quote(crate_name)
+        );
+    }
+    dot.push('\n');
+    for ((kind, login), count) in &crate_counts {
+        let fillcolor = match kind {
+            PublisherKind::user => "#cfe2f3",
+            PublisherKind::team => "#d9ead3",
+        };
+        let label = format!(
+            "{}\n{} crate{}",
+            login,
+            count,
+            if *count == 1 { "" } else { "s" }
+        );
+        let _ = writeln!(
+            dot,
+            "  {} [label={}, shape=ellipse, fillcolor=\"{}\"];",
+            quote(&publisher_node(*kind, login)),
+            quote(&label),
+            fillcolor
+        );
+    }
+    dot.push('\n');
+    for (crate_name, crate_publishers) in &output.crates_io_crates {
+        for publisher in crate_publishers {
+            let _ = writeln!(
+                dot,
+                "  {} -> {};",
+                quote(&crate_node(crate_name)),
+                quote(&publisher_node(publisher.kind, &publisher.login))
+            );
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// IDs are prefixed with the kind of node, since a crate may share its name with a user
+fn crate_node(crate_name: &str) -> String {
+    format!("crate:{}", crate_name)
+}
+
+fn publisher_node(kind: PublisherKind, login: &str) -> String {
+    match kind {
+        PublisherKind::user => format!("user:{}", login),
+        PublisherKind::team => format!("team:{}", login),
+    }
+}
+
+/// Quotes `text` as a DOT string, in which newlines are line breaks of the label
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,117 @@
+//! The error type returned by this crate, which tells the kinds of failures apart
+//! for callers that handle some of them differently from others:
+//!
+//! ```no_run
+//! use cargo_supply_chain::{resolve_publishers, Error, MetadataArgs, RateLimitedClient};
+//! use std::time::Duration;
+//!
+//! let client = RateLimitedClient::new();
+//! match resolve_publishers(&MetadataArgs::default(), &client, Duration::from_secs(48 * 3600)) {
+//!     Ok(crates) => println!("{} crates", crates.len()),
+//!     Err(e @ Error::Network(_)) | Err(e @ Error::TimedOut { .. }) => {
+//!         eprintln!("Try again later: {}", e)
+//!     }
+//!     Err(e) => eprintln!("{}", e),
+//! }
+//! ```
+
+use std::{fmt, io, sync::Arc, time::Duration};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An argument is invalid, such as an `--exclude` pattern that is not a valid regular expression
+    InvalidInput(String),
+    /// `cargo metadata` could not be run, or it failed
+    MetadataFailed(String),
+    /// crates.io or the mirror could not be reached or returned an error, after any retries
+    Network(Box<ureq::Error>),
+    /// A request kept going unanswered for longer than the request timeout, after any retries
+    TimedOut {
+        url: String,
+        timeout: Duration,
+        error: Box<ureq::Error>,
+    },
+    /// The publishers of these crates could not be looked up, for the given reasons.
+    /// The lookups of the other crates succeeded.
+    LookupsFailed(Vec<(String, Arc<Error>)>),
+    /// The local cache could not be written, or it lacks data needed offline
+    Cache(String),
+    /// A file such as a trust file, a lockfile or a saved output of `cargo metadata` is malformed,
+    /// or so is a response from crates.io
+    Parse(String),
+    /// The output is complete, but exceeds a limit such as `--max-publishers`
+    ThresholdExceeded(String),
+    /// The dependency graph has no crates.io crates to analyze, such as that of the wrong manifest
+    EmptyGraph(String),
+    /// Any failure not covered by a more specific variant, such as a file that can't be read
+    Io(io::Error),
+}
+
+impl Error {
+    /// Whether the registry answered that there is no such crate
+    pub(crate) fn is_not_found(&self) -> bool {
+        matches!(self, Error::Network(error) if matches!(**error, ureq::Error::Status(404, _)))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidInput(message)
+            | Error::MetadataFailed(message)
+            | Error::Cache(message)
+            | Error::Parse(message)
+            | Error::ThresholdExceeded(message)
+            | Error::EmptyGraph(message) => f.write_str(message),
+            Error::Network(error) => error.fmt(f),
+            Error::TimedOut { url, timeout, .. } => write!(
+                f,
+                "{}: no response within {}. The connection may be slow or down; try again later or raise --request-timeout",
+                url,
+                humantime::format_duration(*timeout)
+            ),
+            Error::LookupsFailed(errors) => {
+                let count = errors.len();
+                let plural = if count == 1 { "" } else { "s" };
+                write!(f, "Failed to fetch publishers of {} crate{}:", count, plural)?;
+                for (crate_name, error) in errors {
+                    write!(f, "\n  {}: {}", crate_name, error)?;
+                }
+                Ok(())
+            }
+            Error::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // Already part of the message
+            Error::Network(error) => error.source(),
+            Error::Io(error) => error.source(),
+            Error::TimedOut { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Only used for writing the output, so any failure is one of the output
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Io(error.into())
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(error: csv::Error) -> Self {
+        Error::Io(error.into())
+    }
+}
@@ -0,0 +1,95 @@
+//! Looks up the members of the GitHub teams that own crates, for `publishers --expand-teams`,
+//! so that the publishers are counted as the people behind the teams.
+//!
+//! GitHub only shows the members of a team to members of its organization, even if their
+//! membership of the organization is public. The requests are therefore authenticated with
+//! the token in `GITHUB_TOKEN`, and none are made without one. Teams whose members can't be
+//! listed are left as they are.
+
+use crate::api_client::RateLimitedClient;
+use serde::Deserialize;
+
+/// Environment variable holding a GitHub token, as set by GitHub Actions
+pub(crate) const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// Environment variable pointing at the API of a GitHub Enterprise server, as set by GitHub Actions
+const GITHUB_API_URL_ENV: &str = "GITHUB_API_URL";
+const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+
+/// The most members GitHub returns at once
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+pub(crate) struct TeamMember {
+    pub id: u64,
+    pub login: String,
+}
+
+/// Lists the members of the team with the crates.io login `github:ORG:TEAM`.
+/// Returns why they couldn't be listed otherwise.
+pub(crate) fn team_members(
+    client: &RateLimitedClient,
+    team_login: &str,
+) -> Result<Vec<TeamMember>, String> {
+    let (org, team) = match team_login.split(':').collect::<Vec<_>>()[..] {
+        ["github", org, team] => (org, team),
+        _ => return Err("it is not a GitHub team".to_string()),
+    };
+    let token = github_token().ok_or_else(|| {
+        format!(
+            "GitHub only shows them to members of {}. Set {} to a token of one to list them.",
+            org, GITHUB_TOKEN_ENV
+        )
+    })?;
+    let api_url = std::env::var(GITHUB_API_URL_ENV)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_GITHUB_API_URL.to_owned());
+
+    let mut members = Vec::new();
+    for page in 1.. {
+        let url = format!(
+            "{}/orgs/{}/teams/{}/members?per_page={}&page={}",
+            api_url.trim_end_matches('/'),
+            org,
+            team,
+            PAGE_SIZE,
+            page
+        );
+        let response = client
+            .get(&url)
+            .set("Accept", "application/vnd.github+json")
+            .set("Authorization", &format!("Bearer {}", token))
+            .call()
+            .map_err(|error| match error {
+                ureq::Error::Status(401, _) => {
+                    format!("GitHub rejected the token in {}", GITHUB_TOKEN_ENV)
+                }
+                // GitHub answers 404 for teams that exist but aren't visible with the token
+                ureq::Error::Status(403, _) | ureq::Error::Status(404, _) => format!(
+                    "the token in {} can't see the team, or it doesn't exist",
+                    GITHUB_TOKEN_ENV
+                ),
+                error => format!("failed to fetch {}: {}", url, error),
+            })?;
+        let page: Vec<TeamMember> = response
+            .into_json()
+            .map_err(|e| format!("failed to read the members from {}: {}", url, e))?;
+        let last = page.len() < PAGE_SIZE;
+        members.extend(page);
+        if last {
+            break;
+        }
+    }
+    if members.is_empty() {
+        return Err("the team has no members".to_string());
+    }
+    Ok(members)
+}
+
+fn github_token() -> Option<String> {
+    std::env::var(GITHUB_TOKEN_ENV)
+        .ok()
+        .map(|token| token.trim().to_owned())
+        .filter(|token| !token.is_empty())
+}
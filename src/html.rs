@@ -0,0 +1,262 @@
+//! Standalone HTML report built from the output of the `json` subcommand,
+//! for sharing a review with people who won't run the tool themselves.
+//!
+//! Styles and the script sorting the table are inlined, and nothing is loaded from elsewhere,
+//! so the file can be opened straight from disk or attached to an email.
+
+use crate::publishers::PublisherKind;
+use crate::subcommands::json::StructuredOutput;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 70em; padding: 0 1em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.cards { display: flex; flex-wrap: wrap; gap: 1em; margin: 1.5em 0; }
+.card { flex: 1 1 10em; border: 1px solid #ccc; border-radius: 6px; padding: 0.8em 1em; }
+.card .value { font-size: 2em; font-weight: bold; }
+.card .label { color: #555; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; }
+th { cursor: pointer; user-select: none; background: #f4f4f4; }
+th::after { content: ' \\2195'; color: #999; }
+tr.single-owner td:first-child { border-left: 4px solid #d9822b; }
+.yanked { color: #b00020; }
+details { border: 1px solid #ddd; border-radius: 6px; padding: 0.4em 0.8em; margin: 0.4em 0; }
+summary { cursor: pointer; }
+.muted { color: #666; }
+";
+
+/// Sorts the table by the clicked column, descending on a second click.
+/// Cells with a `data-sort` attribute are sorted by it, numerically if all of them are numbers.
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable th').forEach(function (th, column) {
+  th.addEventListener('click', function () {
+    var tbody = th.closest('table').tBodies[0];
+    var rows = Array.prototype.slice.call(tbody.rows);
+    var descending = th.dataset.order === 'asc';
+    th.dataset.order = descending ? 'desc' : 'asc';
+    var key = function (row) {
+      var cell = row.cells[column];
+      return cell.dataset.sort !== undefined ? cell.dataset.sort : cell.textContent.trim();
+    };
+    var numeric = rows.every(function (row) { return key(row) !== '' && !isNaN(key(row)); });
+    rows.sort(function (a, b) {
+      var x = key(a), y = key(b);
+      var order = numeric ? x - y : x.localeCompare(y);
+      return descending ? -order : order;
+    });
+    rows.forEach(function (row) { tbody.appendChild(row); });
+  });
+});
+";
+
+struct PublisherEntry<'a> {
+    /// Display name, which is not necessarily unique
+    name: Option<&'a str>,
+    crates: Vec<&'a str>,
+}
+
+/// Renders `output` as a complete HTML document
+pub(crate) fn render(output: &StructuredOutput) -> String {
+    let mut publishers: BTreeMap<(PublisherKind, &str), PublisherEntry> = BTreeMap::new();
+    for (crate_name, crate_publishers) in &output.crates_io_crates {
+        for publisher in crate_publishers {
+            publishers
+                .entry((publisher.kind, &publisher.login))
+                .or_insert_with(|| PublisherEntry {
+                    name: publisher.name.as_deref(),
+                    crates: Vec::new(),
+                })
+                .crates
+                .push(crate_name);
+        }
+    }
+    let single_owner = |crate_name: &str| {
+        let crate_publishers = &output.crates_io_crates[crate_name];
+        crate_publishers.len() == 1 && crate_publishers[0].kind == PublisherKind::user
+    };
+    let not_audited = &output.not_audited;
+    let total_crates = output.crates_io_crates.len()
+        + not_audited.local_crates.len()
+        + not_audited.foreign_crates.len();
+    let single_owner_crates = output
+        .crates_io_crates
+        .keys()
+        .filter(|crate_name| single_owner(crate_name))
+        .count();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Supply chain report</title>\n");
+    let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+    html.push_str("<h1>Supply chain report</h1>\n");
+    let _ = writeln!(
+        html,
+        "<p class=\"muted\">Generated by cargo-supply-chain {}. Publishers are the people and teams that can publish new versions of a crate to crates.io.</p>",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    html.push_str("<div class=\"cards\">\n");
+    for (label, value) in &[
+        ("Total crates", total_crates),
+        ("From crates.io", output.crates_io_crates.len()),
+        ("Distinct publishers", publishers.len()),
+        ("Single-owner crates", single_owner_crates),
+    ] {
+        let _ = writeln!(
+            html,
+            "<div class=\"card\"><div class=\"value\">{}</div><div class=\"label\">{}</div></div>",
+            value, label
+        );
+    }
+    html.push_str("</div>\n");
+
+    html.push_str("<h2>Crates</h2>\n");
+    html.push_str("<p class=\"muted\">Click a column heading to sort by it. Crates that a single individual can publish are marked on the left.</p>\n");
+    html.push_str("<table class=\"sortable\">\n<thead><tr><th>Crate</th><th>Versions</th><th>Publishers</th><th>Published by</th><th>Dependency</th><th>Last published</th></tr></thead>\n<tbody>\n");
+    for (crate_name, crate_publishers) in &output.crates_io_crates {
+        let versions: Vec<String> = output
+            .crate_versions
+            .get(crate_name)
+            .into_iter()
+            .flatten()
+            .map(|v| {
+                if v.yanked {
+                    format!(
+                        "<span class=\"yanked\">{} (yanked)</span>",
+                        escape(&v.version)
+                    )
+                } else {
+                    escape(&v.version)
+                }
+            })
+            .collect();
+        let mut published_by: Vec<String> = crate_publishers
+            .iter()
+            .map(|p| publisher_link(p.kind, &p.login))
+            .collect();
+        if output.ownerless.get(crate_name) == Some(&true) {
+            published_by
+                .push("<span class=\"yanked\">nobody, no owners are left</span>".to_string());
+        }
+        let mut dependency = if output.direct_dependencies.contains(crate_name) {
+            "direct"
+        } else {
+            "transitive"
+        }
+        .to_string();
+        if output.build_time.get(crate_name) == Some(&true) {
+            dependency.push_str(", build time");
+        }
+        let last_published = output
+            .last_published
+            .get(crate_name)
+            .map(String::as_str)
+            .unwrap_or("");
+        let _ = writeln!(
+            html,
+            "<tr{}><td><a href=\"https://crates.io/crates/{name}\">{name}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td data-sort=\"{}\">{}</td></tr>",
+            if single_owner(crate_name) {
+                " class=\"single-owner\""
+            } else {
+                ""
+            },
+            versions.join(", "),
+            crate_publishers.len(),
+            published_by.join(", "),
+            dependency,
+            escape(last_published),
+            escape(last_published.get(..10).unwrap_or(last_published)),
+            name = escape(crate_name),
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    html.push_str("<h2>Publishers</h2>\n");
+    html.push_str("<p class=\"muted\">Each of them can publish new versions of the crates listed under their name. Any member of a team can publish on its behalf.</p>\n");
+    let mut by_crate_count: Vec<_> = publishers.iter().collect();
+    // Most crates first; the map is already ordered by kind and login, which breaks ties
+    by_crate_count.sort_by_key(|(_, entry)| usize::MAX - entry.crates.len());
+    for ((kind, login), PublisherEntry { name, crates }) in by_crate_count {
+        let display_name = match name {
+            Some(name) if name != login => {
+                format!(" <span class=\"muted\">({})</span>", escape(name))
+            }
+            _ => String::new(),
+        };
+        let _ = writeln!(
+            html,
+            "<details><summary>{} {}{} &mdash; {} crate{}</summary>\n<ul>",
+            kind_name(*kind),
+            publisher_link(*kind, login),
+            display_name,
+            crates.len(),
+            if crates.len() == 1 { "" } else { "s" }
+        );
+        for crate_name in crates {
+            let _ = writeln!(html, "<li>{}</li>", escape(crate_name));
+        }
+        html.push_str("</ul>\n</details>\n");
+    }
+
+    if !not_audited.local_crates.is_empty() || !not_audited.foreign_crates.is_empty() {
+        html.push_str("<h2>Not from crates.io</h2>\n");
+        html.push_str("<p class=\"muted\">These crates have no crates.io publishers, so they are not covered by this report.</p>\n<ul>\n");
+        for crate_name in &not_audited.local_crates {
+            let _ = writeln!(html, "<li>{} (local)</li>", escape(crate_name));
+        }
+        for crate_name in &not_audited.foreign_crates {
+            let source = match not_audited.alternative_registries.get(crate_name) {
+                Some(registry) => format!("registry {}", registry),
+                None => "git or other source".to_string(),
+            };
+            let _ = writeln!(
+                html,
+                "<li>{} ({})</li>",
+                escape(crate_name),
+                escape(&source)
+            );
+        }
+        html.push_str("</ul>\n");
+    }
+
+    let _ = writeln!(html, "<script>{}</script>\n</body>\n</html>", SCRIPT);
+    html
+}
+
+fn kind_name(kind: PublisherKind) -> &'static str {
+    match kind {
+        PublisherKind::user => "user",
+        PublisherKind::team => "team",
+    }
+}
+
+fn publisher_link(kind: PublisherKind, login: &str) -> String {
+    let path = match kind {
+        PublisherKind::user => "users",
+        PublisherKind::team => "teams",
+    };
+    format!(
+        "<a href=\"https://crates.io/{}/{}\">{}</a>",
+        path,
+        escape(login),
+        escape(login)
+    )
+}
+
+/// Escapes text for use in element content and quoted attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,816 @@
+//! Gather author, contributor, publisher data on crates in your dependency graph.
+//!
+//! There are some use cases:
+//!
+//! * Find people and groups worth supporting.
+//! * An analysis of all the contributors you implicitly trust by building their software. This
+//!   might have both a sobering and humbling effect.
+//! * Identify risks in your dependency graph.
+//!
+//! Besides the `cargo supply-chain` binary, this crate can be used as a library.
+//! [`resolve_publishers`] gathers the same data as the `crates` subcommand without printing anything:
+//!
+//! ```no_run
+//! use cargo_supply_chain::{resolve_publishers, MetadataArgs, RateLimitedClient};
+//! use std::time::Duration;
+//!
+//! let client = RateLimitedClient::new().with_request_timeout(Duration::from_secs(10));
+//! let max_age = Duration::from_secs(48 * 3600);
+//! for krate in resolve_publishers(&MetadataArgs::default(), &client, max_age)? {
+//!     println!("{}: {} publishers", krate.name, krate.publishers.len());
+//! }
+//! # Ok::<(), cargo_supply_chain::Error>(())
+//! ```
+
+#![forbid(unsafe_code)]
+
+use std::{
+    collections::BTreeSet,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+mod api_cache;
+mod api_client;
+mod common;
+mod config;
+mod crates_cache;
+mod cyclonedx;
+mod dot;
+mod error;
+mod github;
+mod html;
+mod lockfile;
+mod logger;
+mod published;
+mod publishers;
+mod sarif;
+#[doc(hidden)]
+pub mod subcommands;
+mod timing;
+mod trust;
+
+pub use api_client::{
+    token_from_env, RateLimitedClient, DEFAULT_REGISTRY_URL, DEFAULT_REQUEST_TIMEOUT, TOKEN_ENV,
+    UNAUTHENTICATED_RATE_LIMIT,
+};
+pub use common::{sourced_dependencies, PkgSource, SourcedPackage};
+#[doc(hidden)]
+pub use config::{Config, DEFAULT_CONFIG_FILE};
+pub use error::Error;
+#[doc(hidden)]
+pub use logger::init as init_logging;
+pub use publishers::{
+    fetch_owners_of_crates, publisher_teams, publisher_users, resolve_publishers, yanked_versions,
+    CrateOwners, CratePublishers, OwnershipChange, PublisherContact, PublisherData, PublisherKind,
+    PublishersMap, Verbosity,
+};
+#[doc(hidden)]
+pub use timing::{enable as enable_timing, report as report_timing};
+pub use trust::{FirstPartyOrgs, TrustList, DEFAULT_TRUST_FILE};
+
+/// CLI-focused help message for displaying to the user
+#[doc(hidden)]
+pub const CLI_HELP: &str =
+    "Usage: cargo supply-chain COMMAND [OPTIONS...] [-- CARGO_METADATA_OPTIONS...]
+
+Commands:
+  publishers   List all crates.io publishers in the depedency graph
+  crates       List all crates in dependency graph and crates.io publishers for each
+  json         Like 'crates', but in JSON and with more fields for each publisher
+  owners       List the teams that can publish crates in the dependency graph
+  risks        List crates that a single individual can publish, most depended upon first,
+               and crates whose publishers changed recently
+  stats        Summarize the dependency graph and its publishers in a few numbers
+  verify       Check that the publishers match a reviewed output of 'json'
+  why          Show the paths from the workspace to a crate, and who can publish it
+  diff         Compare two outputs of 'json' and list added or removed crates and publishers
+  update       Download the latest daily dump from crates.io to speed up other commands
+  clean        Delete the local cache, reporting how much disk space it frees
+
+See 'cargo supply-chain help <command>' for more information on a specific command.
+
+Arguments:
+  --cache-max-age  The cache will be considered valid while younger than specified.
+                   The format is a human readable duration such as `1w` or `1d 6h`.
+                   If not specified, the cache is considered valid for 48 hours.
+                   `0` always refreshes it: queries fetch live data for every crate,
+                   as with `--no-cache`, and 'update' downloads the dump again.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+
+Dependency kinds:
+  By default the graph covers the code that ends up in what you ship, and the code
+  that runs on your machine to build it:
+    normal     Always included, proc-macros among them
+    build      Included unless `--exclude-build-dependencies`
+    dev        Left out unless `--include-dev-dependencies`, since they are only
+               built for tests, examples and benchmarks
+  `--build-time-only` keeps only build-dependencies, proc-macros and what they depend on:
+  the code that runs with your privileges during the build.
+
+Any arguments after the `--` will be passed to `cargo metadata`, for example:
+  cargo supply-chain crates -- --filter-platform=x86_64-unknown-linux-gnu
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.
+
+Config file:
+  Defaults for common options can be set in `supply-chain.toml` in the current directory,
+  or in the user's config directory, such as `~/.config/cargo-supply-chain/` on Linux.
+  Options given on the command line take precedence over the file, for example:
+    cache-max-age = \"1w\"
+    target = [\"x86_64-unknown-linux-gnu\"]
+    exclude = [\"windows-*\"]
+    include-dev-dependencies = true
+  The other options accepted are exclude-build-dependencies, features, all-features,
+  no-default-features, requests-per-second, jobs, throttle, registry-url, request-timeout
+  and allow.
+  Settings in the current directory take precedence over those in the user's config
+  directory, including switches set to `false`. Any feature selection on the command line
+  replaces that of the files as a whole.
+
+Environment variables:
+  CARGO_SUPPLY_CHAIN_CACHE
+                   Directory to keep the cache in, created if it doesn't exist.
+                   Defaults to the platform's cache directory, such as
+                   `~/.cache/cargo-supply-chain` on Linux. Crates missing from the daily
+                   dump, such as those published since, are looked up on crates.io and
+                   kept there as well, so that later runs don't repeat the lookups.
+                   They are kept per crate rather than per version, since publishers
+                   are set for a crate as a whole, and expire after `--cache-max-age`.
+  CARGO_SUPPLY_CHAIN_TOKEN
+                   crates.io API token sent with every request to the API, to be allowed
+                   the higher limit on `--requests-per-second` that it was granted.
+                   It is never sent to hosts other than that of the registry.
+  GITHUB_TOKEN     GitHub token of a member of the organizations owning the crates,
+                   for 'publishers --expand-teams' to list the members of their teams.
+                   It is only sent to GitHub, never to crates.io.
+
+Exit codes:
+  0  Success
+  1  Any error not listed below
+  2  A check failed: a limit such as `--max-publishers` was exceeded, 'verify' found
+     unexpected publishers, or `--fail-on-yanked` found yanked versions.
+     The output is still printed.
+  3  Invalid command line arguments
+  4  Failed to fetch data from crates.io, or the publishers of a crate are missing
+     from the cache with `--offline`. By default, the run ends at the first such
+     crate and only it is listed. To list every crate whose publishers could not be
+     looked up, after the output for the others, pass `--keep-going`: an audit then
+     sees all of its gaps at once. Crates that were never published are not failures.
+  5  The dependency graph has no crates.io crates to analyze, which usually means
+     that the wrong manifest was given or that the filters left everything out.
+     Nothing is printed to stdout.";
+/// Arguments shared by all subcommands that query the dependency graph
+#[derive(Debug, Clone)]
+pub struct QueryCommandArgs {
+    pub cache_max_age: Duration,
+    pub diffable: bool,
+    /// Upper bound on the rate of requests to the crates.io API
+    pub requests_per_second: f64,
+    /// Number of crates to look up on crates.io concurrently
+    pub jobs: usize,
+    /// How many times a failed request to crates.io is retried
+    pub retries: u8,
+    /// Never access the network; rely entirely on the local cache
+    pub offline: bool,
+    /// Ignore the local cache and fetch live data for every crate
+    pub no_cache: bool,
+    /// Do not warn when the cached dump was generated longer ago than `cache_max_age`
+    pub allow_stale: bool,
+    /// Look up the other crates when one fails, and list the failures after the output
+    pub keep_going: bool,
+    /// Print nothing but the requested data to stdout, and nothing but warnings and errors to stderr
+    pub quiet: bool,
+    /// Do not warn about yanked versions in the dependency graph
+    pub include_yanked: bool,
+    /// Fail with `ExitCode::ThresholdExceeded` if the dependency graph contains yanked versions
+    pub fail_on_yanked: bool,
+    /// Warnings not to print, which don't fail the checks either
+    pub allow: Vec<WarningCategory>,
+    /// Publishers listed in this file are marked as trusted. Defaults to `supply-chain-trust.toml`.
+    pub trust_file: Option<PathBuf>,
+    /// Only show crates and publishers that are not in the trust file
+    pub untrusted_only: bool,
+    /// Crates that any of these organizations or teams can publish are first-party,
+    /// and left out of the risks
+    pub first_party_orgs: FirstPartyOrgs,
+    /// Leave first-party crates out of the output entirely
+    pub hide_first_party: bool,
+    /// Base URL of a crates.io mirror to use instead of crates.io
+    pub registry_url: Option<String>,
+    /// How long to wait for a response to each request
+    pub request_timeout: Duration,
+    /// Highlight text output with ANSI colors. Never set along with `diffable`.
+    pub color: bool,
+    pub metadata_args: MetadataArgs,
+}
+
+impl QueryCommandArgs {
+    /// Creates a crates.io API client configured according to these arguments
+    pub fn client(&self) -> RateLimitedClient {
+        let client = RateLimitedClient::new()
+            .with_rate_limit(self.requests_per_second)
+            .with_concurrency(self.jobs)
+            .with_retries(self.retries, Duration::from_secs(1))
+            .with_offline(self.offline)
+            .with_no_cache(self.no_cache)
+            .with_allow_stale(self.allow_stale || self.allows(WarningCategory::Stale))
+            .with_allow_ownerless(self.allows(WarningCategory::Ownerless))
+            .with_keep_going(self.keep_going)
+            .with_quiet(self.quiet)
+            .with_request_timeout(self.request_timeout);
+        match &self.registry_url {
+            Some(url) => client.with_registry_url(url),
+            None => client,
+        }
+    }
+
+    /// The progress bar would be noise in the output meant for tools
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.diffable {
+            Verbosity::Normal
+        } else {
+            Verbosity::Progress
+        }
+    }
+
+    /// Whether warnings of `category` were allowed with `--allow`
+    pub fn allows(&self, category: WarningCategory) -> bool {
+        self.allow.contains(&category)
+    }
+
+    /// With `fail_on_yanked`, fails with `ExitCode::ThresholdExceeded` if `yanked`,
+    /// a list of versions such as `serde 1.0.0`, is not empty, unless yanked versions are allowed
+    pub(crate) fn check_yanked(&self, yanked: &[String]) -> Result<(), Error> {
+        if !self.fail_on_yanked || self.allows(WarningCategory::Yanked) || yanked.is_empty() {
+            return Ok(());
+        }
+        let mut message = format!(
+            "Found {} yanked version{} in the dependency graph, which --fail-on-yanked forbids:",
+            yanked.len(),
+            if yanked.len() == 1 { "" } else { "s" }
+        );
+        for crate_version in yanked {
+            message.push_str("\n  ");
+            message.push_str(crate_version);
+        }
+        Err(Error::ThresholdExceeded(message))
+    }
+
+    /// Loads the list of publishers that have already been reviewed
+    pub fn trust_list(&self) -> Result<TrustList, Error> {
+        Ok(TrustList::load(self.trust_file.as_deref())?.with_color(self.color))
+    }
+
+    /// Returns the crates that `first_party_orgs` can publish. With `hide_first_party`,
+    /// or if `hide` is set, they are also left out of `owners` and the output built from it.
+    pub(crate) fn first_party_crates(
+        &self,
+        owners: &mut CrateOwners,
+        hide: bool,
+    ) -> BTreeSet<String> {
+        let first_party: BTreeSet<String> = owners
+            .teams
+            .iter()
+            .filter(|(_, teams)| teams.iter().any(|team| self.first_party_orgs.owns(team)))
+            .map(|(crate_name, _)| crate_name.clone())
+            .collect();
+        if (hide || self.hide_first_party) && !first_party.is_empty() {
+            owners.remove_crates(&first_party);
+            if !self.quiet {
+                eprintln!(
+                    "\nLeft out {} first-party crate{} that --first-party-orgs can publish: {}",
+                    first_party.len(),
+                    if first_party.len() == 1 { "" } else { "s" },
+                    common::comma_separated_list(&first_party.iter().cloned().collect::<Vec<_>>())
+                );
+            }
+        }
+        first_party
+    }
+}
+
+/// Arguments controlling which packages are taken from `cargo metadata`
+#[derive(Debug, Clone, Default)]
+pub struct MetadataArgs {
+    /// Target triples to filter the dependency graph by, each passed as `--filter-platform`.
+    /// Dependencies for any of them are included. If empty, all platforms are included.
+    pub target: Vec<String>,
+    /// Keep crates that are only reachable through dev-dependencies, which are dropped by default
+    pub include_dev_dependencies: bool,
+    /// Drop crates that are only reachable through build-dependencies.
+    /// Proc-macros are normal dependencies, so they are kept.
+    pub exclude_build_dependencies: bool,
+    /// Only include the workspace members, looking up those published to crates.io
+    pub workspace_only: bool,
+    /// Only include the workspace members and the crates they depend on directly
+    pub direct_only: bool,
+    /// Only include the workspace members and the crates that run at build time
+    pub build_time_only: bool,
+    /// Only include the crates at most this many dependency edges away from a workspace member.
+    /// Depth 1 is the same as `direct_only`. If `None`, there is no limit.
+    pub depth: Option<usize>,
+    /// Only include the crates.io crates that at least this many other packages in the graph depend on,
+    /// directly or transitively. Crates from elsewhere are kept.
+    pub min_dependents: Option<usize>,
+    /// Only include these crates and their dependencies, directly or transitively.
+    /// If empty, the whole dependency graph is included.
+    pub filter_crates: Vec<String>,
+    /// Leave out the crates whose names match any of these patterns: globs such as `windows-*`,
+    /// or regular expressions between slashes such as `/^windows-(sys|targets)$/`
+    pub exclude: Vec<String>,
+    /// Only include the crates whose license expression matches every one of these patterns,
+    /// written like those of `exclude`, or doesn't match those starting with `!`.
+    /// Crates from the local filesystem are kept.
+    pub licenses: Vec<String>,
+    /// Only include the crates in any of these crates.io categories, such as `cryptography`,
+    /// or in one of their subcategories, as declared in their manifests.
+    /// Crates from the local filesystem are kept.
+    pub categories: Vec<String>,
+    /// Activate all available features, passed as `--all-features`
+    pub all_features: bool,
+    /// Do not activate the `default` feature, passed as `--no-default-features`
+    pub no_default_features: bool,
+    /// Space or comma separated list of features to activate, passed as `--features`
+    pub features: Option<String>,
+    /// Also include the crates pulled in only by optional dependencies that none of the selected
+    /// features activate, by merging in the dependency graph with all features activated
+    pub include_inactive_optional: bool,
+    /// Arguments after `--`, passed verbatim to `cargo metadata`
+    pub other_args: Vec<String>,
+    /// Manifests of the workspaces to analyze, each passed as `--manifest-path` to a run of
+    /// `cargo metadata` of its own. The dependency graphs of several workspaces are merged into one.
+    /// If empty, the workspace in the current directory is analyzed.
+    pub manifest_paths: Vec<PathBuf>,
+    /// Read the output of `cargo metadata` from this file instead of running it
+    pub metadata_file: Option<PathBuf>,
+    /// Build the dependency graph from this `Cargo.lock` instead of running `cargo metadata`
+    pub lockfile: Option<PathBuf>,
+    /// Analyze this crate published on crates.io, given as `NAME` or `NAME@VERSION`, instead of
+    /// a local workspace. Its newest version is analyzed if none is given.
+    pub published_crate: Option<String>,
+    /// Forbid `cargo metadata` from accessing the network, passed as `--offline`
+    pub offline: bool,
+    /// Forbid `cargo metadata` from updating `Cargo.lock`, passed as `--locked`
+    pub locked: bool,
+    /// Forbid `cargo metadata` from both updating `Cargo.lock` and accessing the network,
+    /// passed as `--frozen`
+    pub frozen: bool,
+}
+
+/// Options only accepted by the `crates` subcommand
+#[derive(Debug, Clone)]
+pub struct CratesArgs {
+    pub format: OutputFormat,
+    /// Collapse all versions of a crate into a single entry
+    pub deduplicate_versions: bool,
+    /// Order of the crates in text and CSV output
+    pub sort: CratesSort,
+    /// Whether text output lists the publishers of each crate, or the crates of each publisher
+    pub group_by: CratesGroupBy,
+    /// Show when the newest version of each crate was published, in text output
+    pub show_dates: bool,
+    /// Show the license expression of each crate, in text output
+    pub show_licenses: bool,
+    /// With `show_dates`, crates without a new version for this long are flagged as potentially unmaintained
+    pub unmaintained_after: Duration,
+}
+
+/// Options only accepted by the `json` subcommand
+#[derive(Debug, Clone)]
+pub struct JsonArgs {
+    /// `OutputFormat::Json`, `OutputFormat::Toml`, `OutputFormat::Html`, `OutputFormat::Ndjson`,
+    /// `OutputFormat::Dot` or `OutputFormat::CycloneDx`
+    pub format: OutputFormat,
+    /// Whether to pretty-print JSON, including CycloneDX. If not set, it is pretty-printed with `diffable`.
+    pub pretty: Option<bool>,
+    /// Include the categories and keywords of each crate in JSON and TOML output
+    pub show_categories: bool,
+}
+
+/// Options only accepted by the `risks` subcommand
+#[derive(Debug, Clone)]
+pub struct RisksArgs {
+    /// Either `OutputFormat::Text` or `OutputFormat::Sarif`
+    pub format: OutputFormat,
+    /// Crates whose publishers changed within this long are reported too
+    pub owner_changes_within: Duration,
+    /// If set, only the crates whose publishers changed after this time are reported,
+    /// rather than all the risks. Takes the place of `owner_changes_within`.
+    pub since: Option<SystemTime>,
+}
+
+/// Options only accepted by the `publishers` subcommand
+#[derive(Debug, Clone, Default)]
+pub struct PublishersArgs {
+    /// Fail with `ExitCode::ThresholdExceeded` if there are more distinct publishers than this
+    pub max_publishers: Option<usize>,
+    /// Only show this many users and teams that can publish the most crates, ranked together
+    pub top: Option<usize>,
+    /// Only show the crates that the user or team with this login can publish
+    pub publisher: Option<String>,
+    /// Only print the distinct logins of users and teams, one per line
+    pub logins_only: bool,
+    /// Break the publishers down by which of the targets in `MetadataArgs::target` they publish crates for
+    pub per_target: bool,
+    /// Only show publishers of this kind, either users or teams
+    pub only_kind: Option<PublisherKind>,
+    /// Replace GitHub teams with their members where GitHub lists them to the token in `GITHUB_TOKEN`
+    pub expand_teams: bool,
+}
+
+/// Output formats selectable via `--format`. Not every subcommand supports every format.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Csv,
+    /// GitHub-flavored Markdown
+    Markdown,
+    Json,
+    Toml,
+    /// Static Analysis Results Interchange Format, for code scanning dashboards
+    Sarif,
+    /// Standalone report to open in a browser
+    Html,
+    /// JSON Lines, one object per crate, printed as soon as each crate is looked up
+    Ndjson,
+    /// Graphviz graph linking crates to their publishers
+    Dot,
+    /// CycloneDX SBOM in JSON, with the publishers of each crate as its authors
+    CycloneDx,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "csv" => Ok(OutputFormat::Csv),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            "toml" => Ok(OutputFormat::Toml),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "html" => Ok(OutputFormat::Html),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "dot" => Ok(OutputFormat::Dot),
+            "cyclonedx" => Ok(OutputFormat::CycloneDx),
+            _ => Err(format!("unknown output format '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Json => "json",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Sarif => "sarif",
+            OutputFormat::Html => "html",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Dot => "dot",
+            OutputFormat::CycloneDx => "cyclonedx",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Orders selectable via `--sort` in the `crates` subcommand. Ties are broken by crate name.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CratesSort {
+    /// Alphabetically by crate name
+    Name,
+    /// Most depended upon first, counting both direct and transitive dependents
+    Dependents,
+    /// Most publishers first
+    Publishers,
+}
+
+impl std::str::FromStr for CratesSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(CratesSort::Name),
+            "dependents" => Ok(CratesSort::Dependents),
+            "publishers" => Ok(CratesSort::Publishers),
+            _ => Err(format!(
+                "unknown sort order '{}', expected name, dependents or publishers",
+                s
+            )),
+        }
+    }
+}
+
+/// Layouts selectable via `--group-by` in the `crates` subcommand
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CratesGroupBy {
+    /// Each crate followed by its publishers
+    Crate,
+    /// Each publisher followed by the crates it can publish, publishers of the most crates first
+    Publisher,
+}
+
+impl std::str::FromStr for CratesGroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "crate" => Ok(CratesGroupBy::Crate),
+            "publisher" => Ok(CratesGroupBy::Publisher),
+            _ => Err(format!(
+                "unknown grouping '{}', expected crate or publisher",
+                s
+            )),
+        }
+    }
+}
+
+/// Profiles selectable via `--throttle`, each setting the rate limit, concurrency and retries
+/// together. `--requests-per-second` and `--jobs` override the corresponding part of a profile.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Throttle {
+    /// Slower than crates.io asks of crawlers and patient with failures, for shared CI
+    Gentle,
+    /// The defaults
+    Normal,
+    /// Many concurrent requests and few retries, for a mirror that allows it
+    Aggressive,
+}
+
+impl Throttle {
+    pub fn requests_per_second(self) -> f64 {
+        match self {
+            Throttle::Gentle => 0.5,
+            Throttle::Normal => 1.0,
+            Throttle::Aggressive => 20.0,
+        }
+    }
+
+    pub fn jobs(self) -> usize {
+        match self {
+            Throttle::Gentle => 1,
+            Throttle::Normal => 4,
+            Throttle::Aggressive => 16,
+        }
+    }
+
+    pub fn retries(self) -> u8 {
+        match self {
+            Throttle::Gentle => 5,
+            Throttle::Normal => 2,
+            Throttle::Aggressive => 1,
+        }
+    }
+}
+
+impl std::str::FromStr for Throttle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gentle" => Ok(Throttle::Gentle),
+            "normal" => Ok(Throttle::Normal),
+            "aggressive" => Ok(Throttle::Aggressive),
+            _ => Err(format!(
+                "unknown throttle profile '{}', expected gentle, normal or aggressive",
+                s
+            )),
+        }
+    }
+}
+
+/// Choices for `--color`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ColorChoice {
+    /// Colors if stdout is a terminal and the `NO_COLOR` environment variable is not set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether text output written to stdout should be colored
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "unknown color choice '{}', expected auto, always or never",
+                s
+            )),
+        }
+    }
+}
+
+/// Categories of warnings that `--allow` suppresses once they have been accepted
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WarningCategory {
+    /// Yanked versions in the dependency graph. Allowing them also passes `--fail-on-yanked`.
+    Yanked,
+    /// Crates that a single individual and no teams can publish
+    SingleOwner,
+    /// Published crates that have no owners left
+    Ownerless,
+    /// Crates whose publishers changed recently
+    OwnershipChanged,
+    /// A cached dump generated longer ago than `--cache-max-age`, the same as `--allow-stale`
+    Stale,
+}
+
+impl std::str::FromStr for WarningCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yanked" => Ok(WarningCategory::Yanked),
+            "single-owner" => Ok(WarningCategory::SingleOwner),
+            "ownerless" => Ok(WarningCategory::Ownerless),
+            "ownership-changed" => Ok(WarningCategory::OwnershipChanged),
+            "stale" => Ok(WarningCategory::Stale),
+            _ => Err(format!(
+                "unknown warning category '{}', expected yanked, single-owner, ownerless, ownership-changed or stale",
+                s
+            )),
+        }
+    }
+}
+
+/// Exit status of the `cargo supply-chain` binary. Documented in `CLI_HELP`,
+/// so that CI scripts can tell the kinds of failures apart.
+#[doc(hidden)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExitCode {
+    Success = 0,
+    /// Any failure not covered by a more specific code
+    Failure = 1,
+    /// A limit such as `--max-publishers` was exceeded
+    ThresholdExceeded = 2,
+    /// The command line arguments are invalid
+    BadArguments = 3,
+    /// crates.io could not be reached or returned an error, or the publishers of some crates
+    /// could not be looked up for another reason
+    Network = 4,
+    /// There are no crates.io crates in the dependency graph to analyze
+    EmptyGraph = 5,
+}
+
+impl ExitCode {
+    /// Picks the exit code appropriate for an error returned by a subcommand
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::InvalidInput(_) => ExitCode::BadArguments,
+            Error::ThresholdExceeded(_) => ExitCode::ThresholdExceeded,
+            Error::Network(_) | Error::TimedOut { .. } => ExitCode::Network,
+            // Whatever the causes, the audit has gaps, which matters more than why
+            Error::LookupsFailed(_) => ExitCode::Network,
+            Error::EmptyGraph(_) => ExitCode::EmptyGraph,
+            Error::MetadataFailed(_) | Error::Cache(_) | Error::Parse(_) | Error::Io(_) => {
+                ExitCode::Failure
+            }
+        }
+    }
+
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
@@ -0,0 +1,152 @@
+//! Builds a dependency graph from a `Cargo.lock` alone, without running `cargo metadata`,
+//! so that projects can be audited without checking out their full source tree.
+//!
+//! The lockfile records every package that could be built, with no indication of
+//! which dependencies are optional, platform-specific or dev-only.
+//! All of them are included, and their edges are treated as normal dependencies.
+//! Packages without a source are taken to be the workspace members.
+
+use crate::Error;
+use cargo_metadata::Metadata;
+use serde::Deserialize;
+use serde_json::json;
+use std::{fs, io, path::Path};
+
+#[derive(Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    /// Each entry is `name`, `name version` or `name version (source)`,
+    /// with as much detail as needed to tell the packages apart
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Unique within the lockfile, in the style of the package IDs reported by `cargo metadata`
+    fn id(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{} {} ({})", self.name, self.version, source),
+            None => format!("{} {} (path+lockfile)", self.name, self.version),
+        }
+    }
+
+    fn matches(&self, reference: &str) -> bool {
+        let mut parts = reference.splitn(3, ' ');
+        let name = parts.next().unwrap_or("");
+        let version = parts.next();
+        let source = parts
+            .next()
+            .map(|s| s.trim_start_matches('(').trim_end_matches(')'));
+        self.name == name
+            && version.is_none_or(|v| self.version == v)
+            && source.is_none_or(|s| self.source.as_deref() == Some(s))
+    }
+}
+
+/// Reads `Cargo.lock` into the same shape as the output of `cargo metadata`
+pub(crate) fn metadata_from_lockfile(path: &Path) -> Result<Metadata, Error> {
+    log::debug!("Reading the dependency graph from {}", path.display());
+    let contents = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to read lockfile {}: {}", path.display(), e),
+        )
+    })?;
+    let invalid =
+        |msg: String| Error::Parse(format!("Invalid lockfile {}: {}", path.display(), msg));
+    let lockfile: Lockfile = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+
+    let root = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let manifest_path = root.join("Cargo.toml");
+    let mut packages = Vec::new();
+    let mut nodes = Vec::new();
+    for package in &lockfile.package {
+        let mut deps = Vec::new();
+        for reference in &package.dependencies {
+            let dependency = lockfile
+                .package
+                .iter()
+                .find(|candidate| candidate.matches(reference))
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "{} depends on {}, which is not in the lockfile",
+                        package.name, reference
+                    ))
+                })?;
+            deps.push(dependency);
+        }
+        packages.push(json!({
+            "name": package.name,
+            "version": package.version,
+            "id": package.id(),
+            "source": package.source,
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": manifest_path,
+        }));
+        nodes.push(json!({
+            "id": package.id(),
+            "deps": deps
+                .iter()
+                .map(|dep| json!({ "name": dep.name, "pkg": dep.id() }))
+                .collect::<Vec<_>>(),
+            "dependencies": deps.iter().map(|dep| dep.id()).collect::<Vec<_>>(),
+        }));
+    }
+    let workspace_members: Vec<String> = lockfile
+        .package
+        .iter()
+        .filter(|package| package.source.is_none())
+        .map(LockedPackage::id)
+        .collect();
+    let metadata = json!({
+        "packages": packages,
+        "workspace_members": workspace_members,
+        "resolve": { "nodes": nodes, "root": null },
+        "workspace_root": root,
+        "target_directory": root.join("target"),
+        "version": 1,
+    });
+    serde_json::from_value(metadata).map_err(|e| invalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_only_give_the_detail_needed_to_tell_packages_apart() {
+        let lockfile: Lockfile = toml::from_str(
+            r#"
+            [[package]]
+            name = "itoa"
+            version = "0.4.8"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "itoa"
+            version = "1.0.11"
+            source = "git+https://github.com/dtolnay/itoa#abc"
+            "#,
+        )
+        .unwrap();
+        let (registry, git) = (&lockfile.package[0], &lockfile.package[1]);
+        assert!(registry.matches("itoa") && git.matches("itoa"));
+        assert!(registry.matches("itoa 0.4.8") && !git.matches("itoa 0.4.8"));
+        assert!(git.matches("itoa 1.0.11 (git+https://github.com/dtolnay/itoa#abc)"));
+        assert!(!git.matches("itoa 1.0.11 (registry+https://github.com/rust-lang/crates.io-index)"));
+        assert!(!registry.matches("itoa-sys"));
+    }
+}
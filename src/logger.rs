@@ -0,0 +1,68 @@
+//! Diagnostic logging to stderr, enabled by `--verbose`.
+//!
+//! Messages go through the `log` facade, so that they can be collected by any logger
+//! when this crate is used as a library. The binary installs the minimal one below.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+struct StderrLogger {
+    level: LevelFilter,
+    /// Whether to include messages from dependencies such as `ureq`, not just our own
+    all_targets: bool,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+            && (self.all_targets || metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let level = match record.level() {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+        let message = redact_authorization(&record.args().to_string());
+        eprintln!("[{} {}] {}", level, record.target(), message);
+    }
+
+    fn flush(&self) {}
+}
+
+/// `ureq` logs the headers of every request, which include the API token if one is set
+fn redact_authorization(message: &str) -> String {
+    message
+        .split("\r\n")
+        .map(|line| {
+            if line.to_ascii_lowercase().starts_with("authorization:") {
+                "Authorization: <redacted>"
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Installs a logger printing to stderr. Nothing is logged at verbosity 0,
+/// cache and network activity is logged at 1, and every detail including that of
+/// the underlying HTTP client is logged at 2 and above.
+#[doc(hidden)]
+pub fn init(verbosity: u8) {
+    let (level, all_targets) = match verbosity {
+        0 => return,
+        1 => (LevelFilter::Debug, false),
+        _ => (LevelFilter::Trace, true),
+    };
+    let logger = StderrLogger { level, all_targets };
+    // Installed once for the lifetime of the process, so leaking it is fine
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -1,184 +1,1073 @@
-//! Gather author, contributor, publisher data on crates in your dependency graph.
-//!
-//! There are some use cases:
-//!
-//! * Find people and groups worth supporting.
-//! * An analysis of all the contributors you implicitly trust by building their software. This
-//!   might have both a sobering and humbling effect.
-//! * Identify risks in your dependency graph.
+//! The `cargo supply-chain` command line interface
 
 #![forbid(unsafe_code)]
 
-use std::{error::Error, ffi::OsString, time::Duration};
+use std::{
+    error::Error,
+    ffi::OsString,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 
+use cargo_supply_chain::{
+    subcommands, token_from_env, ColorChoice, Config, CratesArgs, CratesGroupBy, CratesSort,
+    ExitCode, FirstPartyOrgs, JsonArgs, MetadataArgs, OutputFormat, PublisherKind, PublishersArgs,
+    QueryCommandArgs, RisksArgs, Throttle, WarningCategory, CLI_HELP, DEFAULT_REGISTRY_URL,
+    DEFAULT_REQUEST_TIMEOUT, TOKEN_ENV, UNAUTHENTICATED_RATE_LIMIT,
+};
 use pico_args::Arguments;
 
-mod api_client;
-mod common;
-mod crates_cache;
-mod publishers;
-mod subcommands;
-
-/// CLI-focused help message for displaying to the user
-pub(crate) const CLI_HELP: &str =
-    "Usage: cargo supply-chain COMMAND [OPTIONS...] [-- CARGO_METADATA_OPTIONS...]
-
-Commands:
-  publishers   List all crates.io publishers in the depedency graph
-  crates       List all crates in dependency graph and crates.io publishers for each
-  json         Like 'crates', but in JSON and with more fields for each publisher
-  update       Download the latest daily dump from crates.io to speed up other commands
-
-See 'cargo supply-chain help <command>' for more information on a specific command.
-
-Arguments:
-  --cache-max-age  The cache will be considered valid while younger than specified.
-                   The format is a human readable duration such as `1w` or `1d 6h`.
-                   If not specified, the cache is considered valid for 48 hours.
-  -d, --diffable   Make output more friendly towards tools such as `diff`
-
-Any arguments after the `--` will be passed to `cargo metadata`, for example:
-  cargo supply-chain crates -- --filter-platform=x86_64-unknown-linux-gnu
-See `cargo metadata --help` for a list of flags it supports.";
-
 #[derive(Debug)]
 struct Args {
     help: bool,
+    verbosity: u8,
+    quiet: bool,
     command: String,
     diffable: bool,
-    cache_max_age: Duration,
+    cache_max_age: Option<Duration>,
+    target: Vec<String>,
+    include_dev_dependencies: bool,
+    exclude_dev_dependencies: bool,
+    exclude_build_dependencies: bool,
+    include_build_dependencies: bool,
+    workspace_only: bool,
+    filter_crates: Vec<String>,
+    exclude: Vec<String>,
+    licenses: Vec<String>,
+    categories: Vec<String>,
+    direct_only: bool,
+    build_time_only: bool,
+    depth: Option<usize>,
+    min_dependents: Option<usize>,
+    all_features: bool,
+    no_default_features: bool,
+    default_features: bool,
+    features: Option<String>,
+    include_inactive_optional: bool,
+    requests_per_second: Option<f64>,
+    jobs: Option<usize>,
+    throttle: Option<Throttle>,
+    color: Option<ColorChoice>,
+    registry_url: Option<String>,
+    request_timeout: Option<Duration>,
+    manifest_paths: Vec<PathBuf>,
+    metadata_file: Option<PathBuf>,
+    lockfile: Option<PathBuf>,
+    published_crate: Option<String>,
+    offline: bool,
+    locked: bool,
+    frozen: bool,
+    no_cache: bool,
+    allow_stale: bool,
+    keep_going: bool,
+    include_yanked: bool,
+    fail_on_yanked: bool,
+    allow: Vec<WarningCategory>,
+    trust_file: Option<PathBuf>,
+    untrusted_only: bool,
+    first_party_orgs: Vec<String>,
+    hide_first_party: bool,
+    format: Option<OutputFormat>,
+    pretty: bool,
+    compact: bool,
+    deduplicate_versions: bool,
+    sort: Option<CratesSort>,
+    sort_by_publishers: bool,
+    group_by: Option<CratesGroupBy>,
+    show_dates: bool,
+    show_licenses: bool,
+    show_categories: bool,
+    unmaintained_after: Option<Duration>,
+    owner_changes_within: Option<Duration>,
+    since: Option<SystemTime>,
+    max_publishers: Option<usize>,
+    top: Option<usize>,
+    publisher: Option<String>,
+    logins_only: bool,
+    per_target: bool,
+    expand_teams: bool,
+    only_teams: bool,
+    only_users: bool,
+    update_expectations: bool,
+    dry_run: bool,
+    output: Option<PathBuf>,
+    timing: bool,
     metadata_args: Vec<String>,
     free: Vec<String>,
 }
-
-fn main() -> Result<(), std::io::Error> {
+fn main() {
+    let started = Instant::now();
     match get_args() {
         Err(e) => {
             eprintln!("Error: {}", e);
             eprint_help();
-            std::process::exit(1);
+            ExitCode::BadArguments.exit();
+        }
+        Ok((args, output)) => {
+            // The subcommands print nothing but the requested data to `out`,
+            // so that's exactly what the file gets, while diagnostics still go to stderr
+            let mut out: Box<dyn Write> = match &output {
+                Some(path) => match fs::File::create(path) {
+                    Ok(file) => Box::new(io::BufWriter::new(file)),
+                    Err(e) => {
+                        eprintln!("Error: Failed to create {}: {}", path.display(), e);
+                        ExitCode::Failure.exit();
+                    }
+                },
+                None => Box::new(io::stdout()),
+            };
+            // Whatever was printed before a failure is kept, as it would be on stdout
+            let result = dispatch_command(&mut *out, args);
+            let flushed = out.flush();
+            cargo_supply_chain::report_timing(started.elapsed());
+            if let Err(e) = result.and(flushed.map_err(cargo_supply_chain::Error::from)) {
+                eprintln!("Error: {}", e);
+                ExitCode::from_error(&e).exit();
+            }
         }
-        Ok(args) => dispatch_command(args),
     }
 }
 
-fn get_args() -> Result<ValidatedArgs, Box<dyn Error>> {
-    let args = parse_args()?;
+fn get_args() -> Result<(ValidatedArgs, Option<PathBuf>), Box<dyn Error>> {
+    let mut args = parse_args()?;
+    cargo_supply_chain::init_logging(args.verbosity);
+    if args.exclude_dev_dependencies {
+        eprintln!("Warning: --exclude-dev-dependencies is deprecated and will be removed, since dev-dependencies are left out by default");
+    }
+    if args.timing {
+        cargo_supply_chain::enable_timing();
+    }
+    if !args.help && args.command != "help" {
+        apply_config(&mut args, Config::load()?)?;
+    }
+    // Help is always printed to the terminal
+    let output = args
+        .output
+        .clone()
+        .filter(|_| !args.help && args.command != "help");
     let valid_args = validate_args(args)?;
-    Ok(valid_args)
+    Ok((valid_args, output))
 }
 
 enum ValidatedArgs {
     Publishers {
-        cache_max_age: Duration,
-        diffable: bool,
-        metadata_args: Vec<String>,
+        args: QueryCommandArgs,
+        publishers_args: PublishersArgs,
     },
     Crates {
-        cache_max_age: Duration,
-        diffable: bool,
-        metadata_args: Vec<String>,
+        args: QueryCommandArgs,
+        crates_args: CratesArgs,
     },
     Json {
-        cache_max_age: Duration,
-        diffable: bool,
-        metadata_args: Vec<String>,
+        args: QueryCommandArgs,
+        json_args: JsonArgs,
+    },
+    Owners {
+        args: QueryCommandArgs,
+    },
+    Risks {
+        args: QueryCommandArgs,
+        risks_args: RisksArgs,
+    },
+    Stats {
+        args: QueryCommandArgs,
+    },
+    Verify {
+        args: QueryCommandArgs,
+        expectations: PathBuf,
+        update_expectations: bool,
+    },
+    Why {
+        args: QueryCommandArgs,
+        crate_name: String,
+    },
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
     },
     Update {
         cache_max_age: Duration,
+        registry_url: Option<String>,
+        request_timeout: Duration,
+        dry_run: bool,
+    },
+    Clean {
+        dry_run: bool,
     },
     Help {
         command: Option<String>,
     },
 }
 
+/// Fills in the options missing from the command line with the defaults from the config file.
+/// Options that the subcommand doesn't accept are left alone, so that they don't make it fail.
+fn apply_config(args: &mut Args, config: Config) -> Result<(), std::io::Error> {
+    let queries_graph = !["update", "diff", "clean"].contains(&args.command.as_str());
+    if !["diff", "clean"].contains(&args.command.as_str()) {
+        args.cache_max_age = args.cache_max_age.or(config.cache_max_age);
+        args.registry_url = args.registry_url.take().or(config.registry_url);
+        args.request_timeout = args.request_timeout.or(config.request_timeout);
+    }
+    if !queries_graph {
+        return Ok(());
+    }
+    // Cargo is not invoked with these, so options passed to it don't apply
+    if args.metadata_file.is_none() && args.lockfile.is_none() {
+        if args.target.is_empty() {
+            args.target = config.target;
+        }
+        // The features in the file are those of the project, not of a published crate.
+        // A selection on the command line replaces that of the file as a whole.
+        if args.published_crate.is_none()
+            && args.features.is_none()
+            && !args.all_features
+            && !args.no_default_features
+            && !args.default_features
+        {
+            args.features = config.features;
+            args.all_features = config.all_features.unwrap_or(false);
+            args.no_default_features = config.no_default_features.unwrap_or(false);
+        }
+    }
+    if args.exclude.is_empty() {
+        args.exclude = config.exclude;
+    }
+    // Either switch of a pair on the command line overrides the file
+    if !args.include_dev_dependencies && !args.exclude_dev_dependencies {
+        args.include_dev_dependencies = config.include_dev_dependencies.unwrap_or(false);
+    }
+    if !args.exclude_build_dependencies && !args.include_build_dependencies {
+        args.exclude_build_dependencies = config.exclude_build_dependencies.unwrap_or(false);
+    }
+    if args.allow.is_empty() {
+        args.allow = config
+            .allow
+            .iter()
+            .map(|category| category.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid allow in config file: {}", e),
+                )
+            })?;
+    }
+    // A profile given on the command line overrides the individual settings in the file too
+    if args.throttle.is_none() {
+        args.requests_per_second = args.requests_per_second.or(config.requests_per_second);
+        args.jobs = args.jobs.or(config.jobs);
+        args.throttle = match config.throttle {
+            Some(throttle) => Some(throttle.parse().map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid throttle in config file: {}", e),
+                )
+            })?),
+            None => None,
+        };
+    }
+    Ok(())
+}
+
+/// Lists the options given that only apply to the subcommands querying the dependency graph,
+/// and that 'update', 'clean' and 'diff' therefore reject.
+/// Options only supported by some of the querying subcommands are checked separately.
+fn query_only_options(args: &Args) -> Vec<&'static str> {
+    let options = [
+        ("--diffable", args.diffable),
+        ("--quiet", args.quiet),
+        ("--color", args.color.is_some()),
+        ("--target", !args.target.is_empty()),
+        ("--include-dev-dependencies", args.include_dev_dependencies),
+        ("--exclude-dev-dependencies", args.exclude_dev_dependencies),
+        (
+            "--exclude-build-dependencies",
+            args.exclude_build_dependencies,
+        ),
+        (
+            "--include-build-dependencies",
+            args.include_build_dependencies,
+        ),
+        ("--workspace-only", args.workspace_only),
+        ("--filter-crate", !args.filter_crates.is_empty()),
+        ("--direct-only", args.direct_only),
+        ("--build-time-only", args.build_time_only),
+        ("--depth", args.depth.is_some()),
+        ("--exclude", !args.exclude.is_empty()),
+        ("--license", !args.licenses.is_empty()),
+        ("--category", !args.categories.is_empty()),
+        ("--min-dependents", args.min_dependents.is_some()),
+        ("--features", args.features.is_some()),
+        ("--all-features", args.all_features),
+        ("--no-default-features", args.no_default_features),
+        ("--default-features", args.default_features),
+        (
+            "--include-inactive-optional",
+            args.include_inactive_optional,
+        ),
+        ("--requests-per-second", args.requests_per_second.is_some()),
+        ("--jobs", args.jobs.is_some()),
+        ("--throttle", args.throttle.is_some()),
+        ("--manifest-path", !args.manifest_paths.is_empty()),
+        ("--metadata-file", args.metadata_file.is_some()),
+        ("--lockfile", args.lockfile.is_some()),
+        ("--crate", args.published_crate.is_some()),
+        ("--locked", args.locked),
+        ("--frozen", args.frozen),
+        ("--offline", args.offline),
+        ("--allow-stale", args.allow_stale),
+        ("--no-cache", args.no_cache),
+        ("--keep-going", args.keep_going),
+        ("--fail-on-yanked", args.fail_on_yanked),
+        ("--allow", !args.allow.is_empty()),
+        ("cargo metadata arguments", !args.metadata_args.is_empty()),
+    ];
+    options
+        .iter()
+        .filter(|(_, given)| *given)
+        .map(|(option, _)| *option)
+        .collect()
+}
+
 fn validate_args(args: Args) -> Result<ValidatedArgs, std::io::Error> {
+    let cache_max_age = args.cache_max_age.unwrap_or(DEFAULT_CACHE_MAX_AGE);
     if args.help {
         return Ok(ValidatedArgs::Help {
             command: Some(args.command),
         });
-    } else {
-        if args.command != "help" && !args.free.is_empty() {
+    }
+    let takes_arguments = ["help", "diff", "verify", "why"].contains(&args.command.as_str());
+    if !takes_arguments && !args.free.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Unrecognized argument: {}", args.free[0]),
+        ));
+    }
+    //FIXME Should maybe cause subcommands::help(String::from("update")) to be called instead of eprint_help() in main
+    let query_only = query_only_options(&args);
+    if ["update", "clean", "diff"].contains(&args.command.as_str()) && !query_only.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "The {} subcommand doesn't accept {}",
+                args.command,
+                query_only.join(", ")
+            ),
+        ));
+    }
+    let talks_to_registry = args.cache_max_age.is_some()
+        || args.registry_url.is_some()
+        || args.request_timeout.is_some();
+    if args.command == "clean" && talks_to_registry {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Clean subcommand only accepts --dry-run and --verbose",
+        ));
+    }
+    if args.command == "diff" && talks_to_registry {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Diff subcommand only accepts the paths to two outputs of the json subcommand",
+        ));
+    }
+    let supported_formats: &[OutputFormat] = match args.command.as_str() {
+        "crates" => &[
+            OutputFormat::Text,
+            OutputFormat::Csv,
+            OutputFormat::Markdown,
+        ],
+        "json" => &[
+            OutputFormat::Json,
+            OutputFormat::Toml,
+            OutputFormat::Html,
+            OutputFormat::Ndjson,
+            OutputFormat::Dot,
+            OutputFormat::CycloneDx,
+        ],
+        "risks" => &[OutputFormat::Text, OutputFormat::Sarif],
+        _ => &[],
+    };
+    match args.format {
+        Some(_) if supported_formats.is_empty() => {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
-                format!("Unrecognized argument: {}", args.free[0]),
-            ));
+                "--format is only supported by the crates, json and risks subcommands",
+            ))
         }
-        //FIXME Should maybe cause subcommands::help(String::from("update")) to be called instead of eprint_help() in main
-        if args.command == "update" && (args.diffable || !args.metadata_args.is_empty()) {
+        Some(format) if !supported_formats.contains(&format) => {
+            let supported: Vec<String> = supported_formats.iter().map(|f| f.to_string()).collect();
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
-                format!("Update subcommand doesn't allow --diffable or metadata arguments"),
+                format!(
+                    "The {} subcommand doesn't support --format {}, only {}",
+                    args.command,
+                    format,
+                    supported.join(", ")
+                ),
             ));
         }
-        match args.command.as_str() {
-            "publishers" => {
-                return Ok(ValidatedArgs::Publishers {
-                    cache_max_age: args.cache_max_age,
-                    diffable: args.diffable,
-                    metadata_args: args.metadata_args,
-                })
-            }
-            "crates" => {
-                return Ok(ValidatedArgs::Crates {
-                    cache_max_age: args.cache_max_age,
-                    diffable: args.diffable,
-                    metadata_args: args.metadata_args,
-                })
-            }
-            "json" => {
-                return Ok(ValidatedArgs::Json {
-                    cache_max_age: args.cache_max_age,
-                    diffable: args.diffable,
-                    metadata_args: args.metadata_args,
-                })
-            }
-            "update" => {
-                return Ok(ValidatedArgs::Update {
-                    cache_max_age: args.cache_max_age,
-                })
-            }
-            "help" => {
-                return Ok(ValidatedArgs::Help {
-                    command: args.free.get(0).map(String::to_owned),
-                })
-            }
-            _ => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    format!("Unrecognized argument: {}", args.command.as_str()),
-                ))
-            }
+        _ => (),
+    }
+    if args.deduplicate_versions && args.command != "crates" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--deduplicate-versions is only supported by the crates subcommand",
+        ));
+    }
+    if (args.sort.is_some() || args.sort_by_publishers) && args.command != "crates" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--sort and --sort-by-publishers are only supported by the crates subcommand",
+        ));
+    }
+    if args.sort.is_some() && args.sort_by_publishers {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--sort-by-publishers is the same as `--sort publishers`, specify only one of them",
+        ));
+    }
+    if args.group_by.is_some() && args.command != "crates" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--group-by is only supported by the crates subcommand",
+        ));
+    }
+    if args.group_by == Some(CratesGroupBy::Publisher)
+        && (args.format.is_some_and(|f| f != OutputFormat::Text)
+            || args.show_dates
+            || args.show_licenses)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--group-by publisher only applies to text output, and cannot be combined with --show-dates or --show-licenses",
+        ));
+    }
+    if args.show_dates && args.command != "crates" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--show-dates is only supported by the crates subcommand",
+        ));
+    }
+    if args.show_licenses && args.command != "crates" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--show-licenses is only supported by the crates subcommand",
+        ));
+    }
+    if args.unmaintained_after.is_some() && !args.show_dates {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--unmaintained-after requires --show-dates",
+        ));
+    }
+    if args.owner_changes_within.is_some() && args.command != "risks" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--owner-changes-within is only supported by the risks subcommand",
+        ));
+    }
+    if args.since.is_some() && args.command != "risks" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--since is only supported by the risks subcommand",
+        ));
+    }
+    if args.since.is_some() && args.owner_changes_within.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--since cannot be combined with --owner-changes-within",
+        ));
+    }
+    if args.since.is_some_and(|since| since > SystemTime::now()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--since must not be in the future",
+        ));
+    }
+    if args.output.is_some() && (args.command == "update" || args.command == "clean") {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--output is not supported by the update and clean subcommands, which only report on stderr",
+        ));
+    }
+    if args.dry_run && args.command != "update" && args.command != "clean" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--dry-run is only supported by the update and clean subcommands",
+        ));
+    }
+    if args.update_expectations && args.command != "verify" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--update-expectations is only supported by the verify subcommand",
+        ));
+    }
+    let trust_aware = ["crates", "publishers", "owners", "risks"].contains(&args.command.as_str());
+    if (args.trust_file.is_some() || args.untrusted_only) && !trust_aware {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--trust-file and --untrusted-only are only supported by the crates, publishers, owners and risks subcommands",
+        ));
+    }
+    if (!args.first_party_orgs.is_empty() || args.hide_first_party) && !trust_aware {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--first-party-orgs and --hide-first-party are only supported by the crates, publishers, owners and risks subcommands",
+        ));
+    }
+    if args.hide_first_party && args.command == "risks" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--hide-first-party is implied by --first-party-orgs in the risks subcommand",
+        ));
+    }
+    if args.hide_first_party && args.first_party_orgs.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--hide-first-party requires --first-party-orgs",
+        ));
+    }
+    if args.include_yanked && args.command != "crates" && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--include-yanked is only supported by the crates and publishers subcommands",
+        ));
+    }
+    if args.max_publishers.is_some() && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--max-publishers is only supported by the publishers subcommand",
+        ));
+    }
+    if args.top.is_some() && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--top is only supported by the publishers subcommand",
+        ));
+    }
+    if args.publisher.is_some() && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--publisher is only supported by the publishers subcommand",
+        ));
+    }
+    if args.publisher.is_some() && args.top.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--publisher cannot be combined with --top",
+        ));
+    }
+    if (args.pretty || args.compact) && args.command != "json" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--pretty and --compact are only supported by the json subcommand",
+        ));
+    }
+    if args.pretty && args.compact {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--pretty cannot be combined with --compact",
+        ));
+    }
+    if (args.pretty || args.compact)
+        && args
+            .format
+            .is_some_and(|f| f != OutputFormat::Json && f != OutputFormat::CycloneDx)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--pretty and --compact only apply to --format json and cyclonedx",
+        ));
+    }
+    if args.show_categories && args.command != "json" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--show-categories is only supported by the json subcommand",
+        ));
+    }
+    if args.show_categories
+        && args
+            .format
+            .is_some_and(|f| f != OutputFormat::Json && f != OutputFormat::Toml)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--show-categories only applies to --format json and toml",
+        ));
+    }
+    if args.logins_only && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--logins-only is only supported by the publishers subcommand",
+        ));
+    }
+    if args.logins_only && (args.publisher.is_some() || args.top.is_some()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--logins-only cannot be combined with --publisher or --top",
+        ));
+    }
+    if (args.only_teams || args.only_users) && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--only-teams and --only-users are only supported by the publishers subcommand",
+        ));
+    }
+    if args.only_teams && args.only_users {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--only-teams and --only-users are mutually exclusive",
+        ));
+    }
+    if args.per_target && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--per-target is only supported by the publishers subcommand",
+        ));
+    }
+    if args.per_target && (args.publisher.is_some() || args.top.is_some() || args.logins_only) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--per-target cannot be combined with --publisher, --top or --logins-only",
+        ));
+    }
+    if args.expand_teams && args.command != "publishers" {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--expand-teams is only supported by the publishers subcommand",
+        ));
+    }
+    if args.expand_teams && (args.per_target || args.offline) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--expand-teams cannot be combined with --per-target or --offline",
+        ));
+    }
+    if args.per_target && args.target.len() < 2 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--per-target requires at least two --target options to compare",
+        ));
+    }
+    if args.metadata_file.is_some() && args.lockfile.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--metadata-file cannot be combined with --lockfile",
+        ));
+    }
+    if (args.metadata_file.is_some() || args.lockfile.is_some())
+        && (!args.target.is_empty()
+            || args.all_features
+            || args.no_default_features
+            || args.default_features
+            || args.features.is_some()
+            || args.include_inactive_optional
+            || args.locked
+            || args.frozen
+            || !args.manifest_paths.is_empty()
+            || !args.metadata_args.is_empty())
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--metadata-file and --lockfile cannot be combined with --target, feature selection, --locked, --frozen, --manifest-path or cargo metadata arguments, since cargo is not invoked",
+        ));
+    }
+    if args.published_crate.is_some()
+        && (args.metadata_file.is_some()
+            || args.lockfile.is_some()
+            || !args.manifest_paths.is_empty()
+            || args.workspace_only
+            || args.locked
+            || args.frozen
+            || args.all_features
+            || args.include_inactive_optional)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--crate analyzes a published crate without a local workspace, so it cannot be combined with --metadata-file, --lockfile, --manifest-path, --workspace-only, --locked, --frozen, --all-features or --include-inactive-optional",
+        ));
+    }
+    if args.default_features
+        && (args.all_features || args.no_default_features || args.features.is_some())
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--default-features cannot be combined with --features, --all-features or --no-default-features",
+        ));
+    }
+    if args.include_inactive_optional && args.all_features {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--include-inactive-optional has no effect with --all-features, which activates every optional dependency",
+        ));
+    }
+    if args.quiet && args.verbosity > 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--quiet cannot be combined with --verbose",
+        ));
+    }
+    if args.offline && args.no_cache {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--offline cannot be combined with --no-cache, since live data can't be fetched",
+        ));
+    }
+    if args.offline && args.cache_max_age == Some(Duration::ZERO) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--offline cannot be combined with --cache-max-age 0, which always refreshes the cache",
+        ));
+    }
+    if args.workspace_only
+        && (!args.filter_crates.is_empty()
+            || args.direct_only
+            || args.build_time_only
+            || args.depth.is_some())
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--workspace-only cannot be combined with --filter-crate, --direct-only, --build-time-only or --depth",
+        ));
+    }
+    if args.include_dev_dependencies && args.exclude_dev_dependencies {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--include-dev-dependencies cannot be combined with --exclude-dev-dependencies",
+        ));
+    }
+    if args.include_dev_dependencies && args.build_time_only {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--include-dev-dependencies cannot be combined with --build-time-only, which only includes what runs at build time",
+        ));
+    }
+    if args.exclude_build_dependencies && args.include_build_dependencies {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--exclude-build-dependencies cannot be combined with --include-build-dependencies",
+        ));
+    }
+    if args.exclude_build_dependencies && args.build_time_only {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--exclude-build-dependencies cannot be combined with --build-time-only, which only includes what runs at build time",
+        ));
+    }
+    if !args.licenses.is_empty() && args.lockfile.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--license cannot be combined with --lockfile, which doesn't record the licenses of crates",
+        ));
+    }
+    if !args.categories.is_empty() && args.lockfile.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--category cannot be combined with --lockfile, which doesn't record the categories of crates",
+        ));
+    }
+    if args.exclude_build_dependencies && args.lockfile.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--exclude-build-dependencies cannot be combined with --lockfile, which doesn't record which dependencies are build-dependencies",
+        ));
+    }
+    if args.depth == Some(0) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--depth must be at least 1; use --workspace-only to include only the workspace",
+        ));
+    }
+    let throttle = args.throttle.unwrap_or(Throttle::Normal);
+    let requests_per_second = args
+        .requests_per_second
+        .unwrap_or_else(|| throttle.requests_per_second());
+    if !(requests_per_second.is_finite() && requests_per_second > 0.0) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--requests-per-second must be a positive number",
+        ));
+    }
+    if let Some(url) = &args.registry_url {
+        if !(url.starts_with("https://") || url.starts_with("http://")) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--registry-url must be an http:// or https:// URL",
+            ));
         }
     }
+    // Mirrors set their own limits, but crates.io only grants higher ones along with a token
+    let talks_to_crates_io = args
+        .registry_url
+        .as_deref()
+        .is_none_or(|url| url.trim_end_matches('/') == DEFAULT_REGISTRY_URL);
+    if requests_per_second > UNAUTHENTICATED_RATE_LIMIT
+        && talks_to_crates_io
+        && token_from_env().is_none()
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "{} above {} requests per second requires a crates.io API token granted a higher limit, set in {}",
+                if args.requests_per_second.is_some() {
+                    "--requests-per-second"
+                } else {
+                    "--throttle aggressive"
+                },
+                UNAUTHENTICATED_RATE_LIMIT,
+                TOKEN_ENV
+            ),
+        ));
+    }
+    let request_timeout = args.request_timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+    if request_timeout == Duration::ZERO {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--request-timeout must be longer than zero",
+        ));
+    }
+    let jobs = args.jobs.unwrap_or_else(|| throttle.jobs());
+    if jobs == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--jobs must be at least 1",
+        ));
+    }
+    // The layout meant for tools has no headings, but for JSON it means pretty-printing instead
+    let diffable = args.diffable || (args.quiet && args.command != "json");
+    // Only text output is colored, and never when meant for tools
+    let color = !diffable
+        && args.command != "json"
+        && args
+            .format
+            .is_none_or(|format| format == OutputFormat::Text)
+        && match args.color.unwrap_or(ColorChoice::Auto) {
+            // Files given with --output are never terminals
+            ColorChoice::Auto if args.output.is_some() => false,
+            choice => choice.enabled(),
+        };
+    let query_args = QueryCommandArgs {
+        cache_max_age,
+        diffable,
+        quiet: args.quiet,
+        requests_per_second,
+        jobs,
+        retries: throttle.retries(),
+        offline: args.offline,
+        no_cache: args.no_cache,
+        allow_stale: args.allow_stale,
+        keep_going: args.keep_going,
+        include_yanked: args.include_yanked || args.allow.contains(&WarningCategory::Yanked),
+        fail_on_yanked: args.fail_on_yanked,
+        allow: args.allow,
+        trust_file: args.trust_file,
+        untrusted_only: args.untrusted_only,
+        first_party_orgs: FirstPartyOrgs::parse(&args.first_party_orgs)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?,
+        hide_first_party: args.hide_first_party,
+        registry_url: args.registry_url.clone(),
+        request_timeout,
+        color,
+        metadata_args: MetadataArgs {
+            target: args.target,
+            include_dev_dependencies: args.include_dev_dependencies,
+            exclude_build_dependencies: args.exclude_build_dependencies,
+            workspace_only: args.workspace_only,
+            filter_crates: args.filter_crates,
+            exclude: args.exclude,
+            licenses: args.licenses,
+            categories: args.categories,
+            direct_only: args.direct_only,
+            build_time_only: args.build_time_only,
+            depth: args.depth,
+            min_dependents: args.min_dependents,
+            all_features: args.all_features,
+            no_default_features: args.no_default_features,
+            features: args.features,
+            include_inactive_optional: args.include_inactive_optional,
+            other_args: expand_response_files(args.metadata_args)?,
+            manifest_paths: args.manifest_paths,
+            metadata_file: args.metadata_file,
+            lockfile: args.lockfile,
+            published_crate: args.published_crate,
+            offline: args.offline,
+            locked: args.locked,
+            frozen: args.frozen,
+        },
+    };
+    match args.command.as_str() {
+        "publishers" => Ok(ValidatedArgs::Publishers {
+            args: query_args,
+            publishers_args: PublishersArgs {
+                max_publishers: args.max_publishers,
+                top: args.top,
+                publisher: args.publisher,
+                logins_only: args.logins_only,
+                per_target: args.per_target,
+                only_kind: match (args.only_teams, args.only_users) {
+                    (true, _) => Some(PublisherKind::team),
+                    (_, true) => Some(PublisherKind::user),
+                    _ => None,
+                },
+                expand_teams: args.expand_teams,
+            },
+        }),
+        "crates" => Ok(ValidatedArgs::Crates {
+            args: query_args,
+            crates_args: CratesArgs {
+                format: args.format.unwrap_or(OutputFormat::Text),
+                deduplicate_versions: args.deduplicate_versions,
+                sort: match args.sort {
+                    Some(sort) => sort,
+                    None if args.sort_by_publishers => CratesSort::Publishers,
+                    None => CratesSort::Name,
+                },
+                group_by: args.group_by.unwrap_or(CratesGroupBy::Crate),
+                show_dates: args.show_dates,
+                show_licenses: args.show_licenses,
+                unmaintained_after: args
+                    .unmaintained_after
+                    .unwrap_or(Duration::from_secs(2 * 365 * 24 * 3600)),
+            },
+        }),
+        "json" => Ok(ValidatedArgs::Json {
+            args: query_args,
+            json_args: JsonArgs {
+                format: args.format.unwrap_or(OutputFormat::Json),
+                pretty: match (args.pretty, args.compact) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ if args.output.is_none() && io::stdout().is_terminal() => Some(true),
+                    _ => None,
+                },
+                show_categories: args.show_categories,
+            },
+        }),
+        "owners" => Ok(ValidatedArgs::Owners { args: query_args }),
+        "risks" => Ok(ValidatedArgs::Risks {
+            args: query_args,
+            risks_args: RisksArgs {
+                format: args.format.unwrap_or(OutputFormat::Text),
+                owner_changes_within: args
+                    .owner_changes_within
+                    .unwrap_or(Duration::from_secs(30 * 24 * 3600)),
+                since: args.since,
+            },
+        }),
+        "stats" => Ok(ValidatedArgs::Stats { args: query_args }),
+        "verify" => match args.free.as_slice() {
+            [expectations] => Ok(ValidatedArgs::Verify {
+                args: query_args,
+                expectations: expectations.into(),
+                update_expectations: args.update_expectations,
+            }),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Verify subcommand requires exactly one path: the expectations file",
+            )),
+        },
+        "why" => match args.free.as_slice() {
+            [crate_name] => Ok(ValidatedArgs::Why {
+                args: query_args,
+                crate_name: crate_name.to_owned(),
+            }),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Why subcommand requires exactly one crate name",
+            )),
+        },
+        "diff" => match args.free.as_slice() {
+            [old, new] => Ok(ValidatedArgs::Diff {
+                old: old.into(),
+                new: new.into(),
+            }),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Diff subcommand requires exactly two paths: the old and the new output of the json subcommand",
+            )),
+        },
+        "update" => Ok(ValidatedArgs::Update {
+            cache_max_age,
+            registry_url: args.registry_url,
+            request_timeout,
+            dry_run: args.dry_run,
+        }),
+        "clean" => Ok(ValidatedArgs::Clean {
+            dry_run: args.dry_run,
+        }),
+        "help" => Ok(ValidatedArgs::Help {
+            command: args.free.first().map(String::to_owned),
+        }),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Unrecognized argument: {}", args.command.as_str()),
+        )),
+    }
 }
 
-fn dispatch_command(args: ValidatedArgs) -> Result<(), std::io::Error> {
+fn dispatch_command(
+    out: &mut dyn Write,
+    args: ValidatedArgs,
+) -> Result<(), cargo_supply_chain::Error> {
     match args {
         ValidatedArgs::Publishers {
+            args,
+            publishers_args,
+        } => subcommands::publishers(out, args, publishers_args)?,
+        ValidatedArgs::Crates { args, crates_args } => subcommands::crates(out, args, crates_args)?,
+        ValidatedArgs::Json { args, json_args } => subcommands::json(out, args, json_args)?,
+        ValidatedArgs::Owners { args } => subcommands::owners(out, args)?,
+        ValidatedArgs::Risks { args, risks_args } => subcommands::risks(out, args, risks_args)?,
+        ValidatedArgs::Stats { args } => subcommands::stats(out, args)?,
+        ValidatedArgs::Verify {
+            args,
+            expectations,
+            update_expectations,
+        } => subcommands::verify(out, args, &expectations, update_expectations)?,
+        ValidatedArgs::Why { args, crate_name } => subcommands::why(out, args, &crate_name)?,
+        ValidatedArgs::Diff { old, new } => subcommands::diff(out, &old, &new)?,
+        ValidatedArgs::Update {
             cache_max_age,
-            diffable,
-            metadata_args,
-        } => subcommands::publishers(metadata_args, diffable, cache_max_age)?,
-        ValidatedArgs::Crates {
-            cache_max_age,
-            diffable,
-            metadata_args,
-        } => subcommands::crates(metadata_args, diffable, cache_max_age)?,
-        ValidatedArgs::Json {
+            registry_url,
+            request_timeout,
+            dry_run,
+        } => subcommands::update(
             cache_max_age,
-            diffable,
-            metadata_args,
-        } => subcommands::json(metadata_args, diffable, cache_max_age)?,
-        ValidatedArgs::Update { cache_max_age } => subcommands::update(cache_max_age),
-        ValidatedArgs::Help { command } => subcommands::help(command.as_deref()),
+            registry_url.as_deref(),
+            request_timeout,
+            dry_run,
+        )?,
+        ValidatedArgs::Clean { dry_run } => subcommands::clean(dry_run)?,
+        ValidatedArgs::Help { command } => subcommands::help(command.as_deref())?,
     }
 
     Ok(())
 }
 
+const DEFAULT_CACHE_MAX_AGE: Duration = Duration::from_secs(48 * 3600);
+
+/// Accepts a human readable duration such as `1w` or `1d 6h`. A bare `0` is accepted too,
+/// since it needs no unit to be unambiguous.
 fn parse_max_age(text: &str) -> Result<Duration, humantime::DurationError> {
-    humantime::parse_duration(&text)
+    if text.trim() == "0" {
+        return Ok(Duration::ZERO);
+    }
+    humantime::parse_duration(text)
+}
+
+/// Accepts a date such as `2024-03-01`, taken as midnight UTC, or an RFC 3339 timestamp
+fn parse_date(text: &str) -> Result<SystemTime, humantime::TimestampError> {
+    if text.len() == 10 {
+        humantime::parse_rfc3339(&format!("{}T00:00:00Z", text))
+    } else {
+        humantime::parse_rfc3339_weak(text)
+    }
+}
+
+fn parse_path(text: &std::ffi::OsStr) -> Result<PathBuf, std::convert::Infallible> {
+    Ok(text.into())
 }
 
 /// Separates arguments intended for us and for cargo-metadata
@@ -196,28 +1085,127 @@ fn separate_metadata_args() -> (Vec<OsString>, Vec<String>) {
     // When invoked via `cargo supply-chain update`, Cargo passes the arguments it receives verbatim.
     // So instead of "update" our binary receives "supply-chain update".
     // We ignore the "supply-chain" in the beginning if it's present.
-    if supply_args.get(0) == Some(&OsString::from("supply-chain")) {
+    if supply_args.first() == Some(&OsString::from("supply-chain")) {
         supply_args.remove(0);
     }
 
     (supply_args, metadata_args)
 }
 
+/// Replaces every `@path` among the arguments for `cargo metadata` with the lines of that file,
+/// skipping blank lines and `#` comments. Arguments read from a file are not expanded again.
+fn expand_response_files(args: Vec<String>) -> Result<Vec<String>, std::io::Error> {
+    let mut expanded = Vec::new();
+    for arg in args {
+        let path = match arg.strip_prefix('@') {
+            Some(path) => path,
+            None => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!(
+                    "Failed to read cargo metadata arguments from {}: {}",
+                    path, e
+                ),
+            )
+        })?;
+        expanded.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+    Ok(expanded)
+}
+
 /// Converts all recognized arguments into a struct.
 /// Does not check whether the argument is valid for the given subcommand.
 fn parse_args() -> Result<Args, pico_args::Error> {
     let (supply_args, metadata_args) = separate_metadata_args();
-    let default_cache_max_age = Duration::from_secs(48 * 3600);
     let mut args = Arguments::from_vec(supply_args);
     if let Some(command) = args.subcommand()? {
         let args = Args {
             help: args.contains(["-h", "--help"]),
+            verbosity: count_verbosity(&mut args),
+            quiet: args.contains(["-q", "--quiet"]),
             command,
             diffable: args.contains(["-d", "--diffable"]),
             metadata_args,
-            cache_max_age: args
-                .opt_value_from_fn("--cache-max-age", parse_max_age)?
-                .unwrap_or(default_cache_max_age),
+            cache_max_age: args.opt_value_from_fn("--cache-max-age", parse_max_age)?,
+            target: args.values_from_str("--target")?,
+            include_dev_dependencies: args.contains("--include-dev-dependencies"),
+            exclude_dev_dependencies: args.contains("--exclude-dev-dependencies"),
+            exclude_build_dependencies: args.contains("--exclude-build-dependencies"),
+            include_build_dependencies: args.contains("--include-build-dependencies"),
+            workspace_only: args.contains("--workspace-only"),
+            filter_crates: args.values_from_str("--filter-crate")?,
+            exclude: args.values_from_str("--exclude")?,
+            licenses: args.values_from_str("--license")?,
+            categories: args.values_from_str("--category")?,
+            direct_only: args.contains("--direct-only"),
+            build_time_only: args.contains("--build-time-only"),
+            depth: args.opt_value_from_str("--depth")?,
+            min_dependents: args.opt_value_from_str("--min-dependents")?,
+            all_features: args.contains("--all-features"),
+            include_inactive_optional: args.contains("--include-inactive-optional"),
+            no_default_features: args.contains("--no-default-features"),
+            default_features: args.contains("--default-features"),
+            features: args.opt_value_from_str("--features")?,
+            requests_per_second: args.opt_value_from_str("--requests-per-second")?,
+            jobs: args.opt_value_from_str(["-j", "--jobs"])?,
+            throttle: args.opt_value_from_str("--throttle")?,
+            color: args.opt_value_from_str("--color")?,
+            registry_url: args.opt_value_from_str("--registry-url")?,
+            request_timeout: args.opt_value_from_fn("--request-timeout", parse_max_age)?,
+            manifest_paths: args.values_from_os_str("--manifest-path", parse_path)?,
+            metadata_file: args.opt_value_from_os_str("--metadata-file", parse_path)?,
+            lockfile: args.opt_value_from_os_str("--lockfile", parse_path)?,
+            published_crate: args.opt_value_from_str("--crate")?,
+            offline: args.contains("--offline"),
+            locked: args.contains("--locked"),
+            frozen: args.contains("--frozen"),
+            no_cache: args.contains("--no-cache"),
+            allow_stale: args.contains("--allow-stale"),
+            keep_going: args.contains("--keep-going"),
+            include_yanked: args.contains("--include-yanked"),
+            fail_on_yanked: args.contains("--fail-on-yanked"),
+            allow: args.values_from_str("--allow")?,
+            trust_file: args.opt_value_from_os_str("--trust-file", parse_path)?,
+            untrusted_only: args.contains("--untrusted-only"),
+            first_party_orgs: args.values_from_str("--first-party-orgs")?,
+            hide_first_party: args.contains("--hide-first-party"),
+            format: args.opt_value_from_str("--format")?,
+            pretty: args.contains("--pretty"),
+            compact: args.contains("--compact"),
+            deduplicate_versions: args.contains("--deduplicate-versions"),
+            sort: args.opt_value_from_str("--sort")?,
+            sort_by_publishers: args.contains("--sort-by-publishers"),
+            group_by: args.opt_value_from_str("--group-by")?,
+            show_dates: args.contains("--show-dates"),
+            show_licenses: args.contains("--show-licenses"),
+            show_categories: args.contains("--show-categories"),
+            unmaintained_after: args.opt_value_from_fn("--unmaintained-after", parse_max_age)?,
+            owner_changes_within: args
+                .opt_value_from_fn("--owner-changes-within", parse_max_age)?,
+            since: args.opt_value_from_fn("--since", parse_date)?,
+            max_publishers: args.opt_value_from_str("--max-publishers")?,
+            top: args.opt_value_from_str("--top")?,
+            publisher: args.opt_value_from_str("--publisher")?,
+            logins_only: args.contains("--logins-only"),
+            per_target: args.contains("--per-target"),
+            expand_teams: args.contains("--expand-teams"),
+            only_teams: args.contains("--only-teams"),
+            only_users: args.contains("--only-users"),
+            update_expectations: args.contains("--update-expectations"),
+            dry_run: args.contains("--dry-run"),
+            output: args.opt_value_from_os_str(["-o", "--output"], parse_path)?,
+            timing: args.contains("--timing"),
             free: args.free()?,
         };
         Ok(args)
@@ -228,16 +1216,18 @@ fn parse_args() -> Result<Args, pico_args::Error> {
     }
 }
 
-fn eprint_help() {
-    eprintln!("{}", CLI_HELP);
+/// Counts `-v` and `--verbose`, accepting `-vv` as a shorthand for two of them
+fn count_verbosity(args: &mut Arguments) -> u8 {
+    let mut verbosity = 0u8;
+    while args.contains("-vv") {
+        verbosity = verbosity.saturating_add(2);
+    }
+    while args.contains(["-v", "--verbose"]) {
+        verbosity = verbosity.saturating_add(1);
+    }
+    verbosity
 }
 
-// TODO: remove all uses of this and return error from the function instead
-pub(crate) fn err_exit(msg: &str) -> ! {
-    match msg.into() {
-        Some(v) => eprintln!("{}", v),
-        None => (),
-    };
-
-    std::process::exit(1)
+fn eprint_help() {
+    eprintln!("{}", CLI_HELP);
 }
@@ -0,0 +1,129 @@
+//! Builds the dependency graph of a crate published on crates.io without a local checkout,
+//! so that its publishers can be vetted before adding it as a dependency.
+//!
+//! A throwaway project that depends on nothing but the crate is created in the temporary
+//! directory, and `cargo metadata` resolves its dependency graph. The project itself is then
+//! left out of the packages, so that only the crate and its dependencies are reported,
+//! with the crate as the one direct dependency.
+
+use crate::common::run_cargo_metadata;
+use crate::{Error, MetadataArgs};
+use cargo_metadata::{Metadata, Version};
+use std::{fs, io, path::PathBuf};
+
+/// Name of the throwaway project, which `cargo metadata` reports as the workspace member
+const PROJECT_NAME: &str = "cargo-supply-chain-vetting";
+
+/// Resolves the dependency graph of the crate given as `NAME` or `NAME@VERSION`,
+/// the newest version if none is given.
+/// Features of the crate are selected with `--features` and `--no-default-features`, as if it
+/// were a dependency declared with them.
+pub(crate) fn metadata_of_published_crate(
+    spec: &str,
+    metadata_args: &MetadataArgs,
+) -> Result<Metadata, Error> {
+    let (name, version) = parse_spec(spec)?;
+    let mut dependency = format!(
+        "version = {}",
+        toml::Value::String(match &version {
+            Some(version) => format!("={}", version),
+            None => "*".to_string(),
+        })
+    );
+    if let Some(features) = &metadata_args.features {
+        let features: Vec<String> = features
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .map(|f| toml::Value::String(f.to_string()).to_string())
+            .collect();
+        dependency.push_str(&format!(", features = [{}]", features.join(", ")));
+    }
+    if metadata_args.no_default_features {
+        dependency.push_str(", default-features = false");
+    }
+
+    let project = Project::create(
+        name,
+        &format!(
+            "[package]\nname = \"{}\"\nversion = \"0.0.0\"\npublish = false\n\n[dependencies]\n{} = {{ {} }}\n\n[workspace]\n",
+            PROJECT_NAME, name, dependency
+        ),
+    )?;
+    // The features were given to the dependency, and the project has none of its own
+    let project_args = MetadataArgs {
+        features: None,
+        no_default_features: false,
+        ..metadata_args.clone()
+    };
+    let mut metadata = run_cargo_metadata(&project_args, Some(&project.dir.join("Cargo.toml")))
+        .map_err(|e| {
+            Error::MetadataFailed(format!(
+                "Failed to resolve the dependencies of {}: {}",
+                spec, e
+            ))
+        })?;
+    let members = metadata.workspace_members.clone();
+    metadata
+        .packages
+        .retain(|package| !members.contains(&package.id));
+    Ok(metadata)
+}
+
+/// Splits `NAME[@VERSION]`, checking that the version is an exact one
+fn parse_spec(spec: &str) -> Result<(&str, Option<Version>), Error> {
+    let invalid = |reason: String| Error::InvalidInput(format!("--crate {}: {}", spec, reason));
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (spec, None),
+    };
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(invalid(format!("'{}' is not a valid crate name", name)));
+    }
+    let version = match version {
+        Some(version) => Some(Version::parse(version).map_err(|e| {
+            invalid(format!(
+                "expected an exact version such as 1.0.0 after '@': {}",
+                e
+            ))
+        })?),
+        None => None,
+    };
+    Ok((name, version))
+}
+
+/// The throwaway project, removed when dropped
+struct Project {
+    dir: PathBuf,
+}
+
+impl Project {
+    fn create(crate_name: &str, manifest: &str) -> Result<Self, io::Error> {
+        let dir = std::env::temp_dir().join(format!(
+            "{}-{}-{}",
+            PROJECT_NAME,
+            crate_name,
+            std::process::id()
+        ));
+        let project = Project { dir };
+        fs::create_dir_all(project.dir.join("src"))?;
+        fs::write(project.dir.join("Cargo.toml"), manifest)?;
+        // Cargo refuses to read a package without any targets
+        fs::write(project.dir.join("src").join("lib.rs"), "")?;
+        log::debug!(
+            "Created a project depending on {} in {}",
+            crate_name,
+            project.dir.display()
+        );
+        Ok(project)
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
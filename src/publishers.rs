@@ -1,9 +1,15 @@
-use crate::api_client::RateLimitedClient;
+use crate::api_cache::ApiCache;
+use crate::api_client::{origin, RateLimitedClient};
 use crate::crates_cache::{CacheState, CratesCache};
+use crate::timing::{self, Phase};
+use crate::{Error, MetadataArgs};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Instant;
 use std::{
-    collections::BTreeMap,
-    io::{self, ErrorKind},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    io, thread,
     time::Duration,
 };
 
@@ -14,18 +20,46 @@ use crate::common::*;
 #[derive(Deserialize)]
 struct UsersResponse {
     users: Vec<PublisherData>,
+    #[serde(default)]
+    meta: PageMeta,
 }
 
 #[derive(Deserialize)]
 struct TeamsResponse {
     teams: Vec<PublisherData>,
+    #[serde(default)]
+    meta: PageMeta,
 }
 
-/// Data about a single publisher received from a crates.io API endpoint
+#[derive(Deserialize)]
+struct VersionsResponse {
+    versions: Vec<VersionData>,
+    #[serde(default)]
+    meta: PageMeta,
+}
+
+/// Paginated endpoints link to the next page, and leave `next_page` out or null on the last one
+#[derive(Deserialize, Default)]
+struct PageMeta {
+    #[serde(default)]
+    next_page: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VersionData {
+    num: String,
+    yanked: bool,
+    created_at: String,
+}
+
+/// Data about a single publisher received from a crates.io API endpoint.
+/// Neither the API nor the daily dump tell whether a user is a crates.io administrator,
+/// so publishers are all treated alike.
 #[derive(JsonSchema, Serialize, Deserialize, Debug, Clone)]
 pub struct PublisherData {
     pub id: u64,
     pub login: String,
+    /// Either `"user"` or `"team"`. These values are stable across schema versions.
     pub kind: PublisherKind,
     // URL is disabled because it's present in API responses but not in DB dumps,
     // so the output would vary inconsistent depending on data source
@@ -34,6 +68,73 @@ pub struct PublisherData {
     pub name: Option<String>,
     /// Avatar image URL
     pub avatar: Option<String>,
+    /// Public profile pages of the publisher. Only filled in by the `json` subcommand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<PublisherContact>,
+    /// Number of crates on all of crates.io that the publisher can publish, not only those in
+    /// the dependency graph. Only known when the daily dump is used, and not cached along with API responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_crates: Option<usize>,
+}
+
+/// Public pages where a publisher can be reached, derived from their login.
+/// The crates.io API exposes no email addresses or homepages, so none are included.
+#[derive(JsonSchema, Serialize, Deserialize, Debug, Clone)]
+pub struct PublisherContact {
+    /// Profile of the user or team on crates.io
+    pub crates_io: String,
+    /// GitHub profile of a user, or the page of a team in its GitHub organization
+    pub github: Option<String>,
+}
+
+impl PublisherData {
+    /// Logins are the same regardless of the data source, so unlike the `url` field
+    /// in API responses this is available for data from the daily dump too
+    pub fn contact(&self) -> PublisherContact {
+        let (crates_io, github) = match self.kind {
+            PublisherKind::user => (
+                format!("https://crates.io/users/{}", self.login),
+                Some(format!("https://github.com/{}", self.login)),
+            ),
+            // Team logins look like `github:org:team`
+            PublisherKind::team => (
+                format!("https://crates.io/teams/{}", self.login),
+                match self.login.split(':').collect::<Vec<_>>().as_slice() {
+                    ["github", org, team] => {
+                        Some(format!("https://github.com/orgs/{}/teams/{}", org, team))
+                    }
+                    _ => None,
+                },
+            ),
+        };
+        PublisherContact { crates_io, github }
+    }
+}
+
+/// A user or team that became or stopped being a publisher of a crate
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OwnershipChange {
+    pub login: String,
+    pub kind: PublisherKind,
+    /// False if the publisher was removed
+    pub added: bool,
+    /// When the change was made, or when it was noticed, in RFC 3339
+    pub time: String,
+    /// The change was noticed by comparing a lookup through the crates.io API with an earlier one,
+    /// so it was made at some point between the two, and `time` is the later one
+    pub noticed: bool,
+}
+
+impl OwnershipChange {
+    pub(crate) fn noticed(publisher: &PublisherData, added: bool, time: &str) -> Self {
+        OwnershipChange {
+            login: publisher.login.clone(),
+            kind: publisher.kind,
+            added,
+            time: time.to_owned(),
+            noticed: true,
+        }
+    }
 }
 
 impl PartialEq for PublisherData {
@@ -42,14 +143,12 @@ impl PartialEq for PublisherData {
     }
 }
 
-impl Eq for PublisherData {
-    // holds for PublisherData because we're comparing u64 IDs, and it holds for u64
-    fn assert_receiver_is_total_eq(&self) {}
-}
+// holds for PublisherData because we're comparing u64 IDs, and it holds for u64
+impl Eq for PublisherData {}
 
 impl PartialOrd for PublisherData {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.id.cmp(&other.id))
+        Some(self.cmp(other))
     }
 }
 
@@ -59,9 +158,11 @@ impl Ord for PublisherData {
     }
 }
 
+/// Whether a publisher is an individual crates.io user or a team, such as a GitHub team
 #[derive(
-    JsonSchema, Serialize, Deserialize, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq,
+    JsonSchema, Serialize, Deserialize, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash,
 )]
+// The variants are lowercase because they are serialized by name, as "user" and "team"
 #[allow(non_camel_case_types)]
 pub enum PublisherKind {
     team,
@@ -69,91 +170,355 @@ pub enum PublisherKind {
 }
 
 pub fn publisher_users(
-    client: &mut RateLimitedClient,
+    client: &RateLimitedClient,
     crate_name: &str,
-) -> Result<Vec<PublisherData>, io::Error> {
-    let url = format!("https://crates.io/api/v1/crates/{}/owner_user", crate_name);
-    let resp = get_with_retry(&url, client, 3)?;
-    let data: UsersResponse = resp.into_json()?;
-    Ok(data.users)
+) -> Result<Vec<PublisherData>, Error> {
+    let url = client.api_url(&format!("crates/{}/owner_user", crate_name));
+    get_all_pages(client, &url, |data: UsersResponse| (data.users, data.meta))
+}
+
+/// Returns all yanked versions of the crate
+pub fn yanked_versions(client: &RateLimitedClient, crate_name: &str) -> Result<Vec<String>, Error> {
+    let versions = all_versions(client, crate_name)?;
+    let yanked = versions.into_iter().filter(|v| v.yanked);
+    Ok(yanked.map(|v| v.num).collect())
+}
+
+fn all_versions(client: &RateLimitedClient, crate_name: &str) -> Result<Vec<VersionData>, Error> {
+    let url = client.api_url(&format!("crates/{}/versions", crate_name));
+    get_all_pages(client, &url, |data: VersionsResponse| {
+        (data.versions, data.meta)
+    })
 }
 
 pub fn publisher_teams(
-    client: &mut RateLimitedClient,
+    client: &RateLimitedClient,
     crate_name: &str,
-) -> Result<Vec<PublisherData>, io::Error> {
-    let url = format!("https://crates.io/api/v1/crates/{}/owner_team", crate_name);
-    let resp = get_with_retry(&url, client, 3)?;
-    let data: TeamsResponse = resp.into_json()?;
-    Ok(data.teams)
+) -> Result<Vec<PublisherData>, Error> {
+    let url = client.api_url(&format!("crates/{}/owner_team", crate_name));
+    get_all_pages(client, &url, |data: TeamsResponse| (data.teams, data.meta))
 }
 
-fn get_with_retry(
+/// The most pages `get_all_pages` follows, far more than any crate has owners or versions for.
+/// Otherwise a server that never stops paging would keep us going forever.
+const MAX_PAGES: usize = 1000;
+
+/// Requests `url` and every page after it, and returns the items of all of them.
+/// `split` takes apart the response to a single page.
+fn get_all_pages<R, T>(
+    client: &RateLimitedClient,
     url: &str,
-    client: &mut RateLimitedClient,
-    attempts: u8,
-) -> Result<ureq::Response, io::Error> {
-    let mut resp = client
-        .get(&url)
-        .call()
-        .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
-
-    let mut count = 1;
-    let mut wait = 5;
-    while resp.status() != 200 && count <= attempts {
-        eprintln!(
-            "Failed retrieving {:?}, trying again in {} seconds, attempt {}/{}",
-            url, wait, count, attempts
-        );
-        std::thread::sleep(std::time::Duration::from_secs(wait));
+    split: impl Fn(R) -> (Vec<T>, PageMeta),
+) -> Result<Vec<T>, Error>
+where
+    R: serde::de::DeserializeOwned,
+{
+    let mut items = Vec::new();
+    let mut visited = HashSet::new();
+    let mut page_url = url.to_owned();
+    loop {
+        let response = client.get_with_retry(&page_url)?;
+        // Reading the body may also fail halfway, which is not the server's fault
+        let (page, meta) = split(response.into_json().map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => {
+                Error::Parse(format!("Invalid response from {}: {}", page_url, e))
+            }
+            _ => Error::Io(e),
+        })?);
+        items.extend(page);
+        let next_url = match meta.next_page {
+            Some(next_page) => next_page_url(url, &next_page)?,
+            None => return Ok(items),
+        };
+        visited.insert(page_url);
+        // Otherwise a misbehaving server would keep us going in circles
+        if visited.contains(&next_url) {
+            return Err(Error::Parse(format!(
+                "The pages of {} link back to {} as the next page",
+                url, next_url
+            )));
+        }
+        if visited.len() == MAX_PAGES {
+            return Err(Error::Parse(format!(
+                "{} still links to a next page after {} pages",
+                url, MAX_PAGES
+            )));
+        }
+        log::debug!("Fetching the next page of {}", url);
+        page_url = next_url;
+    }
+}
+
+/// crates.io links to the next page with a query string such as `?page=2&per_page=10`,
+/// which replaces that of `url`. An absolute path is resolved on the host of `url`,
+/// and a full URL is used as is, unless it points at another host.
+fn next_page_url(url: &str, next_page: &str) -> Result<String, Error> {
+    if next_page.starts_with("https://") || next_page.starts_with("http://") {
+        if !origin(next_page).eq_ignore_ascii_case(origin(url)) {
+            return Err(Error::Parse(format!(
+                "{} links to another host as the next page: {}",
+                url, next_page
+            )));
+        }
+        return Ok(next_page.to_owned());
+    }
+    if next_page.starts_with('/') {
+        return Ok(format!("{}{}", origin(url), next_page));
+    }
+    let path = url.split('?').next().unwrap_or(url);
+    Ok(format!("{}?{}", path, next_page.trim_start_matches('?')))
+}
+
+/// What `fetch_owners_of_crates` reports on stderr
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Verbosity {
+    /// Nothing at all
+    Quiet,
+    /// The state of the cache and failures, but no progress bar
+    Normal,
+    /// Everything, as well as a progress bar if stderr is a terminal
+    Progress,
+}
+
+/// Maps crate names to the users or teams that can publish them
+pub type PublishersMap = BTreeMap<String, Vec<PublisherData>>;
 
-        resp = client
-            .get(&url)
-            .call()
-            .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+/// Publishers and yanked versions of crates.io crates, as returned by `fetch_owners_of_crates`
+#[derive(Debug, Clone, Default)]
+pub struct CrateOwners {
+    pub users: PublishersMap,
+    pub teams: PublishersMap,
+    /// Maps crate names to all of their yanked versions, not only those in the dependency graph
+    pub yanked: BTreeMap<String, Vec<String>>,
+    /// Maps crate names to the time their newest version was published, in RFC 3339
+    pub last_published: BTreeMap<String, String>,
+    /// Maps crate names to the publishers added or removed after the first version was published,
+    /// as far as they are known. Crates without any known changes are left out.
+    pub ownership_changes: BTreeMap<String, Vec<OwnershipChange>>,
+    /// Maps crate names to the error that prevented looking them up, if the client keeps going
+    /// despite failed lookups. These crates are absent from the other maps,
+    /// unlike crates that were looked up and found to have no publishers.
+    pub lookup_errors: BTreeMap<String, Arc<Error>>,
+    /// Names of the crates published on crates.io that nobody can publish anymore,
+    /// such as after all of their owners removed themselves.
+    /// Crates that were never published are not included, even though they have no publishers either.
+    pub ownerless: BTreeSet<String>,
+}
+
+impl CrateOwners {
+    /// Returns the versions of `crate_name` present in `versions` that have been yanked
+    pub fn yanked_in<'a>(&self, crate_name: &str, versions: &'a [String]) -> Vec<&'a String> {
+        let yanked = match self.yanked.get(crate_name) {
+            Some(yanked) => yanked,
+            None => return Vec::new(),
+        };
+        versions.iter().filter(|v| yanked.contains(v)).collect()
+    }
 
-        count += 1;
-        wait *= 3;
+    /// Forgets everything about `crates`, as if they weren't in the dependency graph
+    pub(crate) fn remove_crates(&mut self, crates: &BTreeSet<String>) {
+        self.users
+            .retain(|crate_name, _| !crates.contains(crate_name));
+        self.teams
+            .retain(|crate_name, _| !crates.contains(crate_name));
+        self.yanked
+            .retain(|crate_name, _| !crates.contains(crate_name));
+        self.last_published
+            .retain(|crate_name, _| !crates.contains(crate_name));
+        self.ownership_changes
+            .retain(|crate_name, _| !crates.contains(crate_name));
+        self.lookup_errors
+            .retain(|crate_name, _| !crates.contains(crate_name));
+        self.ownerless
+            .retain(|crate_name| !crates.contains(crate_name));
     }
 
-    Ok(resp)
+    /// Fails if any crate could not be looked up, listing all of them.
+    /// Meant to be called once the output is printed, so that the other crates are still reported.
+    pub fn check_lookups(&self) -> Result<(), Error> {
+        if self.lookup_errors.is_empty() {
+            return Ok(());
+        }
+        let errors = self
+            .lookup_errors
+            .iter()
+            .map(|(crate_name, error)| (crate_name.clone(), error.clone()))
+            .collect();
+        Err(Error::LookupsFailed(errors))
+    }
+}
+
+/// A crates.io crate from the dependency graph along with everyone who can publish it
+#[derive(JsonSchema, Serialize, Debug, Clone)]
+pub struct CratePublishers {
+    pub name: String,
+    /// Versions of the crate present in the dependency graph
+    pub versions: Vec<String>,
+    /// Versions of the crate present in the dependency graph that have been yanked
+    pub yanked_versions: Vec<String>,
+    /// Time the newest version of the crate was published, in RFC 3339
+    pub last_published: Option<String>,
+    /// Users and teams that can publish new versions of the crate
+    pub publishers: Vec<PublisherData>,
+    /// Whether the crate is published on crates.io but has no publishers at all,
+    /// which leaves it unmaintainable and open to being taken over
+    pub ownerless: bool,
+    /// Distinct license expressions of the versions present in the dependency graph,
+    /// as declared in their manifests. Versions that only point at a license file add none.
+    pub licenses: Vec<String>,
+}
+
+impl CratePublishers {
+    /// Describes `crate_name` as looked up in `data`, given its versions in the dependency graph
+    /// and their licenses
+    pub(crate) fn from_data(
+        crate_name: &str,
+        versions: Vec<String>,
+        licenses: Vec<String>,
+        data: &CrateData,
+    ) -> Self {
+        let mut publishers: Vec<PublisherData> =
+            data.users.iter().chain(&data.teams).cloned().collect();
+        publishers.sort_unstable_by_key(|p| (p.kind, p.login.clone()));
+        CratePublishers {
+            name: crate_name.to_owned(),
+            yanked_versions: versions
+                .iter()
+                .filter(|v| data.yanked.contains(v))
+                .cloned()
+                .collect(),
+            last_published: data.last_published.clone(),
+            ownerless: data.is_ownerless(),
+            versions,
+            licenses,
+            publishers,
+        }
+    }
+}
+
+/// Resolves the publishers of every crates.io crate in the dependency graph,
+/// looking up those missing from the cache through `client`, as `fetch_owners_of_crates` does.
+/// Unlike the subcommands, this doesn't print anything.
+///
+/// The result is sorted by crate name. Publishers of each crate are listed teams first.
+/// Fails with `Error::EmptyGraph` if there are no crates.io crates in the dependency graph.
+pub fn resolve_publishers(
+    metadata_args: &MetadataArgs,
+    client: &RateLimitedClient,
+    max_age: Duration,
+) -> Result<Vec<CratePublishers>, Error> {
+    let dependencies = sourced_dependencies(metadata_args)?;
+    let mut versions = crate_versions(&dependencies);
+    let mut licenses = crate_licenses(&dependencies);
+    let mut result = Vec::new();
+    let owners = stream_owners_of_crates(
+        &dependencies,
+        client,
+        max_age,
+        Verbosity::Quiet,
+        &mut |crate_name, data| {
+            let versions = versions.remove(crate_name).unwrap_or_default();
+            let licenses = licenses.remove(crate_name).unwrap_or_default();
+            result.push(CratePublishers::from_data(
+                crate_name, versions, licenses, data,
+            ));
+            Ok(())
+        },
+    )?;
+    owners.check_lookups()?;
+    // Fetched crates are passed in order of completion
+    result.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    Ok(result)
 }
 
+/// Fetches publisher users and teams, as well as yanked versions and publication times,
+/// for every crates.io crate in `dependencies`,
+/// using the local cache if it's present and younger than `max_age`.
+/// Crates missing from the cache are looked up through `client`,
+/// and the responses are cached on disk for `max_age` as well.
+/// If `client` is offline, the caches are used regardless of their age,
+/// and crates missing from it are reported as an error.
+/// Crates are fetched concurrently. The first one that fails to be looked up fails the whole lookup
+/// with `Error::LookupsFailed`, unless `client` keeps going: then those that fail are recorded in
+/// `lookup_errors` instead; see `CrateOwners::check_lookups()`.
+/// Progress and cache status are reported on stderr according to `verbosity`.
 pub fn fetch_owners_of_crates(
     dependencies: &[SourcedPackage],
+    client: &RateLimitedClient,
     max_age: Duration,
-) -> Result<
-    (
-        BTreeMap<String, Vec<PublisherData>>,
-        BTreeMap<String, Vec<PublisherData>>,
-    ),
-    io::Error,
-> {
-    let crates_io_names = crate_names_from_source(&dependencies, PkgSource::CratesIo);
-    let mut client = RateLimitedClient::new();
+    verbosity: Verbosity,
+) -> Result<CrateOwners, Error> {
+    stream_owners_of_crates(dependencies, client, max_age, verbosity, &mut |_, _| Ok(()))
+}
+
+/// Like `fetch_owners_of_crates`, but also passes each crate to `on_resolved` as soon as it is
+/// looked up: cached crates in order of name, then fetched ones in order of completion.
+/// Crates that fail to be looked up are not passed to it.
+/// An error returned by `on_resolved` stops the lookup and is returned once requests in flight complete.
+pub(crate) fn stream_owners_of_crates(
+    dependencies: &[SourcedPackage],
+    client: &RateLimitedClient,
+    max_age: Duration,
+    verbosity: Verbosity,
+    on_resolved: &mut dyn FnMut(&str, &CrateData) -> Result<(), Error>,
+) -> Result<CrateOwners, Error> {
+    let quiet = verbosity == Verbosity::Quiet;
+    let crates_io_names = crate_names_from_source(dependencies, PkgSource::CratesIo);
+    let cache_load_started = Instant::now();
     let mut cached = CratesCache::new();
-    let using_cache = match cached.expire(max_age) {
+    let offline = client.is_offline();
+    let cache_state = if offline {
+        cached.state(max_age)
+    } else {
+        cached.expire(max_age)
+    };
+    let using_cache = match cache_state {
+        _ if client.ignores_cache() => {
+            cached.disable();
+            false
+        }
         CacheState::Fresh => true,
+        // Reported below along with any other stale dump
+        CacheState::Expired if offline => true,
+        CacheState::Unknown if offline => {
+            return Err(Error::Cache("The `crates.io` cache was not found or it is invalid, and --offline forbids fetching live data.\n  Run `cargo supply-chain update` while online to generate it.".to_string()));
+        }
+        CacheState::Expired if max_age.is_zero() => {
+            if !quiet {
+                eprintln!(
+                    "\nIgnoring the cache, since --cache-max-age 0 always fetches live data."
+                );
+            }
+            false
+        }
         CacheState::Expired => {
-            eprintln!(
-                "\nIgnoring expired cache, older than {}.",
-                // we use humantime rather than indicatif because we take humantime input
-                // and here we simply repeat it back to the user
-                humantime::format_duration(max_age)
-            );
-            eprintln!("  Run `cargo supply-chain update` to update it.");
+            if !quiet {
+                eprintln!(
+                    "\nIgnoring expired cache, older than {}.",
+                    // we use humantime rather than indicatif because we take humantime input
+                    // and here we simply repeat it back to the user
+                    humantime::format_duration(max_age)
+                );
+                eprintln!("  Run `cargo supply-chain update` to update it.");
+            }
             false
         }
         CacheState::Unknown => {
-            eprintln!("\nThe `crates.io` cache was not found or it is invalid.");
-            eprintln!("  Run `cargo supply-chain update` to generate it.");
+            if !quiet {
+                eprintln!("\nThe `crates.io` cache was not found or it is invalid.");
+                eprintln!("  Run `cargo supply-chain update` to generate it.");
+            }
             false
         }
     };
-    let mut users: BTreeMap<String, Vec<PublisherData>> = BTreeMap::new();
-    let mut teams: BTreeMap<String, Vec<PublisherData>> = BTreeMap::new();
+    let mut owners = CrateOwners::default();
 
-    if using_cache {
+    if using_cache && !client.allows_stale() {
+        warn_if_stale(&mut cached, max_age);
+    }
+    if quiet {
+        // Nothing to report
+    } else if using_cache {
         let age = cached.age().unwrap();
         eprintln!(
             "\nUsing cached data. Cache age: {}",
@@ -161,34 +526,474 @@ pub fn fetch_owners_of_crates(
         );
     } else {
         eprintln!("\nFetching publisher info from crates.io");
-        eprintln!("This will take roughly 2 seconds per crate due to API rate limits");
+        eprintln!(
+            "This will take roughly {} per crate due to API rate limits",
+            // three requests per crate: for users, teams and versions
+            humantime::format_duration(client.min_interval() * 3)
+        );
     }
 
-    let bar = indicatif::ProgressBar::new(crates_io_names.len() as u64)
+    // indicatif doesn't draw anything if stderr is not a terminal, such as in CI
+    let bar = if verbosity == Verbosity::Progress {
+        indicatif::ProgressBar::new(crates_io_names.len() as u64)
+    } else {
+        indicatif::ProgressBar::hidden()
+    }
     .with_prefix("Preparing")
     .with_style(
         indicatif::ProgressStyle::default_bar()
-        .template("{prefix:>12.bright.cyan} [{bar:27}] {pos:>4}/{len:4} ETA {eta:3} - {msg:.cyan}")
-        .progress_chars("=> ")
+            .template(
+                "{prefix:>12.bright.cyan} [{bar:27}] resolved {pos} of {len} crates, ETA {eta:3} - {msg:.cyan}",
+            )
+            .progress_chars("=> "),
     );
 
-    for (i, crate_name) in crates_io_names.iter().enumerate() {
-        bar.set_message(crate_name.clone());
-        bar.set_position((i + 1) as u64);
+    let mut api_cache = ApiCache::load();
+    // Unless offline, entries in the API cache expire just like the database dump does.
+    // With --no-cache, every entry is considered expired.
+    let api_cache_max_age = if client.ignores_cache() {
+        Some(Duration::from_secs(0))
+    } else if offline {
+        None
+    } else {
+        Some(max_age)
+    };
+    let crate_counts = if using_cache {
+        cached.owned_crate_counts()
+    } else {
+        None
+    };
+    let count_crates = |data: &mut CrateData| {
+        for publisher in data.users.iter_mut().chain(data.teams.iter_mut()) {
+            publisher.total_crates = crate_counts
+                .as_ref()
+                .and_then(|counts| counts.get(&(publisher.kind, publisher.id)).copied());
+        }
+    };
+    let mut to_fetch: Vec<&String> = Vec::new();
+    // Counted for the summary at the end, which tells whether the cache is being used as expected
+    let mut resolved_from_cache = 0;
+    let mut resolved_from_network = 0;
+    let mut dump_outdated = !cached.tracks_last_published();
+    for crate_name in &crates_io_names {
         let cached_users = cached.publisher_users(crate_name);
         let cached_teams = cached.publisher_teams(crate_name);
-        if let (Some(pub_users), Some(pub_teams)) = (cached_users, cached_teams) {
+        let from_cache = match (cached_users, cached_teams) {
+            (Some(users), Some(teams)) => {
+                log::debug!("{}: found in the daily dump", crate_name);
+                let yanked = cached.yanked_versions(crate_name).unwrap_or_else(|| {
+                    dump_outdated = true;
+                    Vec::new()
+                });
+                Some(CrateData {
+                    users,
+                    teams,
+                    yanked,
+                    last_published: cached.last_published(crate_name),
+                    ownership_changes: cached.owners_added(crate_name).unwrap_or_default(),
+                    published: true,
+                })
+            }
+            _ => api_cache.get(crate_name, api_cache_max_age),
+        };
+        if let Some(mut data) = from_cache {
+            count_crates(&mut data);
             bar.set_prefix("Loading cache");
-            users.insert(crate_name.clone(), pub_users);
-            teams.insert(crate_name.clone(), pub_teams);
+            bar.inc(1);
+            resolved_from_cache += 1;
+            on_resolved(crate_name, &data)?;
+            data.insert_into(crate_name, &mut owners);
+        } else if offline {
+            bar.inc(1);
+            let error = Arc::new(Error::Cache(
+                "not found in the cache, and --offline forbids fetching it from crates.io"
+                    .to_string(),
+            ));
+            if !client.keeps_going() {
+                bar.finish_and_clear();
+                return Err(Error::LookupsFailed(vec![(crate_name.clone(), error)]));
+            }
+            owners.lookup_errors.insert(crate_name.clone(), error);
         } else {
-            // Handle crates not found in the cache by fetching live data for them
-            bar.set_prefix("Downloading");
-            let pusers = publisher_users(&mut client, crate_name)?;
-            users.insert(crate_name.clone(), pusers);
-            let pteams = publisher_teams(&mut client, crate_name)?;
-            teams.insert(crate_name.clone(), pteams);
+            log::debug!("{}: will be fetched from crates.io", crate_name);
+            to_fetch.push(crate_name);
+        }
+    }
+
+    // Handle crates not found in the cache by fetching live data for them
+    if !to_fetch.is_empty() {
+        bar.set_prefix("Downloading");
+    }
+    timing::record(Phase::CacheLoad, cache_load_started.elapsed());
+    let mut unpublished = Vec::new();
+    let fetches_started = Instant::now();
+    let streamed = fetch_concurrently(client, &to_fetch, &bar, |crate_name, result| {
+        match result {
+            Ok(mut data) => {
+                api_cache.insert(crate_name, &mut data);
+                count_crates(&mut data);
+                resolved_from_network += 1;
+                on_resolved(crate_name, &data)?;
+                data.insert_into(crate_name, &mut owners);
+            }
+            // Workspace members may not have been published yet. Then nobody can publish them so far.
+            Err(e) if e.is_not_found() => {
+                let data = CrateData::unpublished();
+                resolved_from_network += 1;
+                on_resolved(crate_name, &data)?;
+                data.insert_into(crate_name, &mut owners);
+                unpublished.push(crate_name);
+            }
+            Err(e) if !client.keeps_going() => {
+                return Err(Error::LookupsFailed(vec![(
+                    crate_name.clone(),
+                    Arc::new(e),
+                )]));
+            }
+            Err(e) => {
+                owners.lookup_errors.insert(crate_name.clone(), Arc::new(e));
+            }
         }
+        Ok(())
+    });
+    bar.finish_and_clear();
+    if !to_fetch.is_empty() {
+        timing::record(Phase::ApiFetches, fetches_started.elapsed());
+    }
+    // Reported in order of name rather than of completion, so that the output is the same every time
+    unpublished.sort_unstable();
+    for crate_name in unpublished {
+        if !quiet {
+            eprintln!(
+                "Crate `{}` is not published on crates.io, so it has no publishers",
+                crate_name
+            );
+        }
+    }
+    if !client.allows_ownerless() {
+        warn_about_ownerless(&owners.ownerless);
+    }
+    if dump_outdated && using_cache && !quiet {
+        eprintln!("\nThe `crates.io` cache predates tracking of yanked versions and publication times, so they are not reported.");
+        eprintln!("  Run `cargo supply-chain update` to update it.");
+    }
+
+    // Keep whatever was fetched despite the errors, so that a retry picks up where we left off
+    if let Err(e) = api_cache.save() {
+        if !quiet {
+            eprintln!(
+                "\nWarning: failed to save fetched publishers to the cache: {}",
+                e
+            );
+        }
+    }
+    streamed?;
+    if !quiet && !crates_io_names.is_empty() {
+        eprintln!(
+            "\nResolved {} crate{}: {} from cache, {} from network",
+            resolved_from_cache + resolved_from_network,
+            if resolved_from_cache + resolved_from_network == 1 {
+                ""
+            } else {
+                "s"
+            },
+            resolved_from_cache,
+            resolved_from_network
+        );
+    }
+    Ok(owners)
+}
+
+/// Warns, even with `--quiet`, about published crates that nobody can publish anymore.
+/// Nobody can fix or update them, and whoever crates.io hands them to controls what they contain next.
+fn warn_about_ownerless(ownerless: &BTreeSet<String>) {
+    if ownerless.is_empty() {
+        return;
+    }
+    let (crates, have, them) = if ownerless.len() == 1 {
+        ("crate", "has", "it")
+    } else {
+        ("crates", "have", "them")
+    };
+    let names: Vec<String> = ownerless.iter().cloned().collect();
+    eprintln!(
+        "\nWarning: {} {} published on crates.io {} no owners left, so nobody can release fixes for {}: {}\n  Whoever crates.io transfers {} to can publish new versions. Consider replacing {}.",
+        ownerless.len(),
+        crates,
+        have,
+        them,
+        comma_separated_list(&names),
+        them,
+        them
+    );
+}
+
+/// Warns, even with `--quiet`, when the cached dump itself is older than `max_age`.
+/// That happens with `--offline`, and also when a copied cache or a mirror serving an old dump
+/// keeps being confirmed as current.
+fn warn_if_stale(cached: &mut CratesCache, max_age: Duration) {
+    let age = match cached.dump_age() {
+        Some(age) if age >= max_age => age,
+        _ => return,
+    };
+    eprintln!(
+        "\nWarning: the cached crates.io dump was generated {} ago, longer than --cache-max-age of {}.\n  Publisher data may be out of date. Run `cargo supply-chain update` to refresh it,\n  or pass --allow-stale if this is intended.",
+        humantime::format_duration(Duration::from_secs(age.as_secs())),
+        humantime::format_duration(max_age)
+    );
+}
+
+/// Looks up the publishers of `crate_names` using up to `client.concurrency()` threads,
+/// passing each result to `on_result` on the calling thread in order of completion.
+/// If `on_result` fails, no more requests are started and its error is returned.
+fn fetch_concurrently<'a>(
+    client: &RateLimitedClient,
+    crate_names: &[&'a String],
+    bar: &indicatif::ProgressBar,
+    mut on_result: impl FnMut(&'a String, PublishersResult) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..client.concurrency().min(crate_names.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let crate_name = match crate_names.get(i) {
+                    Some(crate_name) => *crate_name,
+                    None => break,
+                };
+                bar.set_message(crate_name.to_string());
+                let result = fetch_crate_data(client, crate_name);
+                bar.inc(1);
+                if sender.send((crate_name, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        // The loop below ends once every worker is done and has dropped its sender
+        drop(sender);
+        for (crate_name, result) in receiver {
+            if let Err(e) = on_result(crate_name, result) {
+                next.store(crate_names.len(), Ordering::Relaxed);
+                return Err(e);
+            }
+        }
+        Ok(())
+    })
+}
+
+type PublishersResult = Result<CrateData, Error>;
+
+fn fetch_crate_data(client: &RateLimitedClient, crate_name: &str) -> PublishersResult {
+    let users = publisher_users(client, crate_name)?;
+    let teams = publisher_teams(client, crate_name)?;
+    let versions = all_versions(client, crate_name)?;
+    let last_published = versions
+        .iter()
+        .filter_map(|v| normalize_timestamp(&v.created_at))
+        .max();
+    Ok(CrateData {
+        users,
+        teams,
+        yanked: versions
+            .into_iter()
+            .filter(|v| v.yanked)
+            .map(|v| v.num)
+            .collect(),
+        last_published,
+        // Filled in by comparing with the previous lookup, if there was one
+        ownership_changes: Vec::new(),
+        published: true,
+    })
+}
+
+/// Everything we look up about a single crate
+pub(crate) struct CrateData {
+    pub users: Vec<PublisherData>,
+    pub teams: Vec<PublisherData>,
+    pub yanked: Vec<String>,
+    pub last_published: Option<String>,
+    pub ownership_changes: Vec<OwnershipChange>,
+    /// False if crates.io doesn't know the crate
+    pub published: bool,
+}
+
+impl CrateData {
+    fn unpublished() -> Self {
+        CrateData {
+            users: Vec::new(),
+            teams: Vec::new(),
+            yanked: Vec::new(),
+            last_published: None,
+            ownership_changes: Vec::new(),
+            published: false,
+        }
+    }
+
+    /// Whether the crate is published but nobody can publish it anymore
+    pub(crate) fn is_ownerless(&self) -> bool {
+        self.published && self.users.is_empty() && self.teams.is_empty()
+    }
+
+    fn insert_into(self, crate_name: &str, owners: &mut CrateOwners) {
+        if self.is_ownerless() {
+            owners.ownerless.insert(crate_name.to_owned());
+        }
+        owners.users.insert(crate_name.to_owned(), self.users);
+        owners.teams.insert(crate_name.to_owned(), self.teams);
+        owners.yanked.insert(crate_name.to_owned(), self.yanked);
+        if let Some(last_published) = self.last_published {
+            owners
+                .last_published
+                .insert(crate_name.to_owned(), last_published);
+        }
+        if !self.ownership_changes.is_empty() {
+            owners
+                .ownership_changes
+                .insert(crate_name.to_owned(), self.ownership_changes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Answers requests for the paths in `pages` with their bodies, until all have been requested,
+    /// and returns the URL to use as the registry
+    fn serve(pages: Vec<(&'static str, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let mut remaining = pages;
+            while !remaining.is_empty() {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let path = request_line.split(' ').nth(1).unwrap().to_owned();
+                let response = match remaining.iter().position(|(p, _)| *p == path) {
+                    Some(i) => {
+                        let body = remaining.remove(i).1;
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                    }
+                    None => {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_owned()
+                    }
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url
+    }
+
+    fn users_page(login: &str, next_page: Option<&str>) -> String {
+        serde_json::json!({
+            "users": [{ "id": login.len(), "login": login, "kind": "user", "name": null, "avatar": null }],
+            "meta": { "next_page": next_page },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn owners_on_every_page_are_merged() {
+        let url = serve(vec![
+            (
+                "/api/v1/crates/foo/owner_user",
+                users_page("first", Some("?page=2&per_page=1")),
+            ),
+            (
+                "/api/v1/crates/foo/owner_user?page=2&per_page=1",
+                users_page(
+                    "second",
+                    Some("/api/v1/crates/foo/owner_user?page=3&per_page=1"),
+                ),
+            ),
+            (
+                "/api/v1/crates/foo/owner_user?page=3&per_page=1",
+                users_page("third", None),
+            ),
+        ]);
+        let client = RateLimitedClient::new()
+            .with_registry_url(&url)
+            .with_rate_limit(1000.0)
+            .with_retries(0, Duration::ZERO);
+        let users = publisher_users(&client, "foo").unwrap();
+        let logins: Vec<&str> = users.iter().map(|user| user.login.as_str()).collect();
+        assert_eq!(logins, ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn pages_linking_back_to_earlier_ones_are_an_error() {
+        let url = serve(vec![
+            (
+                "/api/v1/crates/foo/owner_user",
+                users_page("first", Some("?page=2")),
+            ),
+            (
+                "/api/v1/crates/foo/owner_user?page=2",
+                users_page("second", Some("/api/v1/crates/foo/owner_user")),
+            ),
+        ]);
+        let client = RateLimitedClient::new()
+            .with_registry_url(&url)
+            .with_rate_limit(1000.0)
+            .with_retries(0, Duration::ZERO);
+        let error = publisher_users(&client, "foo").unwrap_err();
+        assert!(matches!(error, Error::Parse(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn a_relative_next_page_replaces_the_query_string() {
+        assert_eq!(
+            next_page_url(
+                "https://crates.io/api/v1/crates/foo/owner_user?per_page=1",
+                "?page=2&per_page=1"
+            )
+            .unwrap(),
+            "https://crates.io/api/v1/crates/foo/owner_user?page=2&per_page=1"
+        );
+    }
+
+    #[test]
+    fn an_absolute_next_page_path_is_resolved_on_the_same_host() {
+        assert_eq!(
+            next_page_url(
+                "http://localhost:8080/api/v1/crates/foo/versions",
+                "/api/v1/crates/foo/versions?page=2"
+            )
+            .unwrap(),
+            "http://localhost:8080/api/v1/crates/foo/versions?page=2"
+        );
+    }
+
+    #[test]
+    fn a_full_next_page_url_must_be_on_the_same_host() {
+        assert_eq!(
+            next_page_url(
+                "https://crates.io/api/v1/crates/foo/versions",
+                "https://CRATES.IO/api/v1/crates/foo/versions?page=2"
+            )
+            .unwrap(),
+            "https://CRATES.IO/api/v1/crates/foo/versions?page=2"
+        );
+        assert!(next_page_url(
+            "https://crates.io/api/v1/crates/foo/versions",
+            "https://example.com/api/v1/crates/foo/versions?page=2"
+        )
+        .is_err());
     }
-    Ok((users, teams))
 }
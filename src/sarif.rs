@@ -0,0 +1,245 @@
+//! Output in the Static Analysis Results Interchange Format (SARIF) 2.1.0,
+//! which code scanning dashboards such as GitHub's can ingest.
+//!
+//! Findings point at the line of `Cargo.toml` that declares the dependency.
+//! Crates that are not declared directly by any workspace member point at their entry in `Cargo.lock` instead.
+//! Paths are relative to the workspace root.
+
+use crate::Error;
+use cargo_metadata::{Metadata, Package, Version};
+use serde_json::{json, Value};
+use std::{fs, io::Write, path::Path};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum Rule {
+    SingleOwner,
+    YankedVersion,
+    OwnershipChanged,
+    Ownerless,
+}
+
+impl Rule {
+    const ALL: [Rule; 4] = [
+        Rule::SingleOwner,
+        Rule::YankedVersion,
+        Rule::OwnershipChanged,
+        Rule::Ownerless,
+    ];
+
+    fn id(self) -> &'static str {
+        match self {
+            Rule::SingleOwner => "single-owner",
+            Rule::YankedVersion => "yanked-version",
+            Rule::OwnershipChanged => "ownership-changed",
+            Rule::Ownerless => "ownerless",
+        }
+    }
+
+    fn level(self) -> &'static str {
+        match self {
+            Rule::SingleOwner => "warning",
+            Rule::YankedVersion => "error",
+            Rule::OwnershipChanged => "warning",
+            Rule::Ownerless => "error",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Rule::SingleOwner => "Crate can be published by a single individual and no teams",
+            Rule::YankedVersion => "Dependency graph contains a yanked version of a crate",
+            Rule::OwnershipChanged => "Publishers of the crate changed recently",
+            Rule::Ownerless => "Crate is published on crates.io but has no owners left",
+        }
+    }
+
+    fn help(self) -> &'static str {
+        match self {
+            Rule::SingleOwner => "A single compromised account is enough to publish a malicious update to this crate.",
+            Rule::YankedVersion => "Versions are usually yanked because they are broken or have a security vulnerability. Update to a version that has not been yanked.",
+            Rule::OwnershipChanged => "New publishers are usually legitimate maintainers, but a takeover of a crate starts the same way. Check that the change was announced by the previous publishers.",
+            Rule::Ownerless => "Nobody can release fixes for this crate, and whoever crates.io transfers it to can publish new versions. Replace it with a maintained crate.",
+        }
+    }
+}
+
+pub(crate) struct Finding {
+    pub rule: Rule,
+    pub crate_name: String,
+    /// The version the finding is about, if it's about one version rather than the whole crate
+    pub version: Option<String>,
+    pub message: String,
+}
+
+/// Prints `findings` as a SARIF log with a single run
+pub(crate) fn print(
+    out: &mut dyn Write,
+    mut findings: Vec<Finding>,
+    meta: &Metadata,
+) -> Result<(), Error> {
+    findings.sort_by(|a, b| (a.rule, &a.crate_name).cmp(&(b.rule, &b.crate_name)));
+    let locator = Locator::new(meta);
+    let rules: Vec<Value> = Rule::ALL
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+                "help": { "text": rule.help() },
+                "defaultConfiguration": { "level": rule.level() },
+            })
+        })
+        .collect();
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "ruleId": finding.rule.id(),
+                "level": finding.rule.level(),
+                "message": { "text": finding.message },
+                "locations": [locator.locate(&finding.crate_name, finding.version.as_deref())],
+            })
+        })
+        .collect();
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cargo-supply-chain",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/rust-secure-code/cargo-supply-chain",
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    });
+    serde_json::to_writer_pretty(&mut *out, &log)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Finds the lines declaring dependencies in the manifests of the workspace
+struct Locator<'a> {
+    /// Workspace members with the paths to their manifests relative to the workspace root,
+    /// and the contents of the manifests if they could be read
+    members: Vec<(&'a Package, String, Option<String>)>,
+    /// The root manifest, which also declares `[workspace.dependencies]`
+    root_manifest: (String, Option<String>),
+    lockfile: Option<String>,
+}
+
+impl<'a> Locator<'a> {
+    fn new(meta: &'a Metadata) -> Self {
+        let root = &meta.workspace_root;
+        let mut members: Vec<_> = meta
+            .packages
+            .iter()
+            .filter(|package| meta.workspace_members.contains(&package.id))
+            .map(|package| {
+                let path = &package.manifest_path;
+                (
+                    package,
+                    relative_uri(root, path),
+                    fs::read_to_string(path).ok(),
+                )
+            })
+            .collect();
+        members.sort_unstable_by(|a, b| a.1.cmp(&b.1));
+        let root_manifest_path = root.join("Cargo.toml");
+        Locator {
+            members,
+            root_manifest: (
+                relative_uri(root, &root_manifest_path),
+                fs::read_to_string(&root_manifest_path).ok(),
+            ),
+            lockfile: fs::read_to_string(root.join("Cargo.lock")).ok(),
+        }
+    }
+
+    /// Points at the declaration of a dependency on `crate_name`,
+    /// preferring the one whose version requirement matches `version` if specified
+    fn locate(&self, crate_name: &str, version: Option<&str>) -> Value {
+        let version = version.and_then(|v| Version::parse(v).ok());
+        for (package, uri, contents) in &self.members {
+            let declared = package.dependencies.iter().filter(|dep| {
+                dep.name == crate_name && version.as_ref().is_none_or(|v| dep.req.matches(v))
+            });
+            for dep in declared {
+                // Renamed dependencies are declared under their new name
+                let key = dep.rename.as_deref().unwrap_or(&dep.name);
+                if let Some(line) = contents.as_deref().and_then(|c| dependency_line(c, key)) {
+                    return physical_location(uri, Some(line));
+                }
+            }
+        }
+        let (root_uri, root_contents) = &self.root_manifest;
+        if let Some(line) = root_contents
+            .as_deref()
+            .and_then(|c| dependency_line(c, crate_name))
+        {
+            return physical_location(root_uri, Some(line));
+        }
+        let needle = format!("name = \"{}\"", crate_name);
+        if let Some(line) = self
+            .lockfile
+            .as_deref()
+            .and_then(|lockfile| lockfile.lines().position(|l| l.trim() == needle))
+        {
+            return physical_location("Cargo.lock", Some(line + 1));
+        }
+        physical_location(root_uri, None)
+    }
+}
+
+fn physical_location(uri: &str, line: Option<usize>) -> Value {
+    let mut location = json!({ "artifactLocation": { "uri": uri } });
+    if let Some(line) = line {
+        location["region"] = json!({ "startLine": line });
+    }
+    json!({ "physicalLocation": location })
+}
+
+fn relative_uri(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let components: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    components.join("/")
+}
+
+/// Returns the 1-based number of the line in a manifest that declares the dependency `key`,
+/// either in a dependency table or as a table of its own such as `[dependencies.serde]`
+fn dependency_line(manifest: &str, key: &str) -> Option<usize> {
+    let own_table = format!("dependencies.{}", key);
+    let mut in_dependencies = false;
+    for (i, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            let header = line.trim_matches(|c| c == '[' || c == ']').trim();
+            if header.ends_with(&own_table) {
+                return Some(i + 1);
+            }
+            // Also covers `[dev-dependencies]`, `[target.'cfg(unix)'.dependencies]` and so on
+            in_dependencies = header.ends_with("dependencies");
+            continue;
+        }
+        if !in_dependencies {
+            continue;
+        }
+        let line_key = line.split('=').next().unwrap_or("").trim();
+        // Keys such as `serde.workspace = true`
+        let line_key = line_key
+            .split('.')
+            .next()
+            .unwrap_or(line_key)
+            .trim_matches('"');
+        if line_key == key {
+            return Some(i + 1);
+        }
+    }
+    None
+}
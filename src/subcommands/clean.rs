@@ -0,0 +1,74 @@
+//! `clean` subcommand deletes the local cache, to reclaim the space taken by the daily dump
+//! or to start over when the cache is in a bad state.
+//! Only the files this tool creates are deleted, since `CARGO_SUPPLY_CHAIN_CACHE` may point at
+//! a directory that holds other files as well.
+use crate::api_cache::ApiCache;
+use crate::crates_cache::CratesCache;
+use crate::Error;
+use std::{fs, io};
+
+pub fn clean(dry_run: bool) -> Result<(), Error> {
+    let dir = CratesCache::cache_dir().ok_or_else(|| {
+        Error::Cache(format!(
+            "Could not determine the cache directory. Set {} to its location.",
+            CratesCache::CACHE_DIR_ENV
+        ))
+    })?;
+    let mut files = Vec::new();
+    let mut freed = 0;
+    for path in CratesCache::files_in(&dir)
+        .into_iter()
+        .chain(ApiCache::files_in(&dir))
+    {
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_file() => {
+                freed += metadata.len();
+                files.push(path);
+            }
+            Ok(_) => log::debug!("{}: not a file, leaving it alone", path.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if files.is_empty() {
+        eprintln!("There is no cache in {}, nothing to delete.", dir.display());
+        return Ok(());
+    }
+    let count = format!(
+        "{} file{}",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" }
+    );
+    if dry_run {
+        for path in &files {
+            eprintln!(" - {}", path.display());
+        }
+        eprintln!(
+            "Without --dry-run, clean would delete these {} from {}, freeing {}.",
+            count,
+            dir.display(),
+            indicatif::HumanBytes(freed)
+        );
+        return Ok(());
+    }
+    for path in &files {
+        log::debug!("Deleting {}", path.display());
+        fs::remove_file(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Failed to delete {}: {}", path.display(), e),
+            )
+        })?;
+    }
+    // Only succeeds if nothing else is left in it
+    if fs::remove_dir(&dir).is_ok() {
+        log::debug!("Deleted the empty directory {}", dir.display());
+    }
+    eprintln!(
+        "Deleted {} from {}, freeing {}.",
+        count,
+        dir.display(),
+        indicatif::HumanBytes(freed)
+    );
+    Ok(())
+}
@@ -1,65 +1,647 @@
 use crate::common::*;
-use crate::publishers::{fetch_owners_of_crates, PublisherKind};
+use crate::publishers::{fetch_owners_of_crates, PublisherData, PublisherKind};
+use crate::trust::TrustList;
+use crate::{
+    CratesArgs, CratesGroupBy, CratesSort, Error, OutputFormat, QueryCommandArgs, WarningCategory,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
 
 pub fn crates(
-    metadata_args: Vec<String>,
-    diffable: bool,
-    max_age: std::time::Duration,
-) -> Result<(), std::io::Error> {
-    let dependencies = sourced_dependencies(metadata_args);
-    complain_about_non_crates_io_crates(&dependencies);
-    let (mut owners, publisher_teams) = fetch_owners_of_crates(&dependencies, max_age)?;
+    out: &mut dyn Write,
+    args: QueryCommandArgs,
+    crates_args: CratesArgs,
+) -> Result<(), Error> {
+    let trust = args.trust_list()?;
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    let dependencies = &graph.packages;
+    if crates_args.format == OutputFormat::Csv {
+        // The other formats list these crates in a section of their own
+        complain_about_non_crates_io_crates(dependencies, args.quiet);
+    }
+    // The summary at the end of the text output tells how many crates were left out instead
+    if crates_args.format != OutputFormat::Text || args.diffable {
+        graph.complain_about_excluded_crates(args.quiet);
+    }
+    graph.complain_about_inactive_optional_crates(args.quiet);
+    let mut crate_owners = fetch_owners_of_crates(
+        dependencies,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+    )?;
+    let first_party = args.first_party_crates(&mut crate_owners, false);
 
-    for (crate_name, publishers) in publisher_teams {
+    let mut owners = crate_owners.users.clone();
+    for (crate_name, publishers) in crate_owners.teams.clone() {
         owners.entry(crate_name).or_default().extend(publishers)
     }
-
-    let mut ordered_owners: Vec<_> = owners.into_iter().collect();
-    if diffable {
-        // Sort alphabetically by crate name
-        ordered_owners.sort_unstable_by_key(|(name, _)| name.clone());
+    // Listed along with the rest, so that a failed lookup isn't mistaken for a lack of publishers
+    for crate_name in crate_owners.lookup_errors.keys() {
+        owners.entry(crate_name.clone()).or_default();
+    }
+    let lookup_errors = &crate_owners.lookup_errors;
+    let no_crates = BTreeSet::new();
+    let ownerless = if args.allows(WarningCategory::Ownerless) {
+        &no_crates
     } else {
-        // Order by the number of owners, but put crates owned by teams first
-        ordered_owners.sort_unstable_by_key(|(name, publishers)| {
-            (
-                publishers
-                    .iter()
-                    .find(|p| p.kind == PublisherKind::team)
-                    .is_none(), // contains at least one team
-                usize::MAX - publishers.len(),
-                name.clone(),
-            )
-        });
+        &crate_owners.ownerless
+    };
+
+    let mut ordered_owners: Vec<_> = owners
+        .into_iter()
+        .filter(|(crate_name, publishers)| {
+            !args.untrusted_only
+                || publishers.iter().any(|p| !trust.is_trusted(p))
+                || lookup_errors.contains_key(crate_name)
+                || ownerless.contains(crate_name)
+        })
+        .collect();
+    // Only computed when needed, since it walks the whole graph for every crate
+    let dependents = (crates_args.sort == CratesSort::Dependents).then(|| graph.dependent_counts());
+    match &dependents {
+        _ if crates_args.sort == CratesSort::Publishers => {
+            // Most publishers first, regardless of their kind
+            ordered_owners.sort_unstable_by_key(|(name, publishers)| {
+                (usize::MAX - publishers.len(), name.clone())
+            });
+        }
+        Some(dependents) => {
+            ordered_owners.sort_unstable_by_key(|(name, _)| {
+                let count = dependents.get(name).copied().unwrap_or(0);
+                (usize::MAX - count, name.clone())
+            });
+        }
+        None => ordered_owners.sort_unstable_by_key(|(name, _)| name.clone()),
     }
     for (_, publishers) in ordered_owners.iter_mut() {
         // For each crate put teams first
         publishers.sort_unstable_by_key(|p| (p.kind, p.login.clone()));
     }
 
+    let versions = crate_versions(dependencies);
+    // Maps crate names to their versions in the dependency graph that have been yanked
+    let yanked: BTreeMap<String, Vec<String>> = versions
+        .iter()
+        .map(|(name, versions)| {
+            let yanked = crate_owners.yanked_in(name, versions);
+            (name.clone(), yanked.into_iter().cloned().collect())
+        })
+        .collect();
+    match crates_args.format {
+        OutputFormat::Csv => print_csv(
+            out,
+            &ordered_owners,
+            &versions,
+            &yanked,
+            lookup_errors,
+            crates_args.deduplicate_versions,
+        )?,
+        OutputFormat::Markdown => print_markdown(
+            out,
+            &ordered_owners,
+            &versions,
+            &yanked,
+            lookup_errors,
+            ownerless,
+            &trust,
+            dependencies,
+        )?,
+        OutputFormat::Text if crates_args.group_by == CratesGroupBy::Publisher => {
+            print_text_by_publisher(
+                out,
+                &ordered_owners,
+                lookup_errors,
+                ownerless,
+                &trust,
+                args.untrusted_only,
+                args.diffable,
+            )?;
+            print_text_non_crates_io(out, &graph, args.diffable)?;
+        }
+        OutputFormat::Text => {
+            let licenses = crate_licenses(dependencies);
+            let annotations = TextAnnotations {
+                direct: &graph.direct_dependencies(),
+                lookup_errors,
+                ownerless,
+                dependents: dependents.as_ref(),
+                versions: crates_args.deduplicate_versions.then_some(&versions),
+                yanked: (!args.include_yanked).then_some(&yanked),
+                dates: crates_args
+                    .show_dates
+                    .then_some((&crate_owners.last_published, crates_args.unmaintained_after)),
+                licenses: crates_args.show_licenses.then_some(&licenses),
+                first_party: &first_party,
+            };
+            print_text(
+                out,
+                &ordered_owners,
+                &annotations,
+                &trust,
+                args.diffable,
+                args.color && !args.allows(WarningCategory::SingleOwner),
+            )?;
+            print_text_non_crates_io(out, &graph, args.diffable)?;
+        }
+        OutputFormat::Json
+        | OutputFormat::Toml
+        | OutputFormat::Sarif
+        | OutputFormat::Html
+        | OutputFormat::Ndjson
+        | OutputFormat::Dot
+        | OutputFormat::CycloneDx => {
+            unreachable!("rejected during argument validation")
+        }
+    }
+
+    if !ordered_owners.is_empty() && !args.quiet {
+        eprintln!("\nNote: there may be outstanding publisher invitations. crates.io provides no way to list them.");
+        eprintln!("See https://github.com/rust-lang/crates.io/issues/2868 for more info.");
+    }
+    crate_owners.check_lookups()?;
+    args.check_yanked(&yanked_in_dependency_graph(dependencies, &crate_owners))
+}
+
+/// What to note about each crate in text output
+struct TextAnnotations<'a> {
+    /// Unless diffable, these crates are annotated as direct dependencies
+    direct: &'a BTreeSet<String>,
+    /// These crates are listed with the error instead of their publishers
+    lookup_errors: &'a BTreeMap<String, Arc<Error>>,
+    /// These crates are flagged as published crates that have no owners left
+    ownerless: &'a BTreeSet<String>,
+    /// If provided and not diffable, crates are annotated with their number of dependents
+    dependents: Option<&'a BTreeMap<String, usize>>,
+    /// If provided, crates present at several versions are annotated with the count
+    versions: Option<&'a BTreeMap<String, Vec<String>>>,
+    /// If provided, crates are annotated with a warning about their yanked versions
+    yanked: Option<&'a BTreeMap<String, Vec<String>>>,
+    /// If provided, crates are annotated with the date of their latest release,
+    /// and flagged if it's older than the duration
+    dates: Option<(&'a BTreeMap<String, String>, Duration)>,
+    /// If provided, crates are annotated with their license expressions
+    licenses: Option<&'a BTreeMap<String, Vec<String>>>,
+    /// These crates are marked as first-party, unless they were left out
+    first_party: &'a BTreeSet<String>,
+}
+
+fn print_text(
+    out: &mut dyn Write,
+    ordered_owners: &[(String, Vec<PublisherData>)],
+    annotations: &TextAnnotations,
+    trust: &TrustList,
+    diffable: bool,
+    color: bool,
+) -> io::Result<()> {
+    let TextAnnotations {
+        direct,
+        lookup_errors,
+        ownerless,
+        dependents,
+        versions,
+        yanked,
+        dates,
+        licenses,
+        first_party,
+    } = *annotations;
     if !diffable {
-        println!(
+        writeln!(
+            out,
             "\nDependency crates with the people and teams that can publish them to crates.io:\n"
-        );
+        )?;
     }
     for (i, (crate_name, publishers)) in ordered_owners.iter().enumerate() {
         let pretty_publishers: Vec<String> = publishers
             .iter()
-            .map(|p| match p.kind {
-                PublisherKind::team => format!("team \"{}\"", p.login),
-                PublisherKind::user => p.login.to_string(),
+            .map(|p| {
+                let login = match p.kind {
+                    PublisherKind::team => format!("team \"{}\"", p.login),
+                    PublisherKind::user => p.login.to_string(),
+                };
+                trust.annotate(p, login)
             })
             .collect();
-        let publishers_list = comma_separated_list(&pretty_publishers);
+        let lookup_error = lookup_errors.get(crate_name);
+        let mut publishers_list = match lookup_error {
+            Some(error) => format!("lookup failed: {}", error),
+            None if ownerless.contains(crate_name) => {
+                "no owners left (warning: nobody can release fixes)".to_string()
+            }
+            None if publishers.is_empty() => "no publishers".to_string(),
+            None => comma_separated_list(&pretty_publishers),
+        };
+        if let Some(yanked) = yanked.and_then(|y| y.get(crate_name)) {
+            if !yanked.is_empty() {
+                let yanked = comma_separated_list(yanked);
+                publishers_list.push_str(&format!(" (warning: yanked version {})", yanked));
+            }
+        }
+        if let (Some((last_published, unmaintained_after)), None) = (dates, lookup_error) {
+            match last_published.get(crate_name) {
+                // Only the date is shown, the time of day doesn't matter here
+                Some(time) if is_older_than(time, unmaintained_after) => {
+                    publishers_list.push_str(&format!(
+                        " (last published {}, potentially unmaintained)",
+                        &time[..10]
+                    ))
+                }
+                Some(time) => {
+                    publishers_list.push_str(&format!(" (last published {})", &time[..10]))
+                }
+                None => publishers_list.push_str(" (last published: unknown)"),
+            }
+        }
+        if first_party.contains(crate_name) {
+            publishers_list.push_str(" (first-party)");
+        }
+        if let Some(licenses) = licenses {
+            match licenses.get(crate_name) {
+                Some(licenses) => {
+                    publishers_list.push_str(&format!(" (license: {})", licenses.join("; ")))
+                }
+                None => publishers_list.push_str(" (license: not specified)"),
+            }
+        }
+        let version_count = versions
+            .and_then(|v| v.get(crate_name))
+            .map_or(0, |v| v.len());
+        let versions_note = if version_count > 1 {
+            Some(format!("{} versions", version_count))
+        } else {
+            None
+        };
         if diffable {
-            println!("{}: {}", crate_name, publishers_list);
+            let crate_name = match versions_note {
+                Some(note) => format!("{} ({})", crate_name, note),
+                None => crate_name.clone(),
+            };
+            writeln!(out, "{}: {}", crate_name, publishers_list)?;
         } else {
-            println!("{}. {}: {}", i + 1, crate_name, publishers_list);
+            let publishers_note = format!(
+                "{} publisher{}",
+                publishers.len(),
+                if publishers.len() == 1 { "" } else { "s" }
+            );
+            let mut notes: Vec<String> = Vec::new();
+            if direct.contains(crate_name) {
+                notes.push("direct".to_string());
+            }
+            notes.extend(versions_note);
+            if let Some(count) = dependents.and_then(|d| d.get(crate_name)) {
+                notes.push(format!(
+                    "{} dependent{}",
+                    count,
+                    if *count == 1 { "" } else { "s" }
+                ));
+            }
+            if lookup_error.is_none() {
+                notes.push(publishers_note);
+            }
+            let notes = comma_separated_list(&notes);
+            let single_owner = publishers.len() == 1 && publishers[0].kind == PublisherKind::user;
+            let crate_name = paint(crate_name.clone(), Color::Red, color && single_owner);
+            writeln!(
+                out,
+                "{}. {} ({}): {}",
+                i + 1,
+                crate_name,
+                notes,
+                publishers_list
+            )?;
         }
     }
+    Ok(())
+}
 
-    if !ordered_owners.is_empty() {
-        eprintln!("\nNote: there may be outstanding publisher invitations. crates.io provides no way to list them.");
-        eprintln!("See https://github.com/rust-lang/crates.io/issues/2868 for more info.");
+/// Lists each publisher with the crates it can publish, in the order of `ordered_owners`.
+/// Publishers of the most crates come first. Crates that failed to be looked up are left out,
+/// since they are reported once the output is printed.
+fn print_text_by_publisher(
+    out: &mut dyn Write,
+    ordered_owners: &[(String, Vec<PublisherData>)],
+    lookup_errors: &BTreeMap<String, Arc<Error>>,
+    ownerless: &BTreeSet<String>,
+    trust: &TrustList,
+    untrusted_only: bool,
+    diffable: bool,
+) -> io::Result<()> {
+    // Keyed by kind and login, since IDs of users and teams may coincide
+    let mut by_publisher: BTreeMap<(PublisherKind, &str), (&PublisherData, Vec<String>)> =
+        BTreeMap::new();
+    let mut unowned = Vec::new();
+    for (crate_name, publishers) in ordered_owners {
+        if publishers.is_empty()
+            && !lookup_errors.contains_key(crate_name)
+            && !ownerless.contains(crate_name)
+        {
+            unowned.push(crate_name.clone());
+        }
+        for publisher in publishers {
+            if untrusted_only && trust.is_trusted(publisher) {
+                continue;
+            }
+            by_publisher
+                .entry((publisher.kind, &publisher.login))
+                .or_insert_with(|| (publisher, Vec::new()))
+                .1
+                .push(crate_name.clone());
+        }
+    }
+    let mut ranking: Vec<_> = by_publisher.into_values().collect();
+    ranking.sort_by_key(|(_, crates)| usize::MAX - crates.len());
+
+    if !diffable {
+        writeln!(out, "\nPeople and teams that can publish your dependencies to crates.io, with their crates:\n")?;
+    }
+    for (i, (publisher, crates)) in ranking.iter().enumerate() {
+        let login = match publisher.kind {
+            PublisherKind::team => format!("team \"{}\"", publisher.login),
+            PublisherKind::user => publisher.login.to_string(),
+        };
+        let login = trust.annotate(publisher, login);
+        if diffable {
+            writeln!(out, "{}: {}", login, comma_separated_list(crates))?;
+        } else {
+            writeln!(
+                out,
+                "{}. {} ({} crate{}): {}",
+                i + 1,
+                login,
+                crates.len(),
+                if crates.len() == 1 { "" } else { "s" },
+                comma_separated_list(crates)
+            )?;
+        }
+    }
+    let ownerless: Vec<String> = ownerless.iter().cloned().collect();
+    if !ownerless.is_empty() {
+        if diffable {
+            writeln!(out, "no owners left: {}", comma_separated_list(&ownerless))?;
+        } else {
+            writeln!(
+                out,
+                "\nPublished crates that have no owners left, so nobody can release fixes: {}",
+                comma_separated_list(&ownerless)
+            )?;
+        }
+    }
+    if unowned.is_empty() {
+        return Ok(());
+    }
+    if diffable {
+        writeln!(out, "no publishers: {}", comma_separated_list(&unowned))?;
+    } else {
+        writeln!(
+            out,
+            "\nCrates that nobody can publish yet: {}",
+            comma_separated_list(&unowned)
+        )?;
+    }
+    Ok(())
+}
+
+/// Lists the crates that have no crates.io publishers, followed by a count of crates per source
+fn print_text_non_crates_io(
+    out: &mut dyn Write,
+    graph: &DependencyGraph,
+    diffable: bool,
+) -> io::Result<()> {
+    let dependencies = &graph.packages;
+    let non_crates_io = non_crates_io_crates(dependencies);
+    let registries = alternative_registries(dependencies);
+    if diffable {
+        for (crate_name, source) in &non_crates_io {
+            writeln!(
+                out,
+                "{}: not from crates.io ({})",
+                crate_name,
+                describe_source(crate_name, *source, &registries)
+            )?;
+        }
+        return Ok(());
+    }
+    if !non_crates_io.is_empty() {
+        writeln!(out, "\nDependency crates that are not from crates.io, so they have no crates.io publishers:\n")?;
+        for (crate_name, source) in &non_crates_io {
+            writeln!(
+                out,
+                " - {} ({})",
+                crate_name,
+                describe_source(crate_name, *source, &registries)
+            )?;
+        }
+    }
+    let mut summary = source_summary(dependencies);
+    if !graph.excluded.is_empty() {
+        summary.push_str(&format!(", {} excluded by --exclude", graph.excluded.len()));
+    }
+    if !graph.license_mismatches.is_empty() {
+        summary.push_str(&format!(
+            ", {} left out by --license",
+            graph.license_mismatches.len()
+        ));
+    }
+    if !graph.category_mismatches.is_empty() {
+        summary.push_str(&format!(
+            ", {} left out by --category",
+            graph.category_mismatches.len()
+        ));
+    }
+    writeln!(out, "\nSummary: {}", summary)?;
+    Ok(())
+}
+
+/// For example "12 from crates.io, 1 from path, 2 from git"
+fn source_summary(dependencies: &[SourcedPackage]) -> String {
+    let counts: Vec<String> = source_counts(dependencies)
+        .into_iter()
+        .map(|(source, count)| format!("{} from {}", count, source.description()))
+        .collect();
+    comma_separated_list(&counts)
+}
+
+/// Emits a GitHub-flavored Markdown table with one row per crate, followed by a summary.
+/// Crates are always sorted by name, so that reports are easy to compare over time.
+#[allow(clippy::too_many_arguments)]
+fn print_markdown(
+    out: &mut dyn Write,
+    ordered_owners: &[(String, Vec<PublisherData>)],
+    versions: &BTreeMap<String, Vec<String>>,
+    yanked: &BTreeMap<String, Vec<String>>,
+    lookup_errors: &BTreeMap<String, Arc<Error>>,
+    ownerless: &BTreeSet<String>,
+    trust: &TrustList,
+    dependencies: &[SourcedPackage],
+) -> io::Result<()> {
+    let mut sorted: Vec<_> = ordered_owners.iter().collect();
+    sorted.sort_unstable_by_key(|(name, _)| name);
+
+    writeln!(out, "| Crate | Versions | Publishers |")?;
+    writeln!(out, "| --- | --- | --- |")?;
+    for (crate_name, publishers) in &sorted {
+        let crate_yanked = yanked.get(crate_name);
+        let crate_versions: Vec<String> = versions
+            .get(crate_name)
+            .into_iter()
+            .flatten()
+            .map(|v| match crate_yanked {
+                Some(y) if y.contains(v) => format!("{} (yanked)", v),
+                _ => v.clone(),
+            })
+            .collect();
+        let pretty_publishers: Vec<String> = publishers
+            .iter()
+            .map(|p| {
+                let login = match p.kind {
+                    PublisherKind::team => format!("team `{}`", p.login),
+                    PublisherKind::user => format!("`{}`", p.login),
+                };
+                trust.annotate(p, login)
+            })
+            .collect();
+        let publishers_cell = match lookup_errors.get(crate_name) {
+            Some(error) => format!("lookup failed: {}", error),
+            None if ownerless.contains(crate_name.as_str()) => "**none left**".to_string(),
+            None if publishers.is_empty() => "none".to_string(),
+            None => comma_separated_list(&pretty_publishers),
+        };
+        writeln!(
+            out,
+            "| {} | {} | {} |",
+            escape_markdown_cell(crate_name),
+            escape_markdown_cell(&comma_separated_list(&crate_versions)),
+            escape_markdown_cell(&publishers_cell)
+        )?;
+    }
+
+    let distinct_publishers: BTreeSet<(PublisherKind, u64)> = sorted
+        .iter()
+        .flat_map(|(_, publishers)| publishers.iter().map(|p| (p.kind, p.id)))
+        .collect();
+    let single_owner = sorted
+        .iter()
+        .filter(|(_, publishers)| {
+            publishers.len() == 1 && publishers[0].kind == PublisherKind::user
+        })
+        .count();
+    writeln!(out)?;
+    writeln!(out, "**Summary**")?;
+    writeln!(out)?;
+    writeln!(out, "- Crates: {}", sorted.len())?;
+    writeln!(out, "- Distinct publishers: {}", distinct_publishers.len())?;
+    writeln!(
+        out,
+        "- Crates with a single individual publisher: {}",
+        single_owner
+    )?;
+    if !ownerless.is_empty() {
+        writeln!(
+            out,
+            "- Published crates with no owners left: {}",
+            ownerless.len()
+        )?;
+    }
+    if !lookup_errors.is_empty() {
+        writeln!(
+            out,
+            "- Crates whose publishers could not be looked up: {}",
+            lookup_errors.len()
+        )?;
+    }
+    writeln!(out, "- Crates by source: {}", source_summary(dependencies))?;
+
+    let non_crates_io = non_crates_io_crates(dependencies);
+    let registries = alternative_registries(dependencies);
+    if !non_crates_io.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "**Not from crates.io**")?;
+        writeln!(out)?;
+        writeln!(out, "| Crate | Source |")?;
+        writeln!(out, "| --- | --- |")?;
+        for (crate_name, source) in &non_crates_io {
+            writeln!(
+                out,
+                "| {} | {} |",
+                escape_markdown_cell(crate_name),
+                escape_markdown_cell(&describe_source(crate_name, *source, &registries))
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Pipes would end the table cell early, and newlines would end the table
+fn escape_markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Emits one record per crate version, quoted and terminated as per RFC 4180.
+/// If `deduplicate_versions` is set, emits one record per crate listing all of its versions instead,
+/// and the `yanked` column is true if any of them is yanked.
+fn print_csv(
+    out: &mut dyn Write,
+    ordered_owners: &[(String, Vec<PublisherData>)],
+    versions: &BTreeMap<String, Vec<String>>,
+    yanked: &BTreeMap<String, Vec<String>>,
+    lookup_errors: &BTreeMap<String, Arc<Error>>,
+    deduplicate_versions: bool,
+) -> Result<(), Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(out);
+    writer.write_record([
+        "crate",
+        "version",
+        "publishers",
+        "publisher_kinds",
+        "yanked",
+        "lookup_error",
+    ])?;
+    for (crate_name, publishers) in ordered_owners {
+        let logins: Vec<String> = publishers.iter().map(|p| p.login.clone()).collect();
+        let kinds: Vec<String> = publishers
+            .iter()
+            .map(|p| match p.kind {
+                PublisherKind::team => "team".to_string(),
+                PublisherKind::user => "user".to_string(),
+            })
+            .collect();
+        let logins = comma_separated_list(&logins);
+        let kinds = comma_separated_list(&kinds);
+        let crate_versions = versions.get(crate_name).cloned().unwrap_or_default();
+        let crate_yanked = yanked.get(crate_name).cloned().unwrap_or_default();
+        let lookup_error = lookup_errors
+            .get(crate_name)
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+        if deduplicate_versions {
+            let version_list = comma_separated_list(&crate_versions);
+            let is_yanked = (!crate_yanked.is_empty()).to_string();
+            writer.write_record([
+                crate_name,
+                &version_list,
+                &logins,
+                &kinds,
+                &is_yanked,
+                &lookup_error,
+            ])?;
+        } else {
+            for version in &crate_versions {
+                let is_yanked = crate_yanked.contains(version).to_string();
+                writer.write_record([
+                    crate_name,
+                    version,
+                    &logins,
+                    &kinds,
+                    &is_yanked,
+                    &lookup_error,
+                ])?;
+            }
+        }
     }
+    writer.flush()?;
     Ok(())
 }
@@ -0,0 +1,157 @@
+//! `diff` subcommand compares two outputs of the `json` subcommand,
+//! showing how a dependency update changed the set of crates and people you trust.
+use crate::common::comma_separated_list;
+use crate::publishers::{PublisherData, PublisherKind};
+use crate::subcommands::json::{StructuredOutput, SCHEMA_VERSION};
+use crate::Error;
+use std::collections::{BTreeMap, BTreeSet};
+use std::{fs, io, io::Write, path::Path};
+
+pub fn diff(out: &mut dyn Write, old_path: &Path, new_path: &Path) -> Result<(), Error> {
+    let old = read_snapshot(old_path)?;
+    let new = read_snapshot(new_path)?;
+
+    let old_crates: BTreeSet<&String> = old.crates_io_crates.keys().collect();
+    let new_crates: BTreeSet<&String> = new.crates_io_crates.keys().collect();
+    let added_crates: Vec<&String> = new_crates.difference(&old_crates).copied().collect();
+    let removed_crates: Vec<&String> = old_crates.difference(&new_crates).copied().collect();
+
+    let old_publishers = publishers_to_crates(&old);
+    let new_publishers = publishers_to_crates(&new);
+    let added_publishers = publishers_missing_from(&new_publishers, &old_publishers);
+    let removed_publishers = publishers_missing_from(&old_publishers, &new_publishers);
+
+    if added_crates.is_empty()
+        && removed_crates.is_empty()
+        && added_publishers.is_empty()
+        && removed_publishers.is_empty()
+    {
+        writeln!(
+            out,
+            "No crates.io crates or publishers were added or removed."
+        )?;
+        return Ok(());
+    }
+
+    if !added_crates.is_empty() {
+        writeln!(out, "Added crates:")?;
+        for crate_name in added_crates {
+            let publishers: Vec<String> = new.crates_io_crates[crate_name]
+                .iter()
+                .map(pretty_publisher)
+                .collect();
+            writeln!(
+                out,
+                " + {}: {}",
+                crate_name,
+                comma_separated_list(&publishers)
+            )?;
+        }
+    }
+    if !removed_crates.is_empty() {
+        writeln!(out, "Removed crates:")?;
+        for crate_name in removed_crates {
+            writeln!(out, " - {}", crate_name)?;
+        }
+    }
+    if !added_publishers.is_empty() {
+        writeln!(out, "Added publishers:")?;
+        for (publisher, crates) in added_publishers {
+            writeln!(
+                out,
+                " + {} via crates: {}",
+                pretty_publisher(publisher),
+                comma_separated_list(crates)
+            )?;
+        }
+    }
+    if !removed_publishers.is_empty() {
+        writeln!(out, "Removed publishers:")?;
+        for (publisher, crates) in removed_publishers {
+            writeln!(
+                out,
+                " - {} via crates: {}",
+                pretty_publisher(publisher),
+                comma_separated_list(crates)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_snapshot(path: &Path) -> Result<StructuredOutput, Error> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to read {}: {}", path.display(), e),
+        )
+    })?;
+    let invalid = |msg: String| {
+        Error::Parse(format!(
+            "Invalid output of the json subcommand in {}: {}",
+            path.display(),
+            msg
+        ))
+    };
+    // Output of `json --format toml` is recognized by the file extension
+    let value: serde_json::Value = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?
+    } else {
+        serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?
+    };
+    match value.get("schema_version").and_then(|v| v.as_u64()) {
+        // Only fields present since version 1 are compared
+        Some(version) if (1..=u64::from(SCHEMA_VERSION)).contains(&version) => (),
+        Some(version) => {
+            return Err(invalid(format!(
+                "schema version {} is not supported, expected at most {}",
+                version, SCHEMA_VERSION
+            )))
+        }
+        None => return Err(invalid("no schema version specified".to_string())),
+    }
+    serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
+}
+
+/// Publishers are identified by kind and ID, since logins can be renamed
+/// and users and teams don't share the ID space.
+pub(crate) type PublisherKey = (PublisherKind, u64);
+
+/// Maps publishers to the crates they can publish
+pub(crate) fn publishers_to_crates(
+    output: &StructuredOutput,
+) -> BTreeMap<PublisherKey, (PublisherData, Vec<String>)> {
+    let mut result: BTreeMap<_, (PublisherData, Vec<String>)> = BTreeMap::new();
+    for (crate_name, publishers) in &output.crates_io_crates {
+        for publisher in publishers {
+            let key = (publisher.kind, publisher.id);
+            result
+                .entry(key)
+                .or_insert_with(|| (publisher.clone(), Vec::new()))
+                .1
+                .push(crate_name.clone());
+        }
+    }
+    result
+}
+
+/// Returns publishers present in `left` but not in `right`, teams first, then sorted by login
+pub(crate) fn publishers_missing_from<'a>(
+    left: &'a BTreeMap<PublisherKey, (PublisherData, Vec<String>)>,
+    right: &BTreeMap<PublisherKey, (PublisherData, Vec<String>)>,
+) -> Vec<&'a (PublisherData, Vec<String>)> {
+    let mut result: Vec<_> = left
+        .iter()
+        .filter(|(key, _)| !right.contains_key(key))
+        .map(|(_, value)| value)
+        .collect();
+    result.sort_unstable_by_key(|(publisher, _)| (publisher.kind, publisher.login.clone()));
+    result
+}
+
+pub(crate) fn pretty_publisher(publisher: &PublisherData) -> String {
+    match publisher.kind {
+        PublisherKind::team => format!("team \"{}\"", publisher.login),
+        PublisherKind::user => format!("user \"{}\"", publisher.login),
+    }
+}
@@ -1,23 +1,33 @@
 //! Displays help information to the user when requested
 
-use crate::{err_exit, subcommands::json::StructuredOutput, CLI_HELP};
+use crate::{subcommands::json::StructuredOutput, Error, CLI_HELP};
 use schemars::schema_for;
 use std::process;
 
 /// Provides help infomation which proceeds to exit
-pub fn help(command: Option<&str>) {
+pub fn help(command: Option<&str>) -> Result<(), Error> {
     match command {
         None => println!("{}", CLI_HELP),
         Some("publishers") => println!("{}", PUBLISHERS_HELP),
         Some("crates") => println!("{}", CRATES_HELP),
+        Some("owners") => println!("{}", OWNERS_HELP),
+        Some("risks") => println!("{}", RISKS_HELP),
+        Some("stats") => println!("{}", STATS_HELP),
+        Some("verify") => println!("{}", VERIFY_HELP),
+        Some("why") => println!("{}", WHY_HELP),
+        Some("diff") => println!("{}", DIFF_HELP),
         Some("update") => println!("{}", UPDATE_HELP),
+        Some("clean") => println!("{}", CLEAN_HELP),
         Some("json") => {
             println!("{}", JSON_HELP);
             let schema = schema_for!(StructuredOutput);
             println!("\n{}", serde_json::to_string_pretty(&schema).unwrap());
         }
         Some(command) => {
-            err_exit(format!("Unknown subcommand: {}\n{}", command, CLI_HELP).as_str())
+            return Err(Error::InvalidInput(format!(
+                "Unknown subcommand: {}\n{}",
+                command, CLI_HELP
+            )))
         }
     }
 
@@ -28,6 +38,7 @@ const CRATES_HELP: &str = "Lists all crates in dependency graph and crates.io pu
 
 If a local cache created by 'update' subcommand is present and up to date,
 it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
 
 It's not guaranteed that the local cache will be used if '--cache-max-age' is
 set to less than 48 hours, even if you've run 'update' subcommand just now.
@@ -40,17 +51,181 @@ OPTIONS:
   --cache-max-age  The cache will be considered valid while younger than specified.
                    The format is a human readable duration such as `1w` or `1d 6h`.
                    If not specified, the cache is considered valid for 48 hours.
-  -d, --diffable  Make output more friendly towards tools such as `diff`
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --format         Output format, either `text` (the default), `csv` or `markdown`.
+                   CSV output contains one row per crate version with the columns
+                   `crate`, `version`, `publishers`, `publisher_kinds`, `yanked` and
+                   `lookup_error`, which is empty unless the crate could not be looked up.
+                   Markdown output is a table of crates sorted by name followed by
+                   a summary, meant to be pasted into a GitHub issue.
+  --deduplicate-versions
+                   Collapse all versions of a crate into a single entry.
+                   Text output notes the number of versions, e.g. `syn (2 versions)`.
+  --sort           Order crates by `name` (the default), by `dependents`, listing the ones
+                   most depended upon within the dependency graph first, or by `publishers`,
+                   listing the ones with the most publishers first. Affects text and CSV output.
+  --sort-by-publishers
+                   Same as `--sort publishers`
+  --group-by       Either `crate` (the default), listing the publishers of each crate,
+                   or `publisher`, listing everything each user and team can publish,
+                   those of the most crates first. Only affects text output.
+  --show-dates     Show when the newest version of each crate was published.
+                   Only affects text output.
+  --show-licenses  Show the license expression of each crate, as declared in its manifest.
+                   Only affects text output. The license is also part of `json` output.
+  --unmaintained-after
+                   With `--show-dates`, flag crates that have not published a new version
+                   for this long as potentially unmaintained. Accepts the same format
+                   as `--cache-max-age`, 2 years by default.
+  --trust-file     File listing publishers that have already been reviewed, which are
+                   marked as trusted in the output. Defaults to `supply-chain-trust.toml`
+                   in the current directory. The file has the following format:
+                     users = [\"github-login\"]
+                     teams = [\"github:org:team\"]
+  --untrusted-only Only show crates with at least one publisher not listed in the trust file
+  --first-party-orgs
+                   Mark the crates that a team of the given GitHub organization can publish
+                   as first-party, your own code. Also accepts a single team, such as
+                   `github:my-org:maintainers`. May be specified multiple times.
+                   First-party crates are left out of the risks.
+  --hide-first-party
+                   Leave first-party crates out of the output entirely
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+  --include-yanked Do not warn about yanked versions in the dependency graph
 
 Any arguments after the `--` will be passed to `cargo metadata`, for example:
   cargo supply-chain crates -- --filter-platform=x86_64-unknown-linux-gnu
-See `cargo metadata --help` for a list of flags it supports.";
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.";
 
 const PUBLISHERS_HELP: &str =
     "Lists all crates.io publishers in the dependency graph and owned crates for each
 
+When the local cache is used, each publisher is listed along with the number of crates
+they can publish on all of crates.io. Someone with hundreds of crates is likely a
+well-known maintainer, while an account with only a few is more narrowly scoped.
+
 If a local cache created by 'update' subcommand is present and up to date,
 it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
 
 It's not guaranteed that the local cache will be used if '--cache-max-age' is
 set to less than 48 hours, even if you've run 'update' subcommand just now.
@@ -63,19 +238,957 @@ OPTIONS:
   --cache-max-age  The cache will be considered valid while younger than specified.
                    The format is a human readable duration such as `1w` or `1d 6h`.
                    If not specified, the cache is considered valid for 48 hours.
-  -d, --diffable  Make output more friendly towards tools such as `diff`
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --max-publishers Exit with code 2 if there are more distinct users and teams
+                   than specified. The list of publishers is printed regardless.
+                   With --untrusted-only, trusted publishers are not counted.
+  --top            Only list this many users and teams that can publish the most crates,
+                   ranked together, along with the share of crates each of them can publish
+  --publisher      Only list the crates that the user or team with the given login can
+                   publish, such as `dtolnay` or `github:rust-lang:libs`, along with
+                   whether each is a direct dependency and how many crates depend on it
+  --logins-only    Only print the logins of the users and teams, one per line, sorted,
+                   such as `dtolnay` or `github:rust-lang:libs`, for use in scripts
+  --only-teams     Only list teams, such as the GitHub organizations whose code you build
+  --only-users     Only list individual users
+  --per-target     Resolve the dependency graph for each `--target` separately, and list
+                   the publishers of crates for all of them, then those only publishing
+                   crates for some of them, such as only for `wasm32-unknown-unknown`
+  --expand-teams   List the members of GitHub teams as individuals, along with the crates
+                   of their teams, so that the people who can publish are counted.
+                   GitHub only shows them to members of the organization, so this makes
+                   authenticated requests to GitHub with the token in GITHUB_TOKEN.
+                   Teams whose members can't be listed are kept as they are.
+  --trust-file     File listing publishers that have already been reviewed, which are
+                   marked as trusted in the output. Defaults to `supply-chain-trust.toml`
+                   in the current directory. The file has the following format:
+                     users = [\"github-login\"]
+                     teams = [\"github:org:team\"]
+  --untrusted-only Only show publishers that are not listed in the trust file
+  --first-party-orgs
+                   Treat the crates that a team of the given GitHub organization can publish
+                   as first-party, your own code. Also accepts a single team, such as
+                   `github:my-org:maintainers`. May be specified multiple times.
+                   First-party crates are left out of the risks.
+  --hide-first-party
+                   Leave first-party crates out of the output entirely
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+  --include-yanked Do not warn about yanked versions in the dependency graph
 
 
 Any arguments after the `--` will be passed to `cargo metadata`, for example:
   cargo supply-chain crates -- --filter-platform=x86_64-unknown-linux-gnu
-See `cargo metadata --help` for a list of flags it supports.";
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.";
+
+const OWNERS_HELP: &str = "Lists the teams that can publish crates in the dependency graph
+
+For each team, shows how many and which of your dependencies it can publish.
+A compromised team token affects all of these crates at once.
+Crates that are not owned by any team are listed afterwards, grouped by individual owner.
+
+crates.io does not expose the membership of teams, so member counts are not shown.
+
+If a local cache created by 'update' subcommand is present and up to date,
+it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
+
+USAGE:
+  cargo supply-chain owners [OPTIONS...] [-- CARGO_METADATA_OPTIONS...]
+
+OPTIONS:
+  --cache-max-age  The cache will be considered valid while younger than specified.
+                   The format is a human readable duration such as `1w` or `1d 6h`.
+                   If not specified, the cache is considered valid for 48 hours.
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --trust-file     File listing publishers that have already been reviewed, which are
+                   marked as trusted in the output. Defaults to `supply-chain-trust.toml`
+                   in the current directory. The file has the following format:
+                     users = [\"github-login\"]
+                     teams = [\"github:org:team\"]
+  --untrusted-only Only show publishers that are not listed in the trust file
+  --first-party-orgs
+                   Treat the crates that a team of the given GitHub organization can publish
+                   as first-party, your own code. Also accepts a single team, such as
+                   `github:my-org:maintainers`. May be specified multiple times.
+                   First-party crates are left out of the risks.
+  --hide-first-party
+                   Leave first-party crates out of the output entirely
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+
+Any arguments after the `--` will be passed to `cargo metadata`, for example:
+  cargo supply-chain owners -- --filter-platform=x86_64-unknown-linux-gnu
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.";
+
+const RISKS_HELP: &str = "Lists crates that a single individual can publish, and no teams
+
+A single compromised account is enough to publish a malicious update to these crates.
+They are sorted by the number of other crates in the dependency graph
+that depend on them, directly or transitively, so the biggest risks come first.
+
+Crates that gained or lost publishers recently are listed as well, since a takeover
+of a crate often starts with a new publisher. Publishers added after the first release
+are known from the daily dump. Crates looked up through the crates.io API are compared
+with the previous lookup cached on disk instead, which also reveals removed publishers.
+
+If a local cache created by 'update' subcommand is present and up to date,
+it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
+
+USAGE:
+  cargo supply-chain risks [OPTIONS...] [-- CARGO_METADATA_OPTIONS...]
+
+OPTIONS:
+  --cache-max-age  The cache will be considered valid while younger than specified.
+                   The format is a human readable duration such as `1w` or `1d 6h`.
+                   If not specified, the cache is considered valid for 48 hours.
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --format         Output format, either `text` (the default) or `sarif`.
+                   SARIF output also reports yanked versions in the dependency graph,
+                   and points each finding at the line of `Cargo.toml` declaring the
+                   dependency, or at `Cargo.lock` for indirect dependencies.
+                   It can be uploaded to GitHub code scanning.
+  --owner-changes-within
+                   Report crates whose publishers changed within this long, 30 days
+                   by default. Accepts the same format as `--cache-max-age`.
+  --since          Only report the crates whose publishers changed after the given date,
+                   such as `2024-03-01`, leaving out single-owner crates and yanked versions.
+                   Removals are only known for crates looked up through the API more than
+                   once, since the daily dump only records when current publishers were added.
+  --trust-file     File listing publishers that have already been reviewed, which are
+                   marked as trusted in the output. Defaults to `supply-chain-trust.toml`
+                   in the current directory. The file has the following format:
+                     users = [\"github-login\"]
+                     teams = [\"github:org:team\"]
+  --untrusted-only Only show publishers that are not listed in the trust file
+  --first-party-orgs
+                   Leave out the crates that a team of the given GitHub organization can
+                   publish, since they are first-party, your own code. Also accepts a single
+                   team, such as `github:my-org:maintainers`. May be specified multiple times.
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+
+Any arguments after the `--` will be passed to `cargo metadata`, for example:
+  cargo supply-chain owners -- --filter-platform=x86_64-unknown-linux-gnu
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.";
+
+const STATS_HELP: &str = "Summarizes the dependency graph and its publishers in a few numbers
+
+Prints the total number of crates, how many of them come from crates.io,
+the number of distinct publishers and teams, the number of crates that
+a single individual can publish, and the publisher of the most crates.
+Meant as a quick health check, for example in a daily CI summary.
+
+If a local cache created by 'update' subcommand is present and up to date,
+it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
+
+USAGE:
+  cargo supply-chain stats [OPTIONS...] [-- CARGO_METADATA_OPTIONS...]
+
+OPTIONS:
+  --cache-max-age  The cache will be considered valid while younger than specified.
+                   The format is a human readable duration such as `1w` or `1d 6h`.
+                   If not specified, the cache is considered valid for 48 hours.
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+
+Any arguments after the `--` will be passed to `cargo metadata`, for example:
+  cargo supply-chain stats -- --filter-platform=x86_64-unknown-linux-gnu
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.";
+
+const VERIFY_HELP: &str =
+    "Checks the publishers in the dependency graph against an expectations file
+
+The expectations file is an output of the 'json' subcommand that is committed to
+the repository once its publishers have been reviewed. If the dependency graph has
+any publishers that are not listed in it, they are printed along with the crates
+they can publish, and the command exits with code 2. Publishers that are listed but
+no longer present are printed too, but do not fail the check.
+
+After reviewing new publishers, run with '--update-expectations' to accept them.
+Files with the `.toml` extension are read and written as the output of `json --format toml`.
+
+If a local cache created by 'update' subcommand is present and up to date,
+it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
+
+USAGE:
+  cargo supply-chain verify [OPTIONS...] EXPECTATIONS_FILE [-- CARGO_METADATA_OPTIONS...]
+
+OPTIONS:
+  --update-expectations
+                   Rewrite the expectations file with the current publishers instead of
+                   checking them. Prints what changed, and creates the file if it's missing.
+  --cache-max-age  The cache will be considered valid while younger than specified.
+                   The format is a human readable duration such as `1w` or `1d 6h`.
+                   If not specified, the cache is considered valid for 48 hours.
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+
+Any arguments after the `--` will be passed to `cargo metadata`, for example:
+  cargo supply-chain verify expected.json -- --filter-platform=x86_64-unknown-linux-gnu
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.";
+
+const WHY_HELP: &str = "Shows why a crate is in the dependency graph, and who can publish it
+
+Prints every version of the crate in the dependency graph, and below each of them
+the crates that depend on it, down to the workspace members, as `cargo tree -i` does.
+Crates that were already printed along with their dependents are marked with (*).
+Then lists the crates.io users and teams that can publish the crate, to tell both
+who owns a suspicious crate and which dependency is responsible for pulling it in.
+
+If a local cache created by 'update' subcommand is present and up to date,
+it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
+
+USAGE:
+  cargo supply-chain why [OPTIONS...] CRATE [-- CARGO_METADATA_OPTIONS...]
+
+OPTIONS:
+  --cache-max-age  The cache will be considered valid while younger than specified.
+                   The format is a human readable duration such as `1w` or `1d 6h`.
+                   If not specified, the cache is considered valid for 48 hours.
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Make output more friendly towards tools such as `diff`
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, without headings, and
+                   nothing but warnings and errors to stderr. Text output is laid out
+                   as with `--diffable`.
+  --color          Highlight crates that a single individual can publish in red, and
+                   trusted publishers in green: `auto` (the default) colors output to
+                   a terminal unless NO_COLOR is set, or `always` or `never`.
+                   Output is never colored with `--diffable` or `--quiet`.
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
+
+Any arguments after the `--` will be passed to `cargo metadata`, for example:
+  cargo supply-chain why libc -- --filter-platform=x86_64-unknown-linux-gnu
+See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.";
 
 const JSON_HELP: &str = "Detailed info on publishers of all crates in the dependency graph, in JSON
 
 The JSON schema is provided below, but the output is designed to be self-explanatory.
 
+The top-level 'schema_version' field is incremented whenever the shape of the output
+changes. Programs parsing the output should reject versions they don't understand.
+
+Each publisher has a 'kind' field that is either \"user\" or \"team\", so that it can be
+deserialized into an enum. These two values are stable across schema versions.
+
+Each publisher has a 'contact' field with links to their public profiles on crates.io
+and GitHub, derived from their login. crates.io does not expose email addresses.
+
+Publishers are not marked as crates.io administrators or other special accounts,
+because crates.io doesn't expose that for anyone but the user making the request.
+Administrators can act on any crate without being listed as its publishers, so a
+publisher listed here was added as an owner of the crate like any other.
+
 If a local cache created by 'update' subcommand is present and up to date,
 it will be used. Otherwise live data will be fetched from the crates.io API.
+Live data is cached as well, and reused while younger than '--cache-max-age'.
 
 It's not guaranteed that the local cache will be used if '--cache-max-age' is
 set to less than 48 hours, even if you've run 'update' subcommand just now.
@@ -88,11 +1201,151 @@ OPTIONS:
   --cache-max-age  The cache will be considered valid while younger than specified.
                    The format is a human readable duration such as `1w` or `1d 6h`.
                    If not specified, the cache is considered valid for 48 hours.
-  -d, --diffable   Pretty-print the resulting JSON, making it easy to diff
+                   `0` always refreshes it, fetching live data for every crate
+                   as with `--no-cache`.
+  -d, --diffable   Pretty-print the resulting JSON with the keys of every object sorted
+                   alphabetically, making it easy to diff
+  --pretty         Pretty-print the JSON, which is the default with --diffable
+                   or when printing to a terminal
+  --compact        Print the JSON on a single line, which is the default otherwise.
+                   Keys are in the same order either way, crates and publishers sorted,
+                   so that saved outputs diff cleanly across runs.
+  --show-categories
+                   Include the crates.io categories and the keywords of each crate, as
+                   declared in its manifest, in JSON and TOML output. They are left out
+                   by default to keep the output small.
+  -v, --verbose    Log cache lookups, requests to crates.io and such to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  -q, --quiet      Only print the requested data to stdout, and nothing but warnings
+                   and errors to stderr
+  --format         Output format: `json` (the default), `toml`, `html`, `ndjson`, `dot`
+                   or `cyclonedx`.
+                   TOML output has the same structure as JSON, and is always pretty-printed.
+                   HTML output is a standalone report to open in a browser, with summary
+                   figures, a sortable table of crates, and the crates of each publisher.
+                   NDJSON output is printed while crates are looked up, one line per
+                   crates.io crate with its name, versions, yanked_versions, last_published,
+                   publishers, ownerless and licenses. Lines are in no particular order unless
+                   `--diffable` is given, which waits for all of them and sorts them by name.
+                   Crates that could not be looked up and crates from elsewhere are left out.
+                   DOT output is a Graphviz graph linking crates to their publishers,
+                   to render with `dot -Tsvg` to see which publishers are hubs.
+                   CycloneDX output is a 1.6 SBOM in JSON with a component for each
+                   version of a crates.io crate, listing its publishers as `authors`,
+                   and whether each is a user or a team in `properties`.
+  --target         Only include dependencies built for the given target triple.
+                   May be specified multiple times to include dependencies of any of them.
+  --include-dev-dependencies
+                   Include crates that are only pulled in by dev-dependencies,
+                   which are left out by default
+  --exclude-dev-dependencies
+                   Deprecated, since dev-dependencies are left out by default. Until it is
+                   removed, it still overrides `include-dev-dependencies` in the config file.
+  --exclude-build-dependencies
+                   Ignore crates that are only pulled in by build-dependencies.
+                   Proc-macros are normal dependencies, so they are still included.
+  --include-build-dependencies
+                   Include build-dependencies, as is the default, even if the config file
+                   sets `exclude-build-dependencies`
+  --workspace-only Only include the crates of the current workspace,
+                   looking up publishers of those that are published to crates.io
+  --filter-crate   Only include the crate with the given name and its dependencies.
+                   May be specified multiple times to include the union of several subtrees.
+  --direct-only    Only include the crates that the workspace depends on directly,
+                   leaving out the ones that are only pulled in by other dependencies
+  --build-time-only
+                   Only include the crates that run code on your machine during the build:
+                   proc-macros, build-dependencies, and the crates they depend on
+  --depth          Only include the crates at most this many dependency edges away
+                   from the workspace. `--depth 1` is the same as `--direct-only`.
+  --exclude        Leave out the crates whose names match the given glob, such as
+                   `windows-*`, or regular expression between slashes, such as
+                   `/^windows-(sys|targets)$/`. May be specified multiple times.
+  --license        Only include the crates whose license expression matches the given glob
+                   or regular expression, such as `*GPL*`, or doesn't if it starts with `!`,
+                   such as `!MIT OR Apache-2.0`. Crates that only point at a license file
+                   match as an empty license. May be specified multiple times, and crates
+                   must satisfy all of them. Crates from the local filesystem are kept.
+  --category       Only include the crates in the given crates.io category, such as
+                   `cryptography` or `network-programming`, or in one of its subcategories,
+                   to focus a review on the security-sensitive parts of the graph.
+                   May be specified multiple times to include crates in any of them.
+                   Crates from the local filesystem are kept. The categories are read from
+                   the manifest of each version in the graph, not from crates.io, so they
+                   can differ from those shown there for the newest version.
+  --min-dependents Only include the crates.io crates that at least this many other crates
+                   in the dependency graph depend on, directly or transitively, to focus
+                   a review on the most relied upon ones
+  --features       Space or comma separated list of features to activate
+  --all-features   Activate all available features
+  --no-default-features
+                   Do not activate the `default` feature
+  --default-features
+                   Only activate the default features, even if the config file selects others
+  --include-inactive-optional
+                   Also include the crates only pulled in by optional dependencies that
+                   none of the selected features activate, and list them on stderr.
+                   By default, only what the selected features actually build is included.
+  --requests-per-second
+                   Limit on the rate of requests to the crates.io API, 1 by default.
+                   Only raise it if crates.io has granted you a higher limit, which also
+                   requires setting the token you were given in CARGO_SUPPLY_CHAIN_TOKEN.
+  -j, --jobs       Number of crates to look up on crates.io concurrently, 4 by default.
+                   Requests are still subject to the rate limit.
+  --throttle       Set the rate limit, concurrency and retries of failed requests together:
+                   'gentle' (0.5 per second, 1 job, 5 retries) suits shared CI,
+                   'normal' (1 per second, 4 jobs, 2 retries) is the default, and
+                   'aggressive' (20 per second, 16 jobs, 1 retry) suits a mirror that allows it.
+                   --requests-per-second and --jobs override the profile.
+  --registry-url   Use a mirror of crates.io at the given URL for API requests,
+                   and for downloading the daily dump. Defaults to https://crates.io
+  --request-timeout
+                   How long to wait for crates.io to respond before retrying a request,
+                   30s by default. Downloads of the daily dump fail if stalled this long.
+  --manifest-path  Path to the `Cargo.toml` of the workspace to analyze. May be specified
+                   multiple times to analyze several workspaces together, counting each
+                   crate they have in common once.
+  --metadata-file  Read the dependency graph from a file containing the output of
+                   `cargo metadata --format-version 1` instead of running it
+  --lockfile       Read the dependency graph from a `Cargo.lock` instead of running
+                   `cargo metadata`, so that the source tree is not needed. Includes every
+                   optional and platform-specific dependency, and treats all dependencies
+                   as normal ones, since the lockfile doesn't record how they are used.
+  --crate          Analyze a crate published on crates.io, given as NAME or NAME@VERSION,
+                   instead of the current workspace, to vet it before depending on it.
+                   The newest version is analyzed if none is given. `--features` and
+                   `--no-default-features` select the features of that crate.
+  --locked         Fail instead of updating `Cargo.lock` when running `cargo metadata`,
+                   so that the versions analyzed are exactly the committed ones
+  --frozen         Passed to cargo as `--frozen`, so that it neither updates `Cargo.lock` nor
+                   accesses the network. Publishers are still fetched from crates.io
+                   unless `--offline` is given too.
+  --offline        Never access the network. Only the local cache created by 'update'
+                   is used, regardless of its age, and crates missing from it are an error.
+  --allow-stale    Don't warn when the cached dump was generated longer ago than
+                   `--cache-max-age`, such as when working offline on purpose
+  --no-cache       Ignore the local cache, including a fresh one, and fetch live data
+                   for every crate. This takes roughly 3 seconds per crate at the default
+                   rate limit. The fetched data is still cached for later runs.
+  --keep-going     Look up the other crates when one fails to be looked up, print the
+                   output for them, then list every crate that failed and exit with code 4.
+                   Otherwise the first failure ends the run.
+  --fail-on-yanked Exit with code 2 after printing the output if the dependency graph
+                   contains yanked versions, listing each of them
+  --allow          Suppress a category of warnings once it has been accepted, so that it
+                   doesn't fail `--fail-on-yanked` either: `yanked`, `single-owner`,
+                   `ownerless`, `ownership-changed` or `stale`, the same as `--allow-stale`.
+                   May be specified multiple times.
+  -o, --output     Write the output to this file instead of stdout, creating or truncating it.
+                   Warnings and errors are still printed to stderr.
+  --timing         Print to stderr how long running `cargo metadata`, reading the cache,
+                   fetching from crates.io and rendering the output took, once done
 
 Any arguments after the `--` will be passed to `cargo metadata`, for example:
   cargo supply-chain crates -- --filter-platform=x86_64-unknown-linux-gnu
 See `cargo metadata --help` for a list of flags it supports.
+An argument of the form `@path` is replaced with the arguments in that file, one per line.
+Blank lines and lines starting with `#` are skipped.
 
 Note that detailed information on the origin of crates outside of crates.io is not
 provided. You can obtain this info from 'cargo metadata' that ships with Cargo,
@@ -100,19 +1353,70 @@ or use 'cargo deny' to define a custom policy regarding crate sources.
 
 The JSON schema definition is as follows:";
 
+const DIFF_HELP: &str =
+    "Compares two outputs of the 'json' subcommand saved before and after a dependency update
+
+Lists crates.io crates that were added or removed, as well as users and teams
+that gained or lost the ability to publish any crate in the dependency graph.
+Publishers are matched by their crates.io ID, so a renamed account is not reported.
+The output is plain text intended to be pasted into a code review.
+
+No network access is performed, and the local cache is not used.
+
+USAGE:
+  cargo supply-chain json > old.json
+  # update the dependencies
+  cargo supply-chain json > new.json
+  cargo supply-chain diff old.json new.json
+
+Files with the `.toml` extension are read as the output of `json --format toml`.\n";
+
 const UPDATE_HELP: &str = "Download the latest daily dump from crates.io to speed up other commands
 
 If the local cache is already younger than specified in '--cache-max-age' option,
-a newer version will not be downloaded.
+a newer version will not be downloaded. Otherwise crates.io is asked whether the dump
+has changed since it was downloaded, and it is only downloaded again if it has.
+If it hasn't, the cache is considered fresh again from that point on.
 
 Note that this downloads the entire crates.io database, which is hundreds of Mb of data!
 If you are on a metered connection, you should not be running the 'update' subcommand.
 Instead, rely on requests to the live API - they are slower, but use much less data.
 
+If the download is interrupted, running 'update' again resumes it where it left off.
+The downloaded archive is verified before it replaces the existing cache,
+so a failed or corrupt download leaves the previous data intact.
+
 USAGE:
   cargo supply-chain update [OPTIONS...]
 
 OPTIONS:
   --cache-max-age  The cache will be considered valid while younger than specified.
                    The format is a human readable duration such as `1w` or `1d 6h`.
-                   If not specified, the cache is considered valid for 48 hours.\n";
+                   If not specified, the cache is considered valid for 48 hours.
+                   `0` always downloads the dump again, even if it hasn't changed.
+  --registry-url   Download the daily dump from `<URL>/db-dump.tar.gz` on a mirror
+                   of crates.io instead of from crates.io
+  --request-timeout
+                   Give up on the download if no data arrives for this long, 30s by default
+  --dry-run        Only ask crates.io when the dump was last modified and how large it is,
+                   and tell whether it would be downloaded, without changing anything
+  -v, --verbose    Log the requests made and the files read to stderr.
+                   Specify twice, as in `-vv`, for even more detail.
+  --timing         Print to stderr how long the download took, once done\n";
+
+const CLEAN_HELP: &str = "Delete the local cache, reporting how much disk space it frees
+
+Removes the daily dump along with the cached API responses and the leftovers of interrupted
+downloads, for when the cache is in a bad state or the disk space is needed.
+Only the files this tool keeps are deleted from the cache directory, which is taken from
+the CARGO_SUPPLY_CHAIN_CACHE environment variable if it is set.
+The next command queries the live API, until 'update' downloads the dump again.
+
+USAGE:
+  cargo supply-chain clean [OPTIONS...]
+
+OPTIONS:
+  --dry-run        List the files that would be deleted and how much space they take,
+                   without deleting anything
+  -v, --verbose    Log the files deleted to stderr.
+                   Specify twice, as in `-vv`, for even more detail.\n";
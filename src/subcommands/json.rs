@@ -1,57 +1,367 @@
 //! `json` subcommand is equivalent to `crates`,
 //! but provides structured output and more info about each publisher.
+//! The same data can also be rendered as a standalone HTML report, as a Graphviz graph,
+//! or as a CycloneDX SBOM.
 use crate::common::*;
-use crate::publishers::{fetch_owners_of_crates, PublisherData};
+use crate::publishers::{
+    fetch_owners_of_crates, stream_owners_of_crates, CrateOwners, CratePublishers, PublisherData,
+};
+use crate::{cyclonedx, dot, html};
+use crate::{Error, JsonArgs, OutputFormat, QueryCommandArgs};
 use schemars::JsonSchema;
-use serde::Serialize;
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
 
-#[derive(JsonSchema, Debug, Serialize, Default, Clone)]
+/// Version of the output format.
+/// Increment this whenever the shape of `StructuredOutput` changes,
+/// so that consumers can reject output they don't know how to parse.
+pub const SCHEMA_VERSION: u32 = 12;
+
+#[derive(JsonSchema, Debug, Serialize, Deserialize, Clone)]
 pub struct StructuredOutput {
-    not_audited: NotAudited,
-    /// Maps crate names to info about the publishers of each crate
-    crates_io_crates: BTreeMap<String, Vec<PublisherData>>,
+    /// Version of this output format. It is incremented whenever the format changes.
+    /// Consumers should reject versions they don't know about.
+    pub(crate) schema_version: u32,
+    pub(crate) not_audited: NotAudited,
+    /// Maps crate names to info about the publishers of each crate.
+    /// An empty list means that the crate was looked up and nobody can publish it,
+    /// such as a workspace member that is not published yet.
+    /// The `contact` field of each publisher was added in schema version 4,
+    /// and the `total_crates` field in schema version 9.
+    pub(crate) crates_io_crates: BTreeMap<String, Vec<PublisherData>>,
+    /// Maps the names of crates.io crates whose publishers could not be looked up to the error.
+    /// These crates are absent from `crates_io_crates`. Added in schema version 8.
+    #[serde(default)]
+    pub(crate) lookup_errors: BTreeMap<String, String>,
+    /// Maps crate names to the versions of each crate present in the dependency graph.
+    /// Added in schema version 2.
+    #[serde(default)]
+    pub(crate) crate_versions: BTreeMap<String, Vec<CrateVersion>>,
+    /// Maps crate names to the time the newest version of each crate was published, in RFC 3339.
+    /// Crates for which the time is not known are omitted. Added in schema version 3.
+    #[serde(default)]
+    pub(crate) last_published: BTreeMap<String, String>,
+    /// Names of the crates.io crates that a workspace member depends on directly.
+    /// All other crates are transitive dependencies. Added in schema version 5.
+    #[serde(default)]
+    pub(crate) direct_dependencies: BTreeSet<String>,
+    /// Maps the name of every crates.io crate to whether it runs code on the machine doing the build,
+    /// as a proc-macro, a build-dependency, or a dependency of either. Added in schema version 7.
+    #[serde(default)]
+    pub(crate) build_time: BTreeMap<String, bool>,
+    /// Maps the name of every crates.io crate to whether it is published but nobody can publish it
+    /// anymore, such as after all of its owners removed themselves. Nobody can release fixes for
+    /// such a crate, and it is a target for takeovers. Crates that were never published are not
+    /// ownerless. Added in schema version 10.
+    #[serde(default)]
+    pub(crate) ownerless: BTreeMap<String, bool>,
+    /// Maps the names of crates.io crates to the crates.io categories in their manifests,
+    /// such as `cryptography` or `network-programming`. Only filled in with `--show-categories`,
+    /// and crates without any are omitted. Added in schema version 12.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) categories: BTreeMap<String, Vec<String>>,
+    /// Maps the names of crates.io crates to the keywords in their manifests.
+    /// Only filled in with `--show-categories`, and crates without any are omitted.
+    /// Added in schema version 12.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) keywords: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(JsonSchema, Debug, Serialize, Deserialize, Clone)]
+pub struct CrateVersion {
+    pub(crate) version: String,
+    /// Whether this version has been yanked from crates.io
+    pub(crate) yanked: bool,
+    /// License expression from the manifest of this version, such as `MIT OR Apache-2.0`.
+    /// Absent if it only points at a license file. Added in schema version 11.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) license: Option<String>,
+}
+
+impl StructuredOutput {
+    /// Lists the versions that have been yanked, such as `serde 1.0.0`
+    pub(crate) fn yanked_versions(&self) -> Vec<String> {
+        self.crate_versions
+            .iter()
+            .flat_map(|(crate_name, versions)| {
+                versions
+                    .iter()
+                    .filter(|v| v.yanked)
+                    .map(move |v| format!("{} {}", crate_name, v.version))
+            })
+            .collect()
+    }
 }
 
-#[derive(JsonSchema, Debug, Serialize, Default, Clone)]
+impl Default for StructuredOutput {
+    fn default() -> Self {
+        StructuredOutput {
+            schema_version: SCHEMA_VERSION,
+            not_audited: NotAudited::default(),
+            crates_io_crates: BTreeMap::new(),
+            lookup_errors: BTreeMap::new(),
+            crate_versions: BTreeMap::new(),
+            last_published: BTreeMap::new(),
+            direct_dependencies: BTreeSet::new(),
+            build_time: BTreeMap::new(),
+            ownerless: BTreeMap::new(),
+            categories: BTreeMap::new(),
+            keywords: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(JsonSchema, Debug, Serialize, Deserialize, Default, Clone)]
 pub struct NotAudited {
     /// Names of crates that are imported from a location in the local filesystem, not from a registry
-    local_crates: Vec<String>,
+    pub(crate) local_crates: Vec<String>,
     /// Names of crates that are neither from crates.io nor from a local filesystem
-    foreign_crates: Vec<String>,
+    pub(crate) foreign_crates: Vec<String>,
+    /// Maps the names of foreign crates that come from a registry other than crates.io
+    /// to the index URL of that registry. Added in schema version 6.
+    #[serde(default)]
+    pub(crate) alternative_registries: BTreeMap<String, String>,
+}
+
+pub fn json(out: &mut dyn Write, args: QueryCommandArgs, json_args: JsonArgs) -> Result<(), Error> {
+    if json_args.format == OutputFormat::Ndjson {
+        return ndjson(out, &args);
+    }
+    let (output, crate_owners) = structured_output(&args, json_args.show_categories)?;
+    if json_args.format == OutputFormat::Toml {
+        write!(out, "{}", to_toml(&output)?)?;
+    } else if json_args.format == OutputFormat::Html {
+        write!(out, "{}", html::render(&output))?;
+    } else if json_args.format == OutputFormat::Dot {
+        write!(out, "{}", dot::render(&output))?;
+    } else {
+        let pretty = json_args.pretty.unwrap_or(args.diffable);
+        let mut document = if json_args.format == OutputFormat::CycloneDx {
+            cyclonedx::render(&output)
+        } else {
+            serde_json::to_value(&output)?
+        };
+        if args.diffable {
+            sort_keys(&mut document);
+        }
+        if pretty {
+            serde_json::to_writer_pretty(out, &document)?
+        } else {
+            serde_json::to_writer(out, &document)?
+        }
+    }
+    crate_owners.check_lookups()?;
+    args.check_yanked(&output.yanked_versions())
+}
+
+/// Prints each crate as soon as it's looked up, rather than everything at the end,
+/// so that nothing but the lookup results of crates is held on to
+fn ndjson(out: &mut dyn Write, args: &QueryCommandArgs) -> Result<(), Error> {
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    graph.complain_about_excluded_crates(args.quiet);
+    graph.complain_about_inactive_optional_crates(args.quiet);
+    let mut versions = crate_versions(&graph.packages);
+    let mut licenses = crate_licenses(&graph.packages);
+    let mut sorted = Vec::new();
+    let mut yanked = Vec::new();
+    let crate_owners = stream_owners_of_crates(
+        &graph.packages,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+        &mut |crate_name, data| {
+            let versions = versions.remove(crate_name).unwrap_or_default();
+            let licenses = licenses.remove(crate_name).unwrap_or_default();
+            let mut line = CratePublishers::from_data(crate_name, versions, licenses, data);
+            for publisher in line.publishers.iter_mut() {
+                publisher.contact = Some(publisher.contact());
+            }
+            for version in &line.yanked_versions {
+                yanked.push(format!("{} {}", crate_name, version));
+            }
+            if args.diffable {
+                let mut line = serde_json::to_value(&line)?;
+                sort_keys(&mut line);
+                sorted.push((crate_name.to_owned(), line));
+                return Ok(());
+            }
+            serde_json::to_writer(&mut *out, &line)?;
+            writeln!(out)?;
+            Ok(())
+        },
+    )?;
+    sorted.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    for (_, line) in &sorted {
+        serde_json::to_writer(&mut *out, line)?;
+        writeln!(out)?;
+    }
+    crate_owners.check_lookups()?;
+    yanked.sort_unstable();
+    args.check_yanked(&yanked)
+}
+
+/// Orders the keys of every object in `value` alphabetically, recursively, so that diffable
+/// output doesn't depend on the order fields are declared in. Arrays are already in a fixed order,
+/// sorted by crate name and version, and publishers by ID or login.
+fn sort_keys(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            let mut entries: Vec<(String, serde_json::Value)> =
+                std::mem::take(object).into_iter().collect();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, mut value) in entries {
+                sort_keys(&mut value);
+                object.insert(key, value);
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(sort_keys),
+        _ => (),
+    }
+}
+
+/// TOML has no compact form, so it is always easy to diff.
+/// TOML requires the plain values of a table to come before its subtables, which is not the
+/// order fields are declared in, so the output goes through `toml::Value` to put them first.
+pub(crate) fn to_toml(output: &StructuredOutput) -> Result<String, Error> {
+    toml::Value::try_from(output)
+        .and_then(|value| toml::to_string_pretty(&value))
+        .map_err(|e| Error::Parse(format!("Failed to render the output as TOML: {}", e)))
 }
 
-pub fn json(
-    args: Vec<String>,
-    diffable: bool,
-    max_age: std::time::Duration,
-) -> Result<(), std::io::Error> {
+/// Resolves the dependency graph and its publishers into the output of the `json` subcommand,
+/// returned along with the lookup results it was built from.
+/// Categories and keywords are only included with `show_categories`.
+pub(crate) fn structured_output(
+    args: &QueryCommandArgs,
+    show_categories: bool,
+) -> Result<(StructuredOutput, CrateOwners), Error> {
     let mut output = StructuredOutput::default();
-    let dependencies = sourced_dependencies(args);
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    let dependencies = &graph.packages;
+    graph.complain_about_excluded_crates(args.quiet);
+    graph.complain_about_inactive_optional_crates(args.quiet);
+    output.direct_dependencies = graph.direct_dependencies();
     // Report non-crates.io dependencies
-    output.not_audited.local_crates = crate_names_from_source(&dependencies, PkgSource::Local);
-    output.not_audited.foreign_crates = crate_names_from_source(&dependencies, PkgSource::Foreign);
+    output.not_audited.local_crates = crate_names_from_source(dependencies, PkgSource::Local);
+    output.not_audited.foreign_crates = [PkgSource::Git, PkgSource::Registry, PkgSource::Foreign]
+        .iter()
+        .flat_map(|source| crate_names_from_source(dependencies, *source))
+        .collect();
+    output.not_audited.alternative_registries = alternative_registries(dependencies);
     output.not_audited.local_crates.sort_unstable();
     output.not_audited.foreign_crates.sort_unstable();
     // Fetch list of owners and publishers
-    let (mut owners, publisher_teams) = fetch_owners_of_crates(&dependencies, max_age)?;
+    let crate_owners = fetch_owners_of_crates(
+        dependencies,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+    )?;
     // Merge the two maps we received into one
-    for (crate_name, publishers) in publisher_teams {
+    let mut owners = crate_owners.users.clone();
+    for (crate_name, publishers) in crate_owners.teams.clone() {
         owners.entry(crate_name).or_default().extend(publishers)
     }
     // Sort the vectors of publisher data. This helps when diffing the output,
     // but we do it unconditionally because it's cheap and helps users pull less hair when debugging.
     for list in owners.values_mut() {
-        list.sort_unstable_by_key(|x| x.id)
+        list.sort_unstable_by_key(|x| x.id);
+        for publisher in list.iter_mut() {
+            publisher.contact = Some(publisher.contact());
+        }
     }
+    output.ownerless = owners
+        .keys()
+        .map(|name| (name.clone(), crate_owners.ownerless.contains(name)))
+        .collect();
     output.crates_io_crates = owners;
-    // Print the result to stdout
-    let stdout = std::io::stdout();
-    let handle = stdout.lock();
-    if diffable {
-        serde_json::to_writer_pretty(handle, &output)?;
-    } else {
-        serde_json::to_writer(handle, &output)?;
+    output.lookup_errors = crate_owners
+        .lookup_errors
+        .iter()
+        .map(|(crate_name, error)| (crate_name.clone(), error.to_string()))
+        .collect();
+    let mut licenses = version_licenses(dependencies);
+    for (crate_name, versions) in crate_versions(dependencies) {
+        let yanked = crate_owners.yanked_in(&crate_name, &versions);
+        let versions = versions
+            .iter()
+            .map(|version| CrateVersion {
+                version: version.clone(),
+                yanked: yanked.contains(&version),
+                license: licenses.remove(&(crate_name.clone(), version.clone())),
+            })
+            .collect();
+        output.crate_versions.insert(crate_name, versions);
+    }
+    let build_time = graph.build_time_dependencies();
+    output.build_time = crate_names_from_source(dependencies, PkgSource::CratesIo)
+        .into_iter()
+        .map(|name| {
+            let runs_at_build_time = build_time.contains(&name);
+            (name, runs_at_build_time)
+        })
+        .collect();
+    output.last_published = crate_owners.last_published.clone();
+    if show_categories {
+        output.categories = crate_tags(dependencies, |package| &package.categories);
+        output.keywords = crate_tags(dependencies, |package| &package.keywords);
+    }
+    Ok((output, crate_owners))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::publishers::PublisherKind;
+
+    /// Fills in every kind of field, since TOML only fails on some orders of values and tables
+    fn output_of_every_field() -> StructuredOutput {
+        let mut publisher = PublisherData {
+            id: 1,
+            login: "alice".to_owned(),
+            kind: PublisherKind::user,
+            name: Some("Alice".to_owned()),
+            avatar: None,
+            contact: None,
+            total_crates: Some(3),
+        };
+        publisher.contact = Some(publisher.contact());
+        let mut output = StructuredOutput::default();
+        output.not_audited.local_crates = vec!["app".to_owned()];
+        output
+            .crates_io_crates
+            .insert("foo".to_owned(), vec![publisher]);
+        output
+            .lookup_errors
+            .insert("bar".to_owned(), "not found".to_owned());
+        let version = CrateVersion {
+            version: "1.0.0".to_owned(),
+            yanked: false,
+            license: Some("MIT".to_owned()),
+        };
+        output
+            .crate_versions
+            .insert("foo".to_owned(), vec![version]);
+        output
+            .last_published
+            .insert("foo".to_owned(), "2021-01-01T00:00:00Z".to_owned());
+        output.direct_dependencies.insert("foo".to_owned());
+        output.build_time.insert("foo".to_owned(), false);
+        output.ownerless.insert("foo".to_owned(), false);
+        output
+            .categories
+            .insert("foo".to_owned(), vec!["parsing".to_owned()]);
+        output
+    }
+
+    #[test]
+    fn toml_output_parses_back() {
+        let output = output_of_every_field();
+        let parsed: StructuredOutput = toml::from_str(&to_toml(&output).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&output).unwrap()
+        );
     }
-    Ok(())
 }
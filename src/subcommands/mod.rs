@@ -1,11 +1,25 @@
+pub mod clean;
 pub mod crates;
+pub mod diff;
 pub mod help;
 pub mod json;
+pub mod owners;
 pub mod publishers;
+pub mod risks;
+pub mod stats;
 pub mod update;
+pub mod verify;
+pub mod why;
 
+pub use clean::clean;
 pub use crates::crates;
+pub use diff::diff;
 pub use help::help;
 pub use json::json;
+pub use owners::owners;
 pub use publishers::publishers;
+pub use risks::risks;
+pub use stats::stats;
 pub use update::update;
+pub use verify::verify;
+pub use why::why;
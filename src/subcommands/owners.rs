@@ -0,0 +1,108 @@
+//! `owners` subcommand groups the dependency graph by the teams that can publish it,
+//! since a single compromised team token can affect every crate the team owns.
+use crate::publishers::fetch_owners_of_crates;
+use crate::subcommands::publishers::{
+    sort_transposed_map_for_diffing, sort_transposed_map_for_display, transpose_publishers_map,
+};
+use crate::{common::*, publishers::PublishersMap, Error, QueryCommandArgs};
+use std::io::Write;
+
+pub fn owners(out: &mut dyn Write, args: QueryCommandArgs) -> Result<(), Error> {
+    let trust = args.trust_list()?;
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    let dependencies = &graph.packages;
+    complain_about_non_crates_io_crates(dependencies, args.quiet);
+    graph.complain_about_excluded_crates(args.quiet);
+    graph.complain_about_inactive_optional_crates(args.quiet);
+    let mut owners = fetch_owners_of_crates(
+        dependencies,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+    )?;
+    args.first_party_crates(&mut owners, false);
+    let yanked = yanked_in_dependency_graph(dependencies, &owners);
+    // Reported once the output is printed
+    let lookups = owners.check_lookups();
+    let (publisher_users, publisher_teams) = (owners.users, owners.teams);
+
+    // Crates without any team among their owners are attributed to individuals only
+    let users_without_teams: PublishersMap = publisher_users
+        .into_iter()
+        .filter(|(crate_name, _)| {
+            publisher_teams
+                .get(crate_name)
+                .is_none_or(|teams| teams.is_empty())
+        })
+        .collect();
+
+    let mut team_to_crate_map = transpose_publishers_map(&publisher_teams);
+    let mut user_to_crate_map = transpose_publishers_map(&users_without_teams);
+    team_to_crate_map.values_mut().for_each(|c| c.sort());
+    user_to_crate_map.values_mut().for_each(|c| c.sort());
+    if args.untrusted_only {
+        team_to_crate_map.retain(|team, _| !trust.is_trusted(team));
+        user_to_crate_map.retain(|user, _| !trust.is_trusted(user));
+    }
+
+    if args.diffable {
+        for (team, crates) in sort_transposed_map_for_diffing(team_to_crate_map).iter() {
+            let login = trust.annotate(team, format!("team \"{}\"", &team.login));
+            writeln!(out, "{}: {}", login, comma_separated_list(crates))?;
+        }
+        for (user, crates) in sort_transposed_map_for_diffing(user_to_crate_map).iter() {
+            let login = trust.annotate(user, format!("user \"{}\"", &user.login));
+            writeln!(out, "{}: {}", login, comma_separated_list(crates))?;
+        }
+        lookups?;
+        return args.check_yanked(&yanked);
+    }
+
+    if !team_to_crate_map.is_empty() {
+        writeln!(
+            out,
+            "\nThe following teams can publish updates for your dependencies:\n"
+        )?;
+        let map_for_display = sort_transposed_map_for_display(team_to_crate_map);
+        for (i, (team, crates)) in map_for_display.iter().enumerate() {
+            writeln!(
+                out,
+                " {}. {} owns {} crate{}: {}",
+                i + 1,
+                trust.annotate(team, format!("\"{}\"", &team.login)),
+                crates.len(),
+                if crates.len() == 1 { "" } else { "s" },
+                comma_separated_list(crates)
+            )?;
+        }
+        if !args.quiet {
+            eprintln!(
+                "\nMember counts are not available: crates.io does not expose team membership, and"
+            );
+            eprintln!("Github teams are black boxes. It's impossible to get the member list without explicit permission.");
+        }
+    }
+
+    if !user_to_crate_map.is_empty() {
+        writeln!(
+            out,
+            "\nThe following individuals can publish crates that are not owned by any team:\n"
+        )?;
+        let map_for_display = sort_transposed_map_for_display(user_to_crate_map);
+        for (i, (user, crates)) in map_for_display.iter().enumerate() {
+            writeln!(
+                out,
+                " {}. {} via crates: {}",
+                i + 1,
+                trust.annotate(user, user.login.clone()),
+                comma_separated_list(crates)
+            )?;
+        }
+        if !args.quiet {
+            eprintln!("\nNote: there may be outstanding publisher invitations. crates.io provides no way to list them.");
+            eprintln!("See https://github.com/rust-lang/crates.io/issues/2868 for more info.");
+        }
+    }
+    lookups?;
+    args.check_yanked(&yanked)
+}
@@ -1,81 +1,606 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
 
+use crate::api_client::RateLimitedClient;
+use crate::github::{self, TeamMember, GITHUB_TOKEN_ENV};
 use crate::publishers::fetch_owners_of_crates;
-use crate::{common::*, publishers::PublisherData};
+use crate::publishers::{PublisherData, PublisherKind};
+use crate::trust::TrustList;
+use crate::{common::*, Error, PublishersArgs, QueryCommandArgs};
 
 pub fn publishers(
-    metadata_args: Vec<String>,
-    diffable: bool,
-    max_age: std::time::Duration,
-) -> Result<(), std::io::Error> {
-    let dependencies = sourced_dependencies(metadata_args);
-    complain_about_non_crates_io_crates(&dependencies);
-    let (publisher_users, publisher_teams) = fetch_owners_of_crates(&dependencies, max_age)?;
+    out: &mut dyn Write,
+    args: QueryCommandArgs,
+    publishers_args: PublishersArgs,
+) -> Result<(), Error> {
+    let trust = args.trust_list()?;
+    if publishers_args.per_target {
+        return publishers_per_target(out, args, publishers_args, &trust);
+    }
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    let dependencies = &graph.packages;
+    complain_about_non_crates_io_crates(dependencies, args.quiet);
+    graph.complain_about_excluded_crates(args.quiet);
+    graph.complain_about_inactive_optional_crates(args.quiet);
+    let client = args.client();
+    let mut owners =
+        fetch_owners_of_crates(dependencies, &client, args.cache_max_age, args.verbosity())?;
+    args.first_party_crates(&mut owners, false);
+    let (publisher_users, publisher_teams) = (&owners.users, &owners.teams);
 
     // Group data by user rather than by crate
-    let mut user_to_crate_map = transpose_publishers_map(&publisher_users);
-    let mut team_to_crate_map = transpose_publishers_map(&publisher_teams);
+    let mut user_to_crate_map = transpose_publishers_map(publisher_users);
+    let mut team_to_crate_map = transpose_publishers_map(publisher_teams);
 
     // Sort crate names alphabetically
     user_to_crate_map.values_mut().for_each(|c| c.sort());
     team_to_crate_map.values_mut().for_each(|c| c.sort());
 
+    let memberships = if publishers_args.expand_teams {
+        Some(expand_teams(
+            &mut user_to_crate_map,
+            &mut team_to_crate_map,
+            &client,
+        ))
+    } else {
+        None
+    };
+
+    if args.untrusted_only {
+        user_to_crate_map.retain(|user, _| !trust.is_trusted(user));
+        team_to_crate_map.retain(|team, _| !trust.is_trusted(team));
+    }
+    match publishers_args.only_kind {
+        Some(PublisherKind::user) => team_to_crate_map.clear(),
+        Some(PublisherKind::team) => user_to_crate_map.clear(),
+        None => (),
+    }
+    let publisher_count = user_to_crate_map.len() + team_to_crate_map.len();
+
+    match (&publishers_args.publisher, publishers_args.top) {
+        _ if publishers_args.logins_only => print_logins(
+            out,
+            user_to_crate_map.keys().chain(team_to_crate_map.keys()),
+        ),
+        (Some(login), _) => print_crates_of(
+            out,
+            login,
+            user_to_crate_map.into_iter().chain(team_to_crate_map),
+            &graph,
+            &trust,
+            args.diffable,
+        ),
+        (None, Some(top)) => print_top(
+            out,
+            user_to_crate_map,
+            team_to_crate_map,
+            top,
+            owners.users.len(),
+            &trust,
+            args.diffable,
+        ),
+        (None, None) => print_by_kind(
+            out,
+            user_to_crate_map,
+            team_to_crate_map,
+            memberships.as_ref(),
+            &trust,
+            args.diffable,
+            args.quiet,
+        ),
+    }?;
+
+    if !args.include_yanked {
+        complain_about_yanked_versions(dependencies, &owners);
+    }
+    owners.check_lookups()?;
+    args.check_yanked(&yanked_in_dependency_graph(dependencies, &owners))?;
+
+    if let Some(max_publishers) = publishers_args.max_publishers {
+        if publisher_count > max_publishers {
+            return Err(Error::ThresholdExceeded(format!(
+                "Found {} publishers, more than the maximum of {} allowed by --max-publishers",
+                publisher_count, max_publishers
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Splits the publishers by the set of targets they publish crates for. Each target has a graph of its own,
+/// but publishers are looked up for all of them at once.
+fn publishers_per_target(
+    out: &mut dyn Write,
+    args: QueryCommandArgs,
+    publishers_args: PublishersArgs,
+    trust: &TrustList,
+) -> Result<(), Error> {
+    let targets = &args.metadata_args.target;
+    let mut graphs = Vec::new();
+    for target in targets {
+        let mut metadata_args = args.metadata_args.clone();
+        metadata_args.target = vec![target.clone()];
+        graphs.push(DependencyGraph::load_possibly_empty(&metadata_args)?);
+    }
+    let mut dependencies: Vec<SourcedPackage> = graphs
+        .iter()
+        .flat_map(|graph| graph.packages.iter().cloned())
+        .collect();
+    dependencies.sort_unstable_by(|a, b| a.package.id.cmp(&b.package.id));
+    dependencies.dedup_by(|a, b| a.package.id == b.package.id);
+    // A target without crates.io crates of its own is fine, as long as another one has some
+    if !graphs.iter().any(|graph| graph.has_crates_io_crates()) {
+        return Err(graphs[0].nothing_to_analyze(&args.metadata_args));
+    }
+    complain_about_non_crates_io_crates(&dependencies, args.quiet);
+    graphs[0].excluded = graphs
+        .iter()
+        .flat_map(|graph| graph.excluded.iter().cloned())
+        .collect();
+    graphs[0].complain_about_excluded_crates(args.quiet);
+    graphs[0].complain_about_inactive_optional_crates(args.quiet);
+    let mut owners = fetch_owners_of_crates(
+        &dependencies,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+    )?;
+    args.first_party_crates(&mut owners, false);
+
+    // Keyed by kind and login, since IDs of users and teams may coincide
+    let mut by_publisher: BTreeMap<(PublisherKind, String), TargetedPublisher> = BTreeMap::new();
+    for (i, graph) in graphs.iter().enumerate() {
+        for crate_name in crate_names_from_source(&graph.packages, PkgSource::CratesIo) {
+            let publishers = owners.users.get(&crate_name).into_iter().flatten();
+            let publishers = publishers.chain(owners.teams.get(&crate_name).into_iter().flatten());
+            for publisher in publishers {
+                if args.untrusted_only && trust.is_trusted(publisher) {
+                    continue;
+                }
+                if publishers_args
+                    .only_kind
+                    .is_some_and(|kind| kind != publisher.kind)
+                {
+                    continue;
+                }
+                let entry = by_publisher
+                    .entry((publisher.kind, publisher.login.clone()))
+                    .or_insert_with(|| TargetedPublisher {
+                        publisher: publisher.clone(),
+                        targets: BTreeSet::new(),
+                        crates: BTreeSet::new(),
+                    });
+                entry.targets.insert(i);
+                entry.crates.insert(crate_name.clone());
+            }
+        }
+    }
+    let publisher_count = by_publisher.len();
+
+    if !args.diffable {
+        writeln!(out)?;
+        for (i, (target, graph)) in targets.iter().zip(&graphs).enumerate() {
+            let crates = crate_names_from_source(&graph.packages, PkgSource::CratesIo);
+            let publishers = by_publisher
+                .values()
+                .filter(|entry| entry.targets.contains(&i))
+                .count();
+            writeln!(
+                out,
+                "{}: {} publisher{} of {} crates.io crate{}",
+                target,
+                publishers,
+                if publishers == 1 { "" } else { "s" },
+                crates.len(),
+                if crates.len() == 1 { "" } else { "s" }
+            )?;
+        }
+    }
+
+    let mut groups: BTreeMap<Vec<usize>, Vec<TargetedPublisher>> = BTreeMap::new();
+    for entry in by_publisher.into_values() {
+        groups
+            .entry(entry.targets.iter().copied().collect())
+            .or_default()
+            .push(entry);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    // Publishers common to all targets first, then those of the fewest targets
+    groups.sort_by_key(|(group, _)| (group.len() != targets.len(), group.len(), group.clone()));
+    for (group, mut entries) in groups {
+        let group_targets: Vec<String> = group.iter().map(|&i| targets[i].clone()).collect();
+        entries.sort_by_key(|entry| {
+            (
+                usize::MAX - entry.crates.len(),
+                entry.publisher.kind,
+                entry.publisher.login.clone(),
+            )
+        });
+        if !args.diffable {
+            if group.len() == targets.len() {
+                writeln!(
+                    out,
+                    "\nThe following publishers can publish crates for all targets:\n"
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "\nThe following publishers can only publish crates for {}:\n",
+                    comma_separated_list(&group_targets)
+                )?;
+            }
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            let kind = match entry.publisher.kind {
+                PublisherKind::team => "team",
+                PublisherKind::user => "user",
+            };
+            let login = trust.annotate(
+                &entry.publisher,
+                format!("{} \"{}\"", kind, &entry.publisher.login),
+            );
+            let crates: Vec<String> = entry.crates.iter().cloned().collect();
+            if args.diffable {
+                writeln!(
+                    out,
+                    "{}: {}: {}",
+                    group_targets.join(", "),
+                    login,
+                    comma_separated_list(&crates)
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    " {}. {} via crates: {}",
+                    i + 1,
+                    login,
+                    comma_separated_list(&crates)
+                )?;
+            }
+        }
+    }
+
+    if !args.include_yanked {
+        complain_about_yanked_versions(&dependencies, &owners);
+    }
+    owners.check_lookups()?;
+    args.check_yanked(&yanked_in_dependency_graph(&dependencies, &owners))?;
+    if let Some(max_publishers) = publishers_args.max_publishers {
+        if publisher_count > max_publishers {
+            return Err(Error::ThresholdExceeded(format!(
+                "Found {} publishers, more than the maximum of {} allowed by --max-publishers",
+                publisher_count, max_publishers
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A publisher along with the indices of the targets and the names of the crates it publishes
+struct TargetedPublisher {
+    publisher: PublisherData,
+    targets: BTreeSet<usize>,
+    crates: BTreeSet<String>,
+}
+
+/// Crates.io IDs are far below this, so adding it to the GitHub IDs of members that
+/// aren't crates.io owners themselves keeps them apart from the users looked up on crates.io
+const TEAM_MEMBER_ID_OFFSET: u64 = 1 << 63;
+
+/// Replaces the teams whose members GitHub lists with those members, who are merged into
+/// `user_to_crate_map` along with the crates of their teams. Other teams are left as they are.
+/// Returns the teams that each member, keyed by lowercase login, was found in.
+fn expand_teams(
+    user_to_crate_map: &mut BTreeMap<PublisherData, Vec<String>>,
+    team_to_crate_map: &mut BTreeMap<PublisherData, Vec<String>>,
+    client: &RateLimitedClient,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let mut memberships: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let teams: Vec<PublisherData> = team_to_crate_map.keys().cloned().collect();
+    for team in teams {
+        let members = match github::team_members(client, &team.login) {
+            Ok(members) => members,
+            Err(reason) => {
+                eprintln!(
+                    "Could not list the members of team \"{}\": {}",
+                    team.login, reason
+                );
+                continue;
+            }
+        };
+        log::debug!("Team \"{}\" has {} members", team.login, members.len());
+        let crates = team_to_crate_map
+            .remove(&team)
+            .expect("the team is one of the keys");
+        for member in members {
+            // Logins on crates.io are those on GitHub, which are case-insensitive
+            let user = user_to_crate_map
+                .keys()
+                .find(|user| user.login.eq_ignore_ascii_case(&member.login))
+                .cloned()
+                .unwrap_or_else(|| member_as_publisher(&member));
+            let user_crates = user_to_crate_map.entry(user).or_default();
+            user_crates.extend(crates.iter().cloned());
+            user_crates.sort();
+            user_crates.dedup();
+            memberships
+                .entry(member.login.to_ascii_lowercase())
+                .or_default()
+                .insert(team.login.clone());
+        }
+    }
+    memberships
+}
+
+fn member_as_publisher(member: &TeamMember) -> PublisherData {
+    PublisherData {
+        id: TEAM_MEMBER_ID_OFFSET + member.id,
+        login: member.login.clone(),
+        kind: PublisherKind::user,
+        name: None,
+        avatar: None,
+        contact: None,
+        total_crates: None,
+    }
+}
+
+/// Logins are unique across users and teams, since team logins contain a colon
+fn print_logins<'a>(
+    out: &mut dyn Write,
+    publishers: impl Iterator<Item = &'a PublisherData>,
+) -> io::Result<()> {
+    let logins: BTreeSet<&str> = publishers.map(|p| p.login.as_str()).collect();
+    for login in logins {
+        writeln!(out, "{}", login)?;
+    }
+    Ok(())
+}
+
+/// `memberships` are the teams each user was found in, if teams were expanded
+fn print_by_kind(
+    out: &mut dyn Write,
+    user_to_crate_map: BTreeMap<PublisherData, Vec<String>>,
+    team_to_crate_map: BTreeMap<PublisherData, Vec<String>>,
+    memberships: Option<&BTreeMap<String, BTreeSet<String>>>,
+    trust: &TrustList,
+    diffable: bool,
+    quiet: bool,
+) -> io::Result<()> {
     if diffable {
         // empty map just means 0 loop iterations here
         let sorted_map = sort_transposed_map_for_diffing(user_to_crate_map);
         for (user, crates) in sorted_map.iter() {
-            let crate_list = comma_separated_list(&crates);
-            println!("user \"{}\": {}", &user.login, crate_list);
+            let crate_list = comma_separated_list(crates);
+            let login = trust.annotate(user, format!("user \"{}\"", &user.login));
+            writeln!(out, "{}: {}", login, crate_list)?;
         }
-    } else if !publisher_users.is_empty() {
-        println!("\nThe following individuals can publish updates for your dependencies:\n");
+    } else if !user_to_crate_map.is_empty() {
+        writeln!(
+            out,
+            "\nThe following individuals can publish updates for your dependencies:\n"
+        )?;
         let map_for_display = sort_transposed_map_for_display(user_to_crate_map);
         for (i, (user, crates)) in map_for_display.iter().enumerate() {
             // We do not print usernames, since you can embed terminal control sequences in them
             // and erase yourself from the output that way.
-            let crate_list = comma_separated_list(&crates);
-            println!(" {}. {} via crates: {}", i + 1, &user.login, crate_list);
+            let crate_list = comma_separated_list(crates);
+            let mut login = trust.annotate(user, user.login.clone());
+            if let Some(teams) = memberships.and_then(|m| m.get(&user.login.to_ascii_lowercase())) {
+                let teams: Vec<String> = teams.iter().map(|team| format!("\"{}\"", team)).collect();
+                login = format!("{} (member of {})", login, comma_separated_list(&teams));
+            }
+            writeln!(
+                out,
+                " {}. {} {}: {}",
+                i + 1,
+                login,
+                via(user, crates),
+                crate_list
+            )?;
+        }
+        if !quiet {
+            eprintln!("\nNote: there may be outstanding publisher invitations. crates.io provides no way to list them.");
+            eprintln!("See https://github.com/rust-lang/crates.io/issues/2868 for more info.");
         }
-        eprintln!("\nNote: there may be outstanding publisher invitations. crates.io provides no way to list them.");
-        eprintln!("See https://github.com/rust-lang/crates.io/issues/2868 for more info.");
     }
 
     if diffable {
         let sorted_map = sort_transposed_map_for_diffing(team_to_crate_map);
         for (team, crates) in sorted_map.iter() {
-            let crate_list = comma_separated_list(&crates);
-            println!("team \"{}\": {}", &team.login, crate_list);
+            let crate_list = comma_separated_list(crates);
+            let login = trust.annotate(team, format!("team \"{}\"", &team.login));
+            writeln!(out, "{}: {}", login, crate_list)?;
         }
-    } else if !publisher_teams.is_empty() {
-        println!(
+    } else if !team_to_crate_map.is_empty() {
+        writeln!(
+            out,
             "\nAll members of the following teams can publish updates for your dependencies:\n"
-        );
+        )?;
         let map_for_display = sort_transposed_map_for_display(team_to_crate_map);
         for (i, (team, crates)) in map_for_display.iter().enumerate() {
-            let crate_list = comma_separated_list(&crates);
+            let crate_list = comma_separated_list(crates);
+            let login = trust.annotate(team, format!("\"{}\"", &team.login));
             if let (true, Some(org)) = (
                 team.login.starts_with("github:"),
                 team.login.split(':').nth(1),
             ) {
-                println!(
-                    " {}. \"{}\" (https://github.com/{}) via crates: {}",
+                writeln!(
+                    out,
+                    " {}. {} (https://github.com/{}) {}: {}",
                     i + 1,
-                    &team.login,
+                    login,
                     org,
+                    via(team, crates),
                     crate_list
-                );
+                )?;
             } else {
-                println!(" {}. \"{}\" via crates: {}", i + 1, &team.login, crate_list);
+                writeln!(
+                    out,
+                    " {}. {} {}: {}",
+                    i + 1,
+                    login,
+                    via(team, crates),
+                    crate_list
+                )?;
+            }
+        }
+        if !quiet {
+            if memberships.is_some() {
+                eprintln!("\nThe members of these teams could not be listed, see above.");
+            } else {
+                eprintln!("\nGithub teams are black boxes. It's impossible to get the member list without explicit permission.");
+                eprintln!(
+                    "Members of an organization can list them with --expand-teams and {} set.",
+                    GITHUB_TOKEN_ENV
+                );
             }
         }
-        eprintln!("\nGithub teams are black boxes. It's impossible to get the member list without explicit permission.");
+    }
+    Ok(())
+}
+
+/// Tells how many of all the crates of `publisher` on crates.io are in the dependency graph, if known,
+/// which tells prolific maintainers apart from accounts only publishing a few related crates
+fn via(publisher: &PublisherData, crates: &[String]) -> String {
+    match publisher.total_crates {
+        Some(total) => format!(
+            "via {} of their {} crate{} on crates.io",
+            crates.len(),
+            total,
+            if total == 1 { "" } else { "s" }
+        ),
+        None => "via crates".to_string(),
+    }
+}
+
+/// Prints the `top` users and teams that can publish the most crates, out of `total_crates`
+fn print_top(
+    out: &mut dyn Write,
+    user_to_crate_map: BTreeMap<PublisherData, Vec<String>>,
+    team_to_crate_map: BTreeMap<PublisherData, Vec<String>>,
+    top: usize,
+    total_crates: usize,
+    trust: &TrustList,
+    diffable: bool,
+) -> io::Result<()> {
+    let mut ranking: Vec<_> = user_to_crate_map
+        .into_iter()
+        .chain(team_to_crate_map)
+        .collect();
+    // Most crates first; ties are broken by kind and login to keep the output stable
+    ranking.sort_unstable_by_key(|(publisher, crates)| {
+        (
+            usize::MAX - crates.len(),
+            publisher.kind,
+            publisher.login.clone(),
+        )
+    });
+    ranking.truncate(top);
+
+    if !diffable && !ranking.is_empty() {
+        writeln!(
+            out,
+            "\nThe following publishers can publish the most crates in your dependency graph:\n"
+        )?;
+    }
+    for (i, (publisher, crates)) in ranking.iter().enumerate() {
+        let kind = match publisher.kind {
+            PublisherKind::team => "team",
+            PublisherKind::user => "user",
+        };
+        let login = trust.annotate(publisher, format!("{} \"{}\"", kind, &publisher.login));
+        if diffable {
+            writeln!(out, "{}: {}", login, crates.len())?;
+        } else {
+            // Share of all crates.io crates in the graph that a single compromise would affect
+            let share = 100.0 * crates.len() as f64 / total_crates.max(1) as f64;
+            writeln!(
+                out,
+                " {}. {} can publish {} crate{} ({:.0}%): {}",
+                i + 1,
+                login,
+                crates.len(),
+                if crates.len() == 1 { "" } else { "s" },
+                share,
+                comma_separated_list(crates)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the crates that the publisher with `login` can publish, most depended upon first.
+/// Logins are compared case-insensitively, like on crates.io.
+fn print_crates_of(
+    out: &mut dyn Write,
+    login: &str,
+    publishers: impl Iterator<Item = (PublisherData, Vec<String>)>,
+    graph: &DependencyGraph,
+    trust: &TrustList,
+    diffable: bool,
+) -> io::Result<()> {
+    let (publisher, mut crates) = match publishers
+        .into_iter()
+        .find(|(publisher, _)| publisher.login.eq_ignore_ascii_case(login))
+    {
+        Some(found) => found,
+        None => {
+            eprintln!(
+                "\nNo crates in your dependency graph can be published by \"{}\"",
+                login
+            );
+            return Ok(());
+        }
+    };
+    let direct = graph.direct_dependencies();
+    let dependents = graph.dependent_counts();
+    let dependents_of = |crate_name: &str| dependents.get(crate_name).copied().unwrap_or(0);
+    crates.sort_unstable_by_key(|crate_name| {
+        (usize::MAX - dependents_of(crate_name), crate_name.clone())
+    });
+
+    let kind = match publisher.kind {
+        PublisherKind::team => "team",
+        PublisherKind::user => "user",
+    };
+    let login = trust.annotate(&publisher, format!("{} \"{}\"", kind, &publisher.login));
+    if !diffable {
+        writeln!(
+            out,
+            "\n{} can publish {} crate{} in your dependency graph:\n",
+            login,
+            crates.len(),
+            if crates.len() == 1 { "" } else { "s" }
+        )?;
+    }
+    for (i, crate_name) in crates.iter().enumerate() {
+        let kind = if direct.contains(crate_name) {
+            "direct"
+        } else {
+            "transitive"
+        };
+        let count = dependents_of(crate_name);
+        if diffable {
+            writeln!(out, "{}: {}, depended on by {}", crate_name, kind, count)?;
+        } else {
+            writeln!(
+                out,
+                " {}. {} ({}, {} crate{} depend{} on it)",
+                i + 1,
+                crate_name,
+                kind,
+                count,
+                if count == 1 { "" } else { "s" },
+                if count == 1 { "s" } else { "" }
+            )?;
+        }
     }
     Ok(())
 }
 
 /// Turns a crate-to-publishers mapping into publisher-to-crates mapping.
 /// BTreeMap is used because PublisherData doesn't implement Hash.
-fn transpose_publishers_map(
+pub(crate) fn transpose_publishers_map(
     input: &BTreeMap<String, Vec<PublisherData>>,
 ) -> BTreeMap<PublisherData, Vec<String>> {
     let mut result: BTreeMap<PublisherData, Vec<String>> = BTreeMap::new();
@@ -92,7 +617,7 @@ fn transpose_publishers_map(
 
 /// Returns a Vec sorted so that publishers are sorted by the number of crates they control.
 /// If that number is the same, sort by login.
-fn sort_transposed_map_for_display(
+pub(crate) fn sort_transposed_map_for_display(
     input: BTreeMap<PublisherData, Vec<String>>,
 ) -> Vec<(PublisherData, Vec<String>)> {
     let mut result: Vec<_> = input.into_iter().collect();
@@ -102,7 +627,7 @@ fn sort_transposed_map_for_display(
     result
 }
 
-fn sort_transposed_map_for_diffing(
+pub(crate) fn sort_transposed_map_for_diffing(
     input: BTreeMap<PublisherData, Vec<String>>,
 ) -> Vec<(PublisherData, Vec<String>)> {
     let mut result: Vec<_> = input.into_iter().collect();
@@ -0,0 +1,256 @@
+//! `risks` subcommand lists crates that a single person can publish,
+//! since a single compromised account is all it takes to push a malicious update to them.
+//! Crates whose publishers changed recently are reported as well, as an early warning of takeovers.
+//! SARIF output also reports yanked versions in the dependency graph, and crates that have no owners left.
+//! With `--since`, only the crates whose publishers changed after a given date are reported.
+use crate::common::*;
+use crate::publishers::{fetch_owners_of_crates, OwnershipChange, PublisherKind};
+use crate::sarif::{self, Finding, Rule};
+use crate::{Error, OutputFormat, QueryCommandArgs, RisksArgs, WarningCategory};
+use std::io::Write;
+
+pub fn risks(
+    out: &mut dyn Write,
+    args: QueryCommandArgs,
+    risks_args: RisksArgs,
+) -> Result<(), Error> {
+    let trust = args.trust_list()?;
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    let dependencies = &graph.packages;
+    complain_about_non_crates_io_crates(dependencies, args.quiet);
+    graph.complain_about_excluded_crates(args.quiet);
+    graph.complain_about_inactive_optional_crates(args.quiet);
+    let mut owners = fetch_owners_of_crates(
+        dependencies,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+    )?;
+    // Our own code is no risk taken on from the internet
+    args.first_party_crates(&mut owners, true);
+    let dependents = graph.dependent_counts();
+
+    // Crates owned by exactly one user and no teams
+    let only_changes = risks_args.since.is_some();
+    let report_single_owner = !only_changes && !args.allows(WarningCategory::SingleOwner);
+    let mut single_owner: Vec<(&String, String, usize)> = owners
+        .users
+        .iter()
+        .filter(|_| report_single_owner)
+        .filter(|(crate_name, users)| {
+            users.len() == 1
+                && owners
+                    .teams
+                    .get(*crate_name)
+                    .is_none_or(|teams| teams.is_empty())
+        })
+        .filter(|(_, users)| !args.untrusted_only || !trust.is_trusted(&users[0]))
+        .map(|(crate_name, users)| {
+            let count = dependents.get(crate_name).copied().unwrap_or(0);
+            let login = trust.annotate(&users[0], users[0].login.clone());
+            (crate_name, login, count)
+        })
+        .collect();
+
+    let yanked = yanked_in_dependency_graph(dependencies, &owners);
+    let (window, period) = match risks_args.since {
+        Some(since) => (
+            since.elapsed().unwrap_or_default(),
+            format!("since {}", humantime::format_rfc3339_seconds(since)),
+        ),
+        None => (
+            risks_args.owner_changes_within,
+            format!(
+                "in the last {}",
+                humantime::format_duration(risks_args.owner_changes_within)
+            ),
+        ),
+    };
+    let recently_changed: Vec<(&String, Vec<&OwnershipChange>)> = owners
+        .ownership_changes
+        .iter()
+        .filter(|_| !args.allows(WarningCategory::OwnershipChanged))
+        .filter_map(|(crate_name, changes)| {
+            let recent: Vec<&OwnershipChange> = changes
+                .iter()
+                .filter(|change| !is_older_than(&change.time, window))
+                .collect();
+            (!recent.is_empty()).then_some((crate_name, recent))
+        })
+        .collect();
+
+    if risks_args.format == OutputFormat::Sarif {
+        let mut findings: Vec<Finding> = single_owner
+            .iter()
+            .map(|(crate_name, login, count)| Finding {
+                rule: Rule::SingleOwner,
+                crate_name: crate_name.to_string(),
+                version: None,
+                message: format!(
+                    "`{}` can be published by a single individual, {}, and no teams. {} crate{} in the dependency graph depend{} on it.",
+                    crate_name,
+                    login,
+                    count,
+                    if *count == 1 { "" } else { "s" },
+                    if *count == 1 { "s" } else { "" },
+                ),
+            })
+            .collect();
+        for (crate_name, changes) in &recently_changed {
+            let described: Vec<String> = changes.iter().map(|c| describe_change(c)).collect();
+            findings.push(Finding {
+                rule: Rule::OwnershipChanged,
+                crate_name: crate_name.to_string(),
+                version: None,
+                message: format!(
+                    "The publishers of `{}` changed {}: {}",
+                    crate_name,
+                    period,
+                    described.join("; ")
+                ),
+            });
+        }
+        for crate_name in owners
+            .ownerless
+            .iter()
+            .filter(|_| !only_changes && !args.allows(WarningCategory::Ownerless))
+        {
+            findings.push(Finding {
+                rule: Rule::Ownerless,
+                crate_name: crate_name.clone(),
+                version: None,
+                message: format!(
+                    "`{}` is published on crates.io, but nobody can publish it anymore",
+                    crate_name
+                ),
+            });
+        }
+        for (crate_name, versions) in crate_versions(dependencies)
+            .into_iter()
+            .filter(|_| !only_changes && !args.allows(WarningCategory::Yanked))
+        {
+            for version in owners.yanked_in(&crate_name, &versions) {
+                findings.push(Finding {
+                    rule: Rule::YankedVersion,
+                    crate_name: crate_name.clone(),
+                    version: Some(version.clone()),
+                    message: format!(
+                        "Version {} of `{}` has been yanked from crates.io",
+                        version, crate_name
+                    ),
+                });
+            }
+        }
+        sarif::print(out, findings, &graph.metadata)?;
+        owners.check_lookups()?;
+        return args.check_yanked(&yanked);
+    }
+
+    if args.diffable {
+        for (crate_name, login, count) in &single_owner {
+            writeln!(
+                out,
+                "{}: {}, {} dependent{}",
+                crate_name,
+                login,
+                count,
+                if *count == 1 { "" } else { "s" }
+            )?;
+        }
+        for (crate_name, changes) in &recently_changed {
+            for change in changes {
+                writeln!(
+                    out,
+                    "{}: {}{} {}",
+                    crate_name,
+                    if change.added { "+" } else { "-" },
+                    kind_name(change.kind),
+                    change.login
+                )?;
+            }
+        }
+        owners.check_lookups()?;
+        return args.check_yanked(&yanked);
+    }
+
+    // Shown first, since a takeover in progress is more urgent than a lack of redundancy
+    if !recently_changed.is_empty() {
+        writeln!(
+            out,
+            "\nThe publishers of the following crates changed {}:\n",
+            period
+        )?;
+        for (crate_name, changes) in &recently_changed {
+            for change in changes {
+                writeln!(out, " - {}: {}", crate_name, describe_change(change))?;
+            }
+        }
+    }
+
+    if !report_single_owner {
+        if recently_changed.is_empty() && !args.allows(WarningCategory::OwnershipChanged) {
+            writeln!(
+                out,
+                "\nThe publishers of the crates in the dependency graph have not changed {}.",
+                period
+            )?;
+        }
+        owners.check_lookups()?;
+        return args.check_yanked(&yanked);
+    }
+
+    // Most depended upon first
+    single_owner.sort_unstable_by_key(|(crate_name, _, count)| (usize::MAX - count, *crate_name));
+    if single_owner.is_empty() {
+        writeln!(
+            out,
+            "\nNo crates in the dependency graph can be published by a single individual."
+        )?;
+        owners.check_lookups()?;
+        return args.check_yanked(&yanked);
+    }
+    writeln!(
+        out,
+        "\nThe following crates can be published by a single individual, and no teams."
+    )?;
+    writeln!(
+        out,
+        "They are sorted by the number of crates in the dependency graph that depend on them:\n"
+    )?;
+    for (i, (crate_name, login, count)) in single_owner.iter().enumerate() {
+        writeln!(
+            out,
+            " {}. {}: {}, {} dependent{}",
+            i + 1,
+            paint(crate_name.to_string(), Color::Red, args.color),
+            login,
+            count,
+            if *count == 1 { "" } else { "s" }
+        )?;
+    }
+    if !args.quiet {
+        eprintln!("\nNote: there may be outstanding publisher invitations. crates.io provides no way to list them.");
+        eprintln!("See https://github.com/rust-lang/crates.io/issues/2868 for more info.");
+    }
+    owners.check_lookups()?;
+    args.check_yanked(&yanked)
+}
+
+fn kind_name(kind: PublisherKind) -> &'static str {
+    match kind {
+        PublisherKind::user => "user",
+        PublisherKind::team => "team",
+    }
+}
+
+/// Such as `added user dtolnay on 2020-01-31T12:00:00Z`
+fn describe_change(change: &OwnershipChange) -> String {
+    format!(
+        "{} {} {} {} {}",
+        if change.added { "added" } else { "removed" },
+        kind_name(change.kind),
+        change.login,
+        if change.noticed { "before" } else { "on" },
+        change.time
+    )
+}
@@ -0,0 +1,89 @@
+//! `stats` subcommand summarizes the dependency graph and its publishers in a few numbers,
+//! for a quick health check or a daily CI summary.
+use crate::common::*;
+use crate::publishers::{fetch_owners_of_crates, PublisherKind};
+use crate::subcommands::publishers::transpose_publishers_map;
+use crate::{Error, QueryCommandArgs};
+use std::io::Write;
+
+pub fn stats(out: &mut dyn Write, args: QueryCommandArgs) -> Result<(), Error> {
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    let dependencies = &graph.packages;
+    graph.complain_about_excluded_crates(args.quiet);
+    graph.complain_about_inactive_optional_crates(args.quiet);
+    let owners = fetch_owners_of_crates(
+        dependencies,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+    )?;
+
+    let source_counts = source_counts(dependencies);
+    let total_crates: usize = source_counts.values().sum();
+    let crates_io_crates = source_counts
+        .get(&PkgSource::CratesIo)
+        .copied()
+        .unwrap_or(0);
+    let user_to_crate_map = transpose_publishers_map(&owners.users);
+    let team_to_crate_map = transpose_publishers_map(&owners.teams);
+    let single_owner_crates = owners
+        .users
+        .iter()
+        .filter(|(crate_name, users)| {
+            users.len() == 1
+                && owners
+                    .teams
+                    .get(*crate_name)
+                    .is_none_or(|teams| teams.is_empty())
+        })
+        .count();
+    // The publisher who can publish the most crates; ties are broken by kind and login to keep the output stable
+    let top_publisher = user_to_crate_map
+        .iter()
+        .chain(team_to_crate_map.iter())
+        .min_by_key(|(publisher, crates)| {
+            (
+                usize::MAX - crates.len(),
+                publisher.kind,
+                publisher.login.clone(),
+            )
+        })
+        .map(|(publisher, crates)| {
+            let kind = match publisher.kind {
+                PublisherKind::team => "team",
+                PublisherKind::user => "user",
+            };
+            format!(
+                "{} \"{}\" ({} crate{})",
+                kind,
+                publisher.login,
+                crates.len(),
+                if crates.len() == 1 { "" } else { "s" }
+            )
+        })
+        .unwrap_or_else(|| "none".to_string());
+
+    let rows = [
+        ("Total crates", total_crates.to_string()),
+        ("Crates from crates.io", crates_io_crates.to_string()),
+        (
+            "Distinct publishers",
+            (user_to_crate_map.len() + team_to_crate_map.len()).to_string(),
+        ),
+        ("Distinct teams", team_to_crate_map.len().to_string()),
+        ("Single-owner crates", single_owner_crates.to_string()),
+        ("Publisher of the most crates", top_publisher),
+    ];
+    if !args.diffable {
+        writeln!(out)?;
+    }
+    for (label, value) in &rows {
+        if args.diffable {
+            writeln!(out, "{}: {}", label, value)?;
+        } else {
+            writeln!(out, " {:<30}{}", format!("{}:", label), value)?;
+        }
+    }
+    owners.check_lookups()?;
+    args.check_yanked(&yanked_in_dependency_graph(dependencies, &owners))
+}
@@ -1,18 +1,142 @@
-use crate::crates_cache::{CratesCache, DownloadState};
-use crate::{api_client::RateLimitedClient, err_exit};
+use crate::api_client::RateLimitedClient;
+use crate::crates_cache::{CacheState, CratesCache, DownloadState};
+use crate::timing::{self, Phase};
+use crate::Error;
+use std::time::Duration;
 
-pub fn update(max_age: std::time::Duration) {
+pub fn update(
+    max_age: Duration,
+    registry_url: Option<&str>,
+    request_timeout: Duration,
+    dry_run: bool,
+) -> Result<(), Error> {
     let mut cache = CratesCache::new();
-    let mut client = RateLimitedClient::new();
+    let client = RateLimitedClient::new().with_request_timeout(request_timeout);
+    let client = match registry_url {
+        Some(url) => client.with_registry_url(url),
+        None => client,
+    };
+    if dry_run {
+        return report_remote_dump(&mut cache, &client, max_age);
+    }
 
-    match cache.download(&mut client, max_age) {
+    match timing::measure(Phase::DumpDownload, || cache.download(&client, max_age)) {
         Ok(state) => match state {
-            DownloadState::Fresh => eprintln!("No updates found"),
-            DownloadState::Expired => {
-                eprintln!("Successfully updated to the newest daily data dump.")
-            }
-            DownloadState::Stale => err_exit("Downloaded latest daily data dump.\n  Warning: it matches the previous version that was considered outdated.")
+            DownloadState::Fresh => eprintln!(
+                "Nothing downloaded, the cache is still fresh. {}",
+                validity(&mut cache, max_age)
+            ),
+            DownloadState::NotModified => eprintln!(
+                "Nothing downloaded, crates.io has no newer dump than the cached one. {}",
+                validity(&mut cache, max_age)
+            ),
+            DownloadState::Expired => eprintln!(
+                "Successfully updated to the newest daily data dump. {}",
+                validity(&mut cache, max_age)
+            ),
+            DownloadState::Stale => return Err(Error::Cache(format!("Downloaded latest daily data dump.\n  Warning: it matches the previous version that was considered outdated. {}", validity(&mut cache, max_age))))
         },
-        Err(error) => err_exit(format!("Could not update to the latest daily data dump!\n{}", error).as_str())
+        Err(error) => {
+            // The error is passed on as is, so that its exit code reflects a network failure
+            eprintln!("Could not update to the latest daily data dump!");
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Tells what `update` would do, without downloading or changing anything
+fn report_remote_dump(
+    cache: &mut CratesCache,
+    client: &RateLimitedClient,
+    max_age: Duration,
+) -> Result<(), Error> {
+    let fresh = matches!(cache.state(max_age), CacheState::Fresh);
+    let remote = cache.probe(client)?;
+    let modified = match &remote.last_modified {
+        Some(time) => format!("was last modified {}", time),
+        None => "has no known modification time".to_string(),
+    };
+    if remote.not_modified {
+        eprintln!(
+            "The dump at {} {}, and is the same as the cached one.",
+            client.dump_url(),
+            modified
+        );
+    } else {
+        let size = match remote.length {
+            Some(length) => indicatif::HumanBytes(length).to_string(),
+            None => "of unknown size".to_string(),
+        };
+        eprintln!(
+            "The dump at {} is {}, and {}.",
+            client.dump_url(),
+            size,
+            modified
+        );
+    }
+    if fresh {
+        eprintln!(
+            "Without --dry-run, update would download nothing. {}",
+            validity(cache, max_age)
+        );
+        return Ok(());
+    }
+    let has_cache = cache.generated_at().is_some();
+    // `--cache-max-age 0` downloads the dump even if it hasn't changed
+    let action = if remote.not_modified && !max_age.is_zero() {
+        "download nothing, and consider the cache fresh again".to_string()
+    } else {
+        match (remote.resumable, remote.length) {
+            (Some(done), Some(length)) if done < length => format!(
+                "resume an interrupted download, with {} left",
+                indicatif::HumanBytes(length - done)
+            ),
+            _ if has_cache => "download it and replace the cache".to_string(),
+            _ => "download it".to_string(),
+        }
+    };
+    let reason = if has_cache && max_age.is_zero() {
+        "--cache-max-age 0 always refreshes the cache".to_string()
+    } else if has_cache {
+        format!(
+            "The cache is older than --cache-max-age {}",
+            humantime::format_duration(max_age)
+        )
+    } else {
+        "There is no cache yet".to_string()
+    };
+    eprintln!("{}, so without --dry-run update would {}.", reason, action);
+    Ok(())
+}
+
+/// Tells when the cached dump was generated and how much longer queries will use it,
+/// so that it's clear why they fetch live data or not
+fn validity(cache: &mut CratesCache, max_age: Duration) -> String {
+    let generated = match cache.generated_at() {
+        Some(time) => humantime::format_rfc3339_seconds(time).to_string(),
+        None => return String::new(),
+    };
+    if max_age.is_zero() {
+        return format!(
+            "The dump was generated at {}, and queries with --cache-max-age 0 ignore it and fetch live data.",
+            generated
+        );
+    }
+    let age = cache.age().unwrap_or(max_age);
+    if age < max_age {
+        let remaining = Duration::from_secs((max_age - age).as_secs());
+        format!(
+            "The dump was generated at {}, and is used for another {} with --cache-max-age {}.",
+            generated,
+            humantime::format_duration(remaining),
+            humantime::format_duration(max_age)
+        )
+    } else {
+        format!(
+            "The dump was generated at {}, and is already older than --cache-max-age {}, so queries with it will fetch live data.",
+            generated,
+            humantime::format_duration(max_age)
+        )
     }
 }
@@ -0,0 +1,125 @@
+//! `verify` subcommand checks the publishers of the dependency graph against an expectations file,
+//! which is an output of the `json` subcommand committed to the repository after review.
+//! Any publisher not listed in it makes the check fail, so that new publishers get reviewed too.
+use crate::common::comma_separated_list;
+use crate::subcommands::diff::{
+    pretty_publisher, publishers_missing_from, publishers_to_crates, read_snapshot,
+};
+use crate::subcommands::json::{structured_output, to_toml};
+use crate::{Error, QueryCommandArgs};
+use std::{fs, io, io::Write, path::Path};
+
+pub fn verify(
+    out: &mut dyn Write,
+    args: QueryCommandArgs,
+    expectations_path: &Path,
+    update_expectations: bool,
+) -> Result<(), Error> {
+    let (current, crate_owners) = structured_output(&args, false)?;
+    // Crates that could not be looked up would show up as publishers no longer present
+    crate_owners.check_lookups()?;
+    let expected = match read_snapshot(expectations_path) {
+        Ok(expected) => Some(expected),
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound && update_expectations => None,
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(Error::Io(io::Error::new(
+                e.kind(),
+                format!(
+                    "{}\n  Run `cargo supply-chain verify --update-expectations {}` to create it.",
+                    e,
+                    expectations_path.display()
+                ),
+            )))
+        }
+        Err(e) => return Err(e),
+    };
+
+    let current_publishers = publishers_to_crates(&current);
+    // A missing file is only accepted when it's about to be created, and then nothing is reported
+    let expected_publishers = match &expected {
+        Some(expected) => publishers_to_crates(expected),
+        None => publishers_to_crates(&current),
+    };
+    let unexpected = publishers_missing_from(&current_publishers, &expected_publishers);
+    let no_longer_present = publishers_missing_from(&expected_publishers, &current_publishers);
+
+    // Quiet output is only the lists, which are told apart by the sign
+    if !unexpected.is_empty() && !args.quiet {
+        writeln!(
+            out,
+            "Publishers not listed in {}:",
+            expectations_path.display()
+        )?;
+    }
+    for (publisher, crates) in &unexpected {
+        writeln!(
+            out,
+            " + {} via crates: {}",
+            pretty_publisher(publisher),
+            comma_separated_list(crates)
+        )?;
+    }
+    if !no_longer_present.is_empty() && !args.quiet {
+        writeln!(
+            out,
+            "Publishers listed in {} that are no longer present:",
+            expectations_path.display()
+        )?;
+    }
+    for (publisher, crates) in &no_longer_present {
+        writeln!(
+            out,
+            " - {} via crates: {}",
+            pretty_publisher(publisher),
+            comma_separated_list(crates)
+        )?;
+    }
+
+    if update_expectations {
+        // Written in the same format as it'd be read back
+        let contents = if expectations_path
+            .extension()
+            .is_some_and(|ext| ext == "toml")
+        {
+            to_toml(&current)?
+        } else {
+            let mut json = serde_json::to_string_pretty(&current)?;
+            json.push('\n');
+            json
+        };
+        fs::write(expectations_path, contents).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Failed to write {}: {}", expectations_path.display(), e),
+            )
+        })?;
+        if args.quiet {
+            return Ok(());
+        }
+        eprintln!(
+            "Updated {} with {} publishers of {} crates",
+            expectations_path.display(),
+            current_publishers.len(),
+            current.crates_io_crates.len()
+        );
+        return Ok(());
+    }
+
+    if !unexpected.is_empty() {
+        return Err(Error::ThresholdExceeded(format!(
+            "Found {} publisher{} not listed in {}. After reviewing them, run `cargo supply-chain verify --update-expectations {}` to accept them.",
+            unexpected.len(),
+            if unexpected.len() == 1 { "" } else { "s" },
+            expectations_path.display(),
+            expectations_path.display()
+        )));
+    }
+    if !args.quiet {
+        writeln!(
+            out,
+            "All publishers in the dependency graph are listed in {}",
+            expectations_path.display()
+        )?;
+    }
+    args.check_yanked(&current.yanked_versions())
+}
@@ -0,0 +1,159 @@
+//! `why` subcommand explains how a crate ends up in the dependency graph.
+//! It prints the crates depending on it up to the workspace, as `cargo tree -i` does,
+//! along with who can publish it, so that a suspicious crate can be traced to the dependency
+//! responsible for pulling it in.
+use crate::common::*;
+use crate::publishers::fetch_owners_of_crates;
+use crate::subcommands::diff::pretty_publisher;
+use crate::{Error, QueryCommandArgs};
+use cargo_metadata::{Package, PackageId};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+pub fn why(out: &mut dyn Write, args: QueryCommandArgs, crate_name: &str) -> Result<(), Error> {
+    let graph = DependencyGraph::load(&args.metadata_args)?;
+    let mut versions: Vec<SourcedPackage> = graph
+        .packages
+        .iter()
+        .filter(|dep| dep.package.name == crate_name)
+        .cloned()
+        .collect();
+    if versions.is_empty() {
+        return Err(Error::InvalidInput(
+            if graph.excluded.contains(crate_name) {
+                format!(
+                    "{} is left out of the dependency graph by --exclude",
+                    crate_name
+                )
+            } else {
+                format!(
+                    "{}: no package with this name in the dependency graph",
+                    crate_name
+                )
+            },
+        ));
+    }
+    versions.sort_by(|a, b| a.package.version.cmp(&b.package.version));
+
+    let tree = InvertedTree {
+        packages: graph
+            .metadata
+            .packages
+            .iter()
+            .map(|package| (&package.id, package))
+            .collect(),
+        dependents: graph.reverse_dependencies(),
+        workspace_members: graph.metadata.workspace_members.iter().collect(),
+    };
+    let mut printed = HashSet::new();
+    for dep in &versions {
+        writeln!(out, "{}", tree.describe(&dep.package.id))?;
+        printed.insert(&dep.package.id);
+        tree.print(out, &dep.package.id, "", &mut printed)?;
+    }
+
+    let source = versions[0].source;
+    if source != PkgSource::CratesIo {
+        if !args.quiet {
+            let registries = alternative_registries(&versions);
+            writeln!(
+                out,
+                "\n{} comes from {} rather than crates.io, so it has no crates.io publishers",
+                crate_name,
+                describe_source(crate_name, source, &registries)
+            )?;
+        }
+        return Ok(());
+    }
+    let owners = fetch_owners_of_crates(
+        &versions,
+        &args.client(),
+        args.cache_max_age,
+        args.verbosity(),
+    )?;
+    let mut publishers: Vec<String> = owners
+        .teams
+        .get(crate_name)
+        .into_iter()
+        .chain(owners.users.get(crate_name))
+        .flatten()
+        .map(pretty_publisher)
+        .collect();
+    publishers.sort_unstable();
+    if args.diffable {
+        for publisher in &publishers {
+            writeln!(out, "{}: {}", crate_name, publisher)?;
+        }
+    } else if owners.lookup_errors.is_empty() {
+        writeln!(
+            out,
+            "\nThe following can publish updates for {}:",
+            crate_name
+        )?;
+        for publisher in &publishers {
+            writeln!(out, " - {}", publisher)?;
+        }
+    }
+    owners.check_lookups()?;
+    args.check_yanked(&yanked_in_dependency_graph(&versions, &owners))
+}
+
+/// The reverse dependency edges, along with what's needed to describe each package
+struct InvertedTree<'a> {
+    packages: HashMap<&'a PackageId, &'a Package>,
+    dependents: HashMap<&'a PackageId, Vec<&'a PackageId>>,
+    workspace_members: HashSet<&'a PackageId>,
+}
+
+impl<'a> InvertedTree<'a> {
+    /// Prints the packages that depend on `id` below it, down to the workspace members.
+    /// Packages already printed along with their dependents are marked with `(*)` instead,
+    /// as `cargo tree` does, which also stops cycles through dev-dependencies.
+    fn print(
+        &self,
+        out: &mut dyn Write,
+        id: &'a PackageId,
+        prefix: &str,
+        printed: &mut HashSet<&'a PackageId>,
+    ) -> io::Result<()> {
+        let mut dependents: Vec<&Package> = self
+            .dependents
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter_map(|dependent| self.packages.get(dependent).copied())
+            .collect();
+        dependents.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        dependents.dedup_by(|a, b| a.id == b.id);
+        for (i, dependent) in dependents.iter().enumerate() {
+            let last = i + 1 == dependents.len();
+            let already_printed = !printed.insert(&dependent.id);
+            writeln!(
+                out,
+                "{}{}{}{}",
+                prefix,
+                if last { "└── " } else { "├── " },
+                self.describe(&dependent.id),
+                if already_printed && self.dependents.contains_key(&dependent.id) {
+                    " (*)"
+                } else {
+                    ""
+                }
+            )?;
+            if !already_printed {
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                self.print(out, &dependent.id, &prefix, printed)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self, id: &PackageId) -> String {
+        let package = self.packages[id];
+        if self.workspace_members.contains(id) {
+            format!("{} v{} (workspace member)", package.name, package.version)
+        } else {
+            format!("{} v{}", package.name, package.version)
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Wall-clock time spent in each phase of a run, reported on stderr by `--timing`,
+//! to tell whether resolving the dependency graph, reading the cache or querying crates.io dominates.
+//!
+//! Phases are recorded in a process-wide registry rather than threaded through every subcommand,
+//! since they happen deep inside the shared lookup code. Nothing is recorded unless enabled.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Phase {
+    /// Running `cargo metadata`, or reading its equivalent from a file or a lockfile
+    Metadata,
+    /// Opening the cached daily dump and API responses, and looking up crates in them
+    CacheLoad,
+    /// Looking up crates missing from the cache through the crates.io API
+    ApiFetches,
+    /// Downloading the daily dump in the `update` subcommand
+    DumpDownload,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [
+        Phase::Metadata,
+        Phase::CacheLoad,
+        Phase::ApiFetches,
+        Phase::DumpDownload,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Metadata => "cargo metadata",
+            Phase::CacheLoad => "cache load",
+            Phase::ApiFetches => "API fetches",
+            Phase::DumpDownload => "dump download",
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECORDED: Mutex<Vec<(Phase, Duration)>> = Mutex::new(Vec::new());
+
+/// Starts recording the time spent in each phase
+#[doc(hidden)]
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Runs `f`, adding the time it takes to `phase`. A phase may be entered several times.
+pub(crate) fn measure<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    record(phase, start.elapsed());
+    result
+}
+
+/// Adds `duration` to `phase`, for phases that don't fit in a single closure
+pub(crate) fn record(phase: Phase, duration: Duration) {
+    if ENABLED.load(Ordering::Relaxed) {
+        RECORDED.lock().unwrap().push((phase, duration));
+    }
+}
+
+/// Prints the time spent in each phase that was entered, if enabled.
+/// Whatever is left of `total` is attributed to rendering the output and everything else.
+#[doc(hidden)]
+pub fn report(total: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let recorded = RECORDED.lock().unwrap();
+    eprintln!("\nTiming:");
+    let mut measured = Duration::ZERO;
+    for phase in Phase::ALL {
+        let durations: Vec<Duration> = recorded
+            .iter()
+            .filter(|(p, _)| *p == phase)
+            .map(|(_, duration)| *duration)
+            .collect();
+        if durations.is_empty() {
+            continue;
+        }
+        let spent: Duration = durations.iter().sum();
+        measured += spent;
+        eprintln!("  {:<16}{:>10.3}s", phase.name(), spent.as_secs_f64());
+    }
+    eprintln!(
+        "  {:<16}{:>10.3}s",
+        "rendering, other",
+        total.saturating_sub(measured).as_secs_f64()
+    );
+    eprintln!("  {:<16}{:>10.3}s", "total", total.as_secs_f64());
+}
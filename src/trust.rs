@@ -0,0 +1,163 @@
+//! Publishers that have already been reviewed, loaded from `supply-chain-trust.toml`.
+//!
+//! The file lists GitHub logins of users and crates.io names of teams:
+//!
+//! ```toml
+//! users = ["dtolnay"]
+//! teams = ["github:rust-lang:libs"]
+//! ```
+
+use crate::common::{paint, Color};
+use crate::publishers::{PublisherData, PublisherKind};
+use crate::Error;
+use serde::Deserialize;
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+/// Looked up in the current directory unless `--trust-file` is specified
+pub const DEFAULT_TRUST_FILE: &str = "supply-chain-trust.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustList {
+    #[serde(default)]
+    users: BTreeSet<String>,
+    #[serde(default)]
+    teams: BTreeSet<String>,
+    /// Whether `annotate` also highlights trusted publishers in color
+    #[serde(skip)]
+    color: bool,
+}
+
+impl TrustList {
+    /// Loads the trust file from `path`, or from `DEFAULT_TRUST_FILE` if `path` is `None`.
+    /// A missing default file results in an empty list, but a missing explicitly specified file is an error.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let (path, explicit) = match path {
+            Some(path) => (path, true),
+            None => (Path::new(DEFAULT_TRUST_FILE), false),
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => {
+                return Ok(TrustList::default())
+            }
+            Err(e) => {
+                return Err(Error::Io(io::Error::new(
+                    e.kind(),
+                    format!("Failed to read trust file {}: {}", path.display(), e),
+                )))
+            }
+        };
+        toml::from_str(&contents)
+            .map_err(|e| Error::Parse(format!("Invalid trust file {}: {}", path.display(), e)))
+    }
+
+    pub fn is_trusted(&self, publisher: &PublisherData) -> bool {
+        match publisher.kind {
+            PublisherKind::user => self.users.contains(&publisher.login),
+            PublisherKind::team => self.teams.contains(&publisher.login),
+        }
+    }
+
+    /// Highlights trusted publishers in green in the output of `annotate`
+    pub fn with_color(self, color: bool) -> Self {
+        TrustList { color, ..self }
+    }
+
+    /// Appends a marker to the login of trusted publishers, for display purposes
+    pub fn annotate(&self, publisher: &PublisherData, login: String) -> String {
+        if self.is_trusted(publisher) {
+            paint(format!("{} (trusted)", login), Color::Green, self.color)
+        } else {
+            login
+        }
+    }
+}
+
+/// GitHub organizations and teams given with `--first-party-orgs`, whose crates are the user's
+/// own code rather than the internet's. Each is `ORG`, `github:ORG` or `github:ORG:TEAM`,
+/// and organization names are compared case-insensitively, like on GitHub.
+#[derive(Debug, Clone, Default)]
+pub struct FirstPartyOrgs {
+    /// Organizations along with the team, if only one of their teams is first-party
+    orgs: Vec<(String, Option<String>)>,
+}
+
+impl FirstPartyOrgs {
+    /// Fails on specifications that name no organization
+    pub fn parse(specs: &[String]) -> Result<Self, Error> {
+        let orgs = specs
+            .iter()
+            .map(|spec| {
+                let rest = spec.strip_prefix("github:").unwrap_or(spec);
+                let (org, team) = match rest.split_once(':') {
+                    Some((org, team)) => (org, Some(team.to_ascii_lowercase())),
+                    None => (rest, None),
+                };
+                if org.is_empty() || team.as_deref() == Some("") {
+                    return Err(Error::InvalidInput(format!(
+                        "--first-party-orgs {}: expected ORG, github:ORG or github:ORG:TEAM",
+                        spec
+                    )));
+                }
+                Ok((org.to_ascii_lowercase(), team))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(FirstPartyOrgs { orgs })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orgs.is_empty()
+    }
+
+    /// Whether `publisher` is one of the teams, or a team of the organizations
+    pub fn owns(&self, publisher: &PublisherData) -> bool {
+        if publisher.kind != PublisherKind::team {
+            return false;
+        }
+        let login = publisher.login.to_ascii_lowercase();
+        let (org, team) = match login.split(':').collect::<Vec<_>>()[..] {
+            ["github", org, team] => (org, team),
+            _ => return false,
+        };
+        self.orgs.iter().any(|(first_party_org, first_party_team)| {
+            first_party_org == org && first_party_team.as_deref().is_none_or(|t| t == team)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publisher(kind: PublisherKind, login: &str) -> PublisherData {
+        PublisherData {
+            id: 1,
+            login: login.to_owned(),
+            kind,
+            name: None,
+            avatar: None,
+            contact: None,
+            total_crates: None,
+        }
+    }
+
+    #[test]
+    fn first_party_orgs_cover_their_teams_regardless_of_case() {
+        let orgs = FirstPartyOrgs::parse(&["Acme".to_owned(), "github:rust-lang:libs".to_owned()])
+            .unwrap();
+        assert!(orgs.owns(&publisher(PublisherKind::team, "github:acme:core")));
+        assert!(orgs.owns(&publisher(PublisherKind::team, "github:rust-lang:Libs")));
+        assert!(!orgs.owns(&publisher(PublisherKind::team, "github:rust-lang:compiler")));
+        assert!(!orgs.owns(&publisher(PublisherKind::user, "acme")));
+        assert!(FirstPartyOrgs::parse(&["github:acme:".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_in_the_trust_file_are_an_error() {
+        assert!(toml::from_str::<TrustList>("user = [\"dtolnay\"]").is_err());
+        let list: TrustList = toml::from_str("users = [\"dtolnay\"]").unwrap();
+        assert!(list.is_trusted(&publisher(PublisherKind::user, "dtolnay")));
+        assert!(!list.is_trusted(&publisher(PublisherKind::team, "dtolnay")));
+    }
+}
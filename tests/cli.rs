@@ -0,0 +1,979 @@
+//! Runs the `cargo supply-chain` binary on the workspaces in `tests/fixtures`,
+//! without network access: everything is read from a cache written by the tests.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    time::{Duration, SystemTime},
+};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+        .join("Cargo.toml")
+}
+
+/// Creates a cache directory of its own for each test, holding a dump generated now
+/// with these crates, each published by a user of the same name
+fn offline_cache(test: &str, crates: &[&str]) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(test);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let timestamp = humantime::format_rfc3339(SystemTime::now());
+    let mut crates_json = serde_json::Map::new();
+    let mut owners_json = serde_json::Map::new();
+    let mut users_json = serde_json::Map::new();
+    for (id, name) in (1..).zip(crates) {
+        crates_json.insert(
+            name.to_string(),
+            serde_json::json!({ "name": name, "id": id, "repository": null }),
+        );
+        owners_json.insert(
+            id.to_string(),
+            serde_json::json!([{ "crate_id": id, "owner_id": id, "owner_kind": 0 }]),
+        );
+        users_json.insert(
+            id.to_string(),
+            serde_json::json!({ "id": id, "gh_avatar": null, "gh_id": null, "gh_login": name, "name": null }),
+        );
+    }
+    let files = [
+        (
+            "metadata.json",
+            serde_json::json!({ "timestamp": timestamp.to_string() }),
+        ),
+        ("crates.json", crates_json.into()),
+        ("crate_owners.json", owners_json.into()),
+        ("users.json", users_json.into()),
+        ("teams.json", serde_json::json!({})),
+        ("yanked_versions.json", serde_json::json!({})),
+        ("last_published.json", serde_json::json!({})),
+    ];
+    for (file, contents) in &files {
+        fs::write(dir.join(file), contents.to_string()).unwrap();
+    }
+    dir
+}
+
+fn run_offline(cache: &Path, args: &[&str]) -> Output {
+    run_offline_in(Path::new(env!("CARGO_MANIFEST_DIR")), cache, args)
+}
+
+/// Runs in `dir`, to pick up the `supply-chain.toml` there.
+/// The subcommand comes first in `args`, and the flags are added right after it,
+/// so that they are not passed to cargo when `args` ends with `--` and its arguments.
+fn run_offline_in(dir: &Path, cache: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cargo-supply-chain"))
+        .args(&args[..1])
+        .args(["--offline", "--quiet"])
+        .args(&args[1..])
+        .current_dir(dir)
+        .env("CARGO_SUPPLY_CHAIN_CACHE", cache)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn crates_in_the_cache_are_listed_offline() {
+    let cache = offline_cache(
+        "crates_in_the_cache_are_listed_offline",
+        &["cfg-if", "itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let output = run_offline(
+        &cache,
+        &["crates", "--manifest-path", manifest.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains("cfg-if"), "{}", stdout);
+    assert!(stdout.contains("itoa"), "{}", stdout);
+}
+
+#[test]
+fn crates_missing_from_the_cache_offline_exit_with_code_4() {
+    let cache = offline_cache(
+        "crates_missing_from_the_cache_offline_exit_with_code_4",
+        &[],
+    );
+    let manifest = fixture("registry-dep");
+    let output = run_offline(
+        &cache,
+        &["crates", "--manifest-path", manifest.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    assert!(stderr(&output).contains("cfg-if"), "{:?}", output);
+}
+
+#[test]
+fn the_first_failed_lookup_ends_the_run() {
+    let cache = offline_cache("the_first_failed_lookup_ends_the_run", &["itoa"]);
+    let manifest = fixture("registry-dep");
+    let output = run_offline(
+        &cache,
+        &["crates", "--manifest-path", manifest.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    assert_eq!(stdout(&output), "");
+    assert!(
+        stderr(&output).contains("Failed to fetch publishers of 1 crate:\n  cfg-if: "),
+        "{:?}",
+        output
+    );
+}
+
+#[test]
+fn keep_going_prints_the_other_crates_then_the_failures() {
+    let cache = offline_cache(
+        "keep_going_prints_the_other_crates_then_the_failures",
+        &["itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let output = run_offline(
+        &cache,
+        &[
+            "publishers",
+            "--keep-going",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    assert_eq!(stdout(&output), "user \"itoa\": itoa\n");
+    assert!(
+        stderr(&output).contains("Failed to fetch publishers of 1 crate:\n  cfg-if: "),
+        "{:?}",
+        output
+    );
+}
+
+#[test]
+fn output_gets_what_stdout_would_even_if_the_run_fails() {
+    let cache = offline_cache(
+        "output_gets_what_stdout_would_even_if_the_run_fails",
+        &["itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let args = [
+        "crates",
+        "--keep-going",
+        "--manifest-path",
+        manifest.to_str().unwrap(),
+    ];
+    let printed = run_offline(&cache, &args);
+    let path = cache.join("output.txt");
+    let written = run_offline(
+        &cache,
+        &[&args[..], &["--output", path.to_str().unwrap()]].concat(),
+    );
+    assert_eq!(written.status.code(), Some(4), "{:?}", written);
+    assert_eq!(stdout(&written), "");
+    assert_eq!(fs::read_to_string(&path).unwrap(), stdout(&printed));
+    assert!(stdout(&printed).contains("itoa"), "{:?}", printed);
+}
+
+/// Runs a subcommand, given with its arguments, on a fixture whose crates.io dependencies
+/// are all in the cache
+fn stdout_of(test: &str, fixture_name: &str, args: &[&str]) -> String {
+    let cache = offline_cache(test, &["cfg-if", "itoa"]);
+    let manifest = fixture(fixture_name);
+    let output = run_offline(
+        &cache,
+        &[args, &["--manifest-path", manifest.to_str().unwrap()]].concat(),
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    stdout(&output)
+}
+
+fn crates_of(test: &str, fixture_name: &str, args: &[&str]) -> String {
+    stdout_of(test, fixture_name, &[&["crates"], args].concat())
+}
+
+#[test]
+fn optional_dependencies_are_left_out_unless_activated() {
+    let stdout = crates_of(
+        "optional_dependencies_are_left_out_unless_activated",
+        "optional-dep",
+        &[],
+    );
+    assert!(stdout.contains("cfg-if"), "{}", stdout);
+    assert!(!stdout.contains("itoa"), "{}", stdout);
+}
+
+#[test]
+fn optional_dependencies_activated_by_all_features_are_included() {
+    let stdout = crates_of(
+        "optional_dependencies_activated_by_all_features_are_included",
+        "optional-dep",
+        &["--all-features"],
+    );
+    assert!(stdout.contains("cfg-if"), "{}", stdout);
+    assert!(stdout.contains("itoa"), "{}", stdout);
+}
+
+#[test]
+fn optional_dependencies_activated_by_features_are_included() {
+    let stdout = crates_of(
+        "optional_dependencies_activated_by_features_are_included",
+        "optional-dep",
+        &["--features", "numbers"],
+    );
+    assert!(stdout.contains("cfg-if"), "{}", stdout);
+    assert!(stdout.contains("itoa"), "{}", stdout);
+}
+
+#[test]
+fn a_target_only_includes_its_own_dependencies() {
+    let stdout = crates_of(
+        "a_target_only_includes_its_own_dependencies",
+        "platform-deps",
+        &["--target", "x86_64-pc-windows-msvc"],
+    );
+    assert!(stdout.contains("itoa"), "{}", stdout);
+    assert!(!stdout.contains("cfg-if"), "{}", stdout);
+}
+
+#[test]
+fn several_targets_include_the_dependencies_of_any_of_them() {
+    let stdout = crates_of(
+        "several_targets_include_the_dependencies_of_any_of_them",
+        "platform-deps",
+        &[
+            "--target",
+            "x86_64-pc-windows-msvc",
+            "--target",
+            "x86_64-unknown-linux-gnu",
+        ],
+    );
+    assert!(stdout.contains("itoa"), "{}", stdout);
+    assert!(stdout.contains("cfg-if"), "{}", stdout);
+}
+
+#[test]
+fn offline_runs_use_api_responses_however_old() {
+    // cfg-if is in the dump, and itoa was looked up on crates.io by a run a year ago
+    let cache = offline_cache("offline_runs_use_api_responses_however_old", &["cfg-if"]);
+    let fetched = SystemTime::now() - Duration::from_secs(365 * 24 * 3600);
+    let api_responses = serde_json::json!({
+        "itoa": {
+            "fetched": humantime::format_rfc3339(fetched).to_string(),
+            "users": [{ "id": 2, "login": "itoa", "kind": "user", "name": null, "avatar": null }],
+            "teams": [],
+        }
+    });
+    fs::write(cache.join("api_responses.json"), api_responses.to_string()).unwrap();
+    let manifest = fixture("registry-dep");
+    let output = run_offline(
+        &cache,
+        &["crates", "--manifest-path", manifest.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains("cfg-if"), "{}", stdout);
+    assert!(stdout.contains("itoa"), "{}", stdout);
+}
+
+#[test]
+fn toml_snapshots_can_be_diffed_against_json_ones() {
+    let cache = offline_cache(
+        "toml_snapshots_can_be_diffed_against_json_ones",
+        &["cfg-if", "itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let json_path = cache.join("snapshot.json");
+    let toml_path = cache.join("snapshot.toml");
+    for (format, path) in [("json", &json_path), ("toml", &toml_path)] {
+        let output = run_offline(
+            &cache,
+            &[
+                "json",
+                "--format",
+                format,
+                "--output",
+                path.to_str().unwrap(),
+                "--manifest-path",
+                manifest.to_str().unwrap(),
+            ],
+        );
+        assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    }
+    let snapshot: toml::Value = fs::read_to_string(&toml_path).unwrap().parse().unwrap();
+    assert!(
+        snapshot["crates_io_crates"].get("cfg-if").is_some(),
+        "{}",
+        snapshot
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-supply-chain"))
+        .args([
+            "diff".as_ref(),
+            json_path.as_os_str(),
+            toml_path.as_os_str(),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "No crates.io crates or publishers were added or removed.\n"
+    );
+}
+
+#[test]
+fn the_command_line_replaces_the_feature_selection_of_the_config_file() {
+    let cache = offline_cache(
+        "the_command_line_replaces_the_feature_selection_of_the_config_file",
+        &["cfg-if", "itoa"],
+    );
+    fs::write(cache.join("supply-chain.toml"), "all-features = true\n").unwrap();
+    let manifest = fixture("optional-dep");
+    let args = ["crates", "--manifest-path", manifest.to_str().unwrap()];
+    let from_file = run_offline_in(&cache, &cache, &args);
+    assert_eq!(from_file.status.code(), Some(0), "{:?}", from_file);
+    assert!(stdout(&from_file).contains("itoa"), "{:?}", from_file);
+    let overridden = run_offline_in(
+        &cache,
+        &cache,
+        &[&args[..], &["--default-features"]].concat(),
+    );
+    assert_eq!(overridden.status.code(), Some(0), "{:?}", overridden);
+    assert!(!stdout(&overridden).contains("itoa"), "{:?}", overridden);
+}
+
+#[test]
+fn dev_dependencies_are_left_out_by_default() {
+    let default = crates_of("dev_dependencies_are_left_out_by_default", "dev-dep", &[]);
+    assert!(default.contains("cfg-if"), "{}", default);
+    assert!(!default.contains("itoa"), "{}", default);
+    let included = crates_of(
+        "dev_dependencies_are_left_out_by_default",
+        "dev-dep",
+        &["--include-dev-dependencies"],
+    );
+    assert!(included.contains("itoa"), "{}", included);
+}
+
+#[test]
+fn exclude_dev_dependencies_is_deprecated() {
+    let cache = offline_cache(
+        "exclude_dev_dependencies_is_deprecated",
+        &["cfg-if", "itoa"],
+    );
+    let manifest = fixture("dev-dep");
+    let output = run_offline(
+        &cache,
+        &[
+            "crates",
+            "--exclude-dev-dependencies",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert!(!stdout(&output).contains("itoa"), "{:?}", output);
+    assert!(
+        stderr(&output).contains("--exclude-dev-dependencies is deprecated"),
+        "{:?}",
+        output
+    );
+}
+
+#[test]
+fn the_library_looks_crates_up_through_the_given_client() {
+    let cache = offline_cache(
+        "the_library_looks_crates_up_through_the_given_client",
+        &["cfg-if"],
+    );
+    // Only read by this process, since the binary is given the cache explicitly
+    std::env::set_var("CARGO_SUPPLY_CHAIN_CACHE", &cache);
+    let metadata_args = cargo_supply_chain::MetadataArgs {
+        manifest_paths: vec![fixture("registry-dep")],
+        offline: true,
+        ..Default::default()
+    };
+    let max_age = Duration::from_secs(3600);
+    let client = cargo_supply_chain::RateLimitedClient::new()
+        .with_offline(true)
+        .with_keep_going(true);
+    match cargo_supply_chain::resolve_publishers(&metadata_args, &client, max_age) {
+        Err(cargo_supply_chain::Error::LookupsFailed(errors)) => {
+            let failed: Vec<&str> = errors.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(failed, ["itoa"]);
+        }
+        result => panic!("{:?}", result),
+    }
+    let cache = offline_cache(
+        "the_library_looks_crates_up_through_the_given_client",
+        &["cfg-if", "itoa"],
+    );
+    std::env::set_var("CARGO_SUPPLY_CHAIN_CACHE", &cache);
+    let crates = cargo_supply_chain::resolve_publishers(&metadata_args, &client, max_age).unwrap();
+    let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["cfg-if", "itoa"]);
+    assert_eq!(crates[1].publishers[0].login, "itoa");
+    assert_eq!(crates[1].versions, ["0.4.8"]);
+}
+
+#[test]
+fn categories_are_read_from_the_manifests() {
+    let stdout = crates_of(
+        "categories_are_read_from_the_manifests",
+        "registry-dep",
+        &["--category", "value-formatting"],
+    );
+    assert!(stdout.contains("itoa"), "{}", stdout);
+    assert!(!stdout.contains("cfg-if"), "{}", stdout);
+}
+
+#[test]
+fn risks_count_a_single_dependent_in_the_singular() {
+    let cache = offline_cache(
+        "risks_count_a_single_dependent_in_the_singular",
+        &["cfg-if", "itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let output = run_offline(
+        &cache,
+        &["risks", "--manifest-path", manifest.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "cfg-if: cfg-if, 1 dependent\nitoa: itoa, 1 dependent\n"
+    );
+}
+
+#[test]
+fn subcommands_not_querying_the_graph_reject_query_options() {
+    for command in ["update", "clean", "diff"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_cargo-supply-chain"))
+            .args([command, "--quiet", "--target", "x86_64-unknown-linux-gnu"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3), "{:?}", output);
+        assert!(
+            stderr(&output).starts_with(&format!(
+                "Error: The {} subcommand doesn't accept --quiet, --target\n",
+                command
+            )),
+            "{:?}",
+            output
+        );
+    }
+}
+
+#[test]
+fn stats_count_the_crates_of_the_top_publisher_in_the_singular() {
+    let stdout = stdout_of(
+        "stats_count_the_crates_of_the_top_publisher_in_the_singular",
+        "registry-dep",
+        &["stats"],
+    );
+    assert_eq!(
+        stdout,
+        "Total crates: 3\nCrates from crates.io: 2\nDistinct publishers: 2\nDistinct teams: 0\n\
+         Single-owner crates: 2\nPublisher of the most crates: user \"cfg-if\" (1 crate)\n"
+    );
+}
+
+#[test]
+fn crates_can_be_listed_as_csv() {
+    let stdout = crates_of(
+        "crates_can_be_listed_as_csv",
+        "registry-dep",
+        &["--format", "csv"],
+    );
+    assert_eq!(
+        stdout,
+        "crate,version,publishers,publisher_kinds,yanked,lookup_error\r\n\
+         cfg-if,1.0.5,cfg-if,user,false,\r\n\
+         itoa,0.4.8,itoa,user,false,\r\n"
+    );
+}
+
+#[test]
+fn crates_can_be_listed_as_a_markdown_table() {
+    let stdout = crates_of(
+        "crates_can_be_listed_as_a_markdown_table",
+        "registry-dep",
+        &["--format", "markdown"],
+    );
+    assert!(
+        stdout.starts_with(
+            "| Crate | Versions | Publishers |\n\
+             | --- | --- | --- |\n\
+             | cfg-if | 1.0.5 | `cfg-if` |\n\
+             | itoa | 0.4.8 | `itoa` |\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("| registry-dep | path |\n"), "{}", stdout);
+}
+
+#[test]
+fn risks_point_at_the_manifest_lines_in_sarif() {
+    let stdout = stdout_of(
+        "risks_point_at_the_manifest_lines_in_sarif",
+        "registry-dep",
+        &["risks", "--format", "sarif"],
+    );
+    let sarif: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    let found: Vec<(&str, &str, u64)> = results
+        .iter()
+        .map(|result| {
+            let location = &result["locations"][0]["physicalLocation"];
+            (
+                result["ruleId"].as_str().unwrap(),
+                location["artifactLocation"]["uri"].as_str().unwrap(),
+                location["region"]["startLine"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("single-owner", "Cargo.toml", 8),
+            ("single-owner", "Cargo.toml", 9)
+        ]
+    );
+    assert!(
+        results[1]["message"]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("`itoa` can be published by a single individual, itoa,"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn the_html_report_has_a_section_per_publisher() {
+    let stdout = stdout_of(
+        "the_html_report_has_a_section_per_publisher",
+        "registry-dep",
+        &["json", "--format", "html"],
+    );
+    assert!(stdout.starts_with("<!DOCTYPE html>\n"), "{}", stdout);
+    assert!(stdout.trim_end().ends_with("</html>"), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "<tr class=\"single-owner\"><td><a href=\"https://crates.io/crates/itoa\">itoa</a></td>"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(
+            "<details><summary>user <a href=\"https://crates.io/users/itoa\">itoa</a> &mdash; 1 crate</summary>\n\
+             <ul>\n<li>itoa</li>\n</ul>\n</details>\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("<li>registry-dep (local)</li>"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn the_dot_graph_links_each_crate_to_its_publishers() {
+    let stdout = stdout_of(
+        "the_dot_graph_links_each_crate_to_its_publishers",
+        "registry-dep",
+        &["json", "--format", "dot"],
+    );
+    assert!(stdout.starts_with("digraph supply_chain {\n"), "{}", stdout);
+    assert!(stdout.ends_with("}\n"), "{}", stdout);
+    for line in [
+        "  \"crate:itoa\" [label=\"itoa\", shape=box, fillcolor=\"#f4f4f4\"];\n",
+        "  \"user:itoa\" [label=\"itoa\\n1 crate\", shape=ellipse, fillcolor=\"#cfe2f3\"];\n",
+        "  \"crate:cfg-if\" -> \"user:cfg-if\";\n",
+        "  \"crate:itoa\" -> \"user:itoa\";\n",
+    ] {
+        assert!(stdout.contains(line), "{}", stdout);
+    }
+    assert!(!stdout.contains("registry-dep"), "{}", stdout);
+}
+
+#[test]
+fn the_cyclonedx_sbom_lists_publishers_of_each_component() {
+    let stdout = stdout_of(
+        "the_cyclonedx_sbom_lists_publishers_of_each_component",
+        "registry-dep",
+        &["json", "--format", "cyclonedx"],
+    );
+    let bom: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(bom["bomFormat"], "CycloneDX");
+    let components = bom["components"].as_array().unwrap();
+    let purls: Vec<&str> = components
+        .iter()
+        .map(|component| component["purl"].as_str().unwrap())
+        .collect();
+    assert_eq!(purls, ["pkg:cargo/cfg-if@1.0.5", "pkg:cargo/itoa@0.4.8"]);
+    assert!(
+        components[1]["properties"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!({
+                "name": "cargo-supply-chain:publisher:user",
+                "value": "itoa"
+            })),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn a_lockfile_includes_every_locked_crate() {
+    let cache = offline_cache(
+        "a_lockfile_includes_every_locked_crate",
+        &["cfg-if", "itoa"],
+    );
+    // itoa is only pulled in by a feature the manifest doesn't enable by default
+    let lockfile = fixture("optional-dep").with_file_name("Cargo.lock");
+    let output = run_offline(
+        &cache,
+        &["crates", "--lockfile", lockfile.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "cfg-if: cfg-if\nitoa: itoa\noptional-dep: not from crates.io (path)\n"
+    );
+}
+
+#[test]
+fn publishers_in_the_trust_file_can_be_left_out() {
+    let cache = offline_cache(
+        "publishers_in_the_trust_file_can_be_left_out",
+        &["cfg-if", "itoa"],
+    );
+    fs::write(
+        cache.join("supply-chain-trust.toml"),
+        "users = [\"itoa\"]\n",
+    )
+    .unwrap();
+    let manifest = fixture("registry-dep");
+    let output = run_offline_in(
+        &cache,
+        &cache,
+        &[
+            "publishers",
+            "--untrusted-only",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert_eq!(stdout(&output), "user \"cfg-if\": cfg-if\n");
+    let missing = cache.join("missing-trust.toml");
+    let output = run_offline_in(
+        &cache,
+        &cache,
+        &[
+            "publishers",
+            "--trust-file",
+            missing.to_str().unwrap(),
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert!(
+        stderr(&output).contains("Failed to read trust file"),
+        "{:?}",
+        output
+    );
+}
+
+#[test]
+fn diff_lists_the_crates_and_publishers_added_since_a_snapshot() {
+    let cache = offline_cache(
+        "diff_lists_the_crates_and_publishers_added_since_a_snapshot",
+        &["cfg-if", "itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let before = cache.join("before.json");
+    let after = cache.join("after.json");
+    for (path, exclude) in [(&before, "itoa"), (&after, "none")] {
+        let output = run_offline(
+            &cache,
+            &[
+                "json",
+                "--exclude",
+                exclude,
+                "--output",
+                path.to_str().unwrap(),
+                "--manifest-path",
+                manifest.to_str().unwrap(),
+            ],
+        );
+        assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    }
+    let diff = |old: &Path, new: &Path| {
+        Command::new(env!("CARGO_BIN_EXE_cargo-supply-chain"))
+            .args(["diff".as_ref(), old.as_os_str(), new.as_os_str()])
+            .output()
+            .unwrap()
+    };
+    let added = diff(&before, &after);
+    assert_eq!(added.status.code(), Some(0), "{:?}", added);
+    assert_eq!(
+        stdout(&added),
+        "Added crates:\n + itoa: user \"itoa\"\n\
+         Added publishers:\n + user \"itoa\" via crates: itoa\n"
+    );
+    let removed = diff(&after, &before);
+    assert_eq!(removed.status.code(), Some(0), "{:?}", removed);
+    assert!(stdout(&removed).contains(" - itoa"), "{:?}", removed);
+}
+
+#[test]
+fn verify_fails_on_publishers_missing_from_the_expectations() {
+    let cache = offline_cache(
+        "verify_fails_on_publishers_missing_from_the_expectations",
+        &["cfg-if", "itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let expectations = cache.join("expectations.json");
+    let verify = |args: &[&str]| {
+        run_offline(
+            &cache,
+            &[
+                &["verify"][..],
+                args,
+                &[
+                    expectations.to_str().unwrap(),
+                    "--manifest-path",
+                    manifest.to_str().unwrap(),
+                ],
+            ]
+            .concat(),
+        )
+    };
+    let created = verify(&["--update-expectations"]);
+    assert_eq!(created.status.code(), Some(0), "{:?}", created);
+    assert!(expectations.exists());
+    let unchanged = verify(&[]);
+    assert_eq!(unchanged.status.code(), Some(0), "{:?}", unchanged);
+
+    // Someone else now owns itoa. The first run compressed the tables, so they are
+    // replaced with uncompressed ones to be compressed again
+    let owners = serde_json::json!({
+        "1": [{ "crate_id": 1, "owner_id": 1, "owner_kind": 0 }],
+        "2": [{ "crate_id": 2, "owner_id": 3, "owner_kind": 0 }],
+    });
+    let users = serde_json::json!({
+        "1": { "id": 1, "gh_avatar": null, "gh_id": null, "gh_login": "cfg-if", "name": null },
+        "3": { "id": 3, "gh_avatar": null, "gh_id": null, "gh_login": "newcomer", "name": null },
+    });
+    for (file, contents) in [("crate_owners.json", owners), ("users.json", users)] {
+        fs::remove_file(cache.join(format!("{}.gz", file))).unwrap();
+        fs::write(cache.join(file), contents.to_string()).unwrap();
+    }
+    let changed = verify(&[]);
+    assert_eq!(changed.status.code(), Some(2), "{:?}", changed);
+    assert!(
+        stdout(&changed).contains(" + user \"newcomer\" via crates: itoa\n"),
+        "{:?}",
+        changed
+    );
+    assert!(
+        stderr(&changed).contains("Found 1 publisher not listed in"),
+        "{:?}",
+        changed
+    );
+}
+
+#[test]
+fn cargo_metadata_arguments_can_be_read_from_a_response_file() {
+    let cache = offline_cache(
+        "cargo_metadata_arguments_can_be_read_from_a_response_file",
+        &["cfg-if", "itoa"],
+    );
+    let response_file = cache.join("metadata-args");
+    fs::write(
+        &response_file,
+        "# Only what Linux builds\n\n--filter-platform\nx86_64-unknown-linux-gnu\n",
+    )
+    .unwrap();
+    let manifest = fixture("platform-deps");
+    let output = run_offline(
+        &cache,
+        &[
+            "crates",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--",
+            &format!("@{}", response_file.display()),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains("cfg-if"), "{}", stdout);
+    assert!(!stdout.contains("itoa"), "{}", stdout);
+}
+
+#[test]
+fn ndjson_has_a_line_per_crate() {
+    let stdout = stdout_of(
+        "ndjson_has_a_line_per_crate",
+        "registry-dep",
+        &["json", "--format", "ndjson"],
+    );
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let names: Vec<&str> = lines
+        .iter()
+        .map(|line| line["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["cfg-if", "itoa"]);
+    assert_eq!(lines[1]["versions"], serde_json::json!(["0.4.8"]));
+    assert_eq!(lines[1]["publishers"][0]["login"], "itoa");
+}
+
+#[test]
+fn the_dependency_graph_can_be_read_from_a_metadata_file() {
+    let cache = offline_cache(
+        "the_dependency_graph_can_be_read_from_a_metadata_file",
+        &["cfg-if", "itoa"],
+    );
+    let metadata = Command::new(env!("CARGO"))
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--offline",
+            "--manifest-path",
+        ])
+        .arg(fixture("registry-dep"))
+        .output()
+        .unwrap();
+    assert!(metadata.status.success(), "{:?}", metadata);
+    let path = cache.join("metadata.out.json");
+    fs::write(&path, &metadata.stdout).unwrap();
+    let output = run_offline(
+        &cache,
+        &["crates", "--metadata-file", path.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "cfg-if: cfg-if\nitoa: itoa\nregistry-dep: not from crates.io (path)\n"
+    );
+}
+
+#[test]
+fn a_graph_left_empty_by_the_filters_exits_with_code_5() {
+    let cache = offline_cache(
+        "a_graph_left_empty_by_the_filters_exits_with_code_5",
+        &["cfg-if", "itoa"],
+    );
+    let manifest = fixture("registry-dep");
+    let output = run_offline(
+        &cache,
+        &[
+            "crates",
+            "--exclude",
+            "*",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(5), "{:?}", output);
+    assert_eq!(stdout(&output), "");
+    assert!(
+        stderr(&output).contains("has no crates.io crates to analyze"),
+        "{:?}",
+        output
+    );
+}
+
+#[test]
+fn why_shows_the_path_to_a_crate_and_its_publishers() {
+    let stdout = stdout_of(
+        "why_shows_the_path_to_a_crate_and_its_publishers",
+        "registry-dep",
+        &["why", "itoa"],
+    );
+    assert_eq!(
+        stdout,
+        "itoa v0.4.8\n└── registry-dep v0.1.0 (workspace member)\nitoa: user \"itoa\"\n"
+    );
+}
+
+#[test]
+fn crates_can_be_filtered_by_license() {
+    let cache = offline_cache("crates_can_be_filtered_by_license", &["cfg-if", "itoa"]);
+    let manifest = fixture("registry-dep");
+    let crates_with_license = |pattern: &str| {
+        run_offline(
+            &cache,
+            &[
+                "crates",
+                "--license",
+                pattern,
+                "--manifest-path",
+                manifest.to_str().unwrap(),
+            ],
+        )
+    };
+    let apache = crates_with_license("*Apache*");
+    assert_eq!(apache.status.code(), Some(0), "{:?}", apache);
+    assert!(stdout(&apache).contains("cfg-if"), "{:?}", apache);
+    assert!(stdout(&apache).contains("itoa"), "{:?}", apache);
+    // Both are dual-licensed, so nothing from crates.io is left
+    let not_dual = crates_with_license("!MIT OR Apache-2.0");
+    assert_eq!(not_dual.status.code(), Some(5), "{:?}", not_dual);
+}
+
+#[test]
+fn logins_only_prints_one_publisher_per_line() {
+    let stdout = stdout_of(
+        "logins_only_prints_one_publisher_per_line",
+        "registry-dep",
+        &["publishers", "--logins-only"],
+    );
+    assert_eq!(stdout, "cfg-if\nitoa\n");
+}
+
+#[test]
+fn several_manifests_are_analyzed_together() {
+    let cache = offline_cache(
+        "several_manifests_are_analyzed_together",
+        &["cfg-if", "itoa"],
+    );
+    let (registry_dep, platform_deps) = (fixture("registry-dep"), fixture("platform-deps"));
+    let output = run_offline(
+        &cache,
+        &[
+            "crates",
+            "--manifest-path",
+            registry_dep.to_str().unwrap(),
+            "--manifest-path",
+            platform_deps.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "cfg-if: cfg-if\nitoa: itoa\n\
+         platform-deps: not from crates.io (path)\n\
+         registry-dep: not from crates.io (path)\n"
+    );
+}